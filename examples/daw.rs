@@ -2,25 +2,37 @@
 //! Sticky header at top lists tracks and draws live level meters (ASCII).
 //!
 //! Run with: `cargo run --example daw`
+//! Replay a session: `cargo run --example daw -- --script session.txt` (one command per line,
+//! `sleep <ms>` to pause, `#` for comments).
+//! Headphone-safe start: `cargo run --example daw -- --safe-mode` (master capped at -12 dBFS).
+//! Monitors on other outputs: `cargo run --example daw -- --out-channels 3,4` (1-based).
+//! Events as JSON lines on stdout (TUI on stderr):
+//! `cargo run --example daw --features serde -- --json-events > events.jsonl`.
+//! Start on a JSON graph patch (format in `capstan::patch`):
+//! `cargo run --example daw --features serde -- --patch patch.json`.
 //!
 //! Structure:
-//! - Types: CLI, track/source, OpenInputs, Session, StatusKind
+//! - Types: CLI, track/source, InputDevices, OpenInputs, Session, Daw, StatusKind
 //! - Graph: build_session_graph, send_graph
-//! - Paths: recording_path, expand_tilde
+//! - Paths: recording_path, bounce_path, expand_tilde
 //! - UI: draw_header, draw_history, meter helpers
-//! - Commands: parse_track_no, handle_command
+//! - Commands: parse_track_no, handle_command, Daw::dispatch
+//! - Scripts: parse_script
+//! - Patches: parse_patch
 //! - Recording: stop_recording_and_save
+//! - Bounce: parse_bounce_options, bounce_session
 //! - Main: event loop
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use capstan::command::{command_channel, Command};
-use capstan::device::{input_device_list, open_input_stream, DeviceError};
+use capstan::device::{input_device_list, open_input_stream, DeviceError, InputDeviceInfo};
 use capstan::event::event_channel;
 use capstan::file_feeder::load_wav_at_rate;
 use capstan::graph::{AudioGraph, CompiledGraph, GraphNode};
@@ -29,8 +41,10 @@ use capstan::meter::MeterBuffer;
 use capstan::nodes::{
     Echo, GainProcessor, InputNode, Mixer, Overdrive, RecordNode, SineGenerator, Tremolo,
 };
+use capstan::notes::parse_frequency;
 use capstan::record::{write_wav, RecordBuffer};
-use capstan::run_audio;
+use capstan::render::{render_to_wav, BitDepth, RenderOptions};
+use capstan::{run_audio_with_options, AudioOptions};
 use clap::Parser;
 use crossterm::cursor::{MoveTo, Show};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
@@ -47,12 +61,18 @@ const METER_WIDTH: usize = 24;
 const HEADER_REDRAW_MS: u64 = 80;
 const METER_DB_MIN: f32 = -60.0;
 const HISTORY_LINES: usize = 6;
+/// How long `quit` waits for the engine's Stop fade before forcing shutdown.
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
 const COMMAND_HISTORY_CAP: usize = 50;
 const SUCCESS_PREFIX: &str = "  ✓ ";
 const WARNING_PREFIX: &str = "\u{200B}  ";
 const ERROR_PREFIX: &str = "  ✗ ";
+const GRAPH_QUEUE_FULL_MSG: &str = "Command queue full; the graph was not sent. Try again.";
+#[cfg(not(feature = "serde"))]
+const PATCH_NEEDS_SERDE_MSG: &str =
+    "Patches need the serde feature: cargo run --example daw --features serde";
 
-const HELP_MSG: &str = "track create | track delete <no> | input <tn> ... | gain [tn] <lvl> | echo <tn> <ms>|none | tremolo <tn> <rate> <depth>|none | overdrive <tn> <0-5>|none | record | quit";
+const HELP_MSG: &str = "track create | track delete <no> | input <tn> ... | gain [tn] <lvl> | echo <tn> <ms>|none | tremolo <tn> <rate> <depth>|none | overdrive <tn> <0-5>|none | record | status | mute | unmute | panic|! | ramp gain <target> <ms> | solo <tn>|off | preset <name> | patch <file.json> | show | bounce <secs> [--normalize] [--tail] [--fade-ms <ms>] [--bit-depth 16|24|32] | quit";
 
 // -----------------------------------------------------------------------------
 // Types
@@ -64,6 +84,45 @@ const HELP_MSG: &str = "track create | track delete <no> | input <tn> ... | gain
 struct Cli {
     #[arg(long, default_value = "1024")]
     channel_capacity: usize,
+    /// Run commands from this file (one per line) before taking keyboard input.
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Write every engine event to stdout as a JSON line (for wrapping in a GUI); the TUI then
+    /// draws on stderr. Needs the `serde` feature. Stops on a write error.
+    #[arg(long)]
+    json_events: bool,
+    /// Hearing protection: cap the engine's master gain at -12 dBFS and force the limiter on.
+    #[arg(long)]
+    safe_mode: bool,
+    /// Device output channels to play on, 1-based and comma-separated (e.g. 3,4); default all.
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u16).range(1..))]
+    out_channels: Vec<u16>,
+    /// Crossfade between the old and new graph for this many ms on every edit (0 = instant).
+    #[arg(long, default_value = "0")]
+    crossfade_ms: f32,
+    /// Run the audio callback in the CPU's flush-to-zero mode (guards against denormal stalls).
+    #[arg(long)]
+    flush_denormals: bool,
+    /// Render device blocks in pieces of at most this many frames (0 = the stream's buffer
+    /// size, at least 4096).
+    #[arg(long, default_value = "0", value_name = "FRAMES")]
+    max_block: usize,
+    /// Start playing this preset (as if typed `preset <name>`) instead of the empty session.
+    #[arg(long, conflicts_with = "patch")]
+    preset: Option<String>,
+    /// Start playing the graph in this JSON patch file (as if typed `patch <file>`) instead of
+    /// the empty session. Needs the serde feature.
+    #[arg(long, value_name = "FILE")]
+    patch: Option<PathBuf>,
+}
+
+/// One step of a `--script` file.
+#[derive(Debug, PartialEq)]
+enum ScriptStep {
+    /// A command line, handled exactly as if typed.
+    Line(String),
+    /// `sleep <ms>`: wait before the next step.
+    Sleep(Duration),
 }
 
 /// Source for a track: none, device input, sine tone, or file playback.
@@ -75,6 +134,8 @@ enum TrackSource {
     },
     File {
         path: PathBuf,
+        /// Decoded file; kept so an offline bounce can play it from the start.
+        samples: Arc<Vec<f32>>,
         buffer: Arc<dyn SampleSource + Send + Sync>,
     },
 }
@@ -97,6 +158,20 @@ struct Session {
     recording: bool,
     record_buffer: Option<Arc<RecordBuffer>>,
     record_output_path: Option<PathBuf>,
+    /// Clip light from `Event::ClipState`, shown on the master row.
+    clipping: bool,
+    /// Track (1-based) whose output is soloed in the live graph, bypassing the mix and master.
+    solo: Option<usize>,
+    /// Uncompiled copy of the graph last sent to the engine, for `show`.
+    live_graph: Option<AudioGraph>,
+}
+
+/// The command interpreter: the session plus the host-dependent pieces commands need.
+struct Daw {
+    session: Session,
+    inputs: Box<dyn InputDevices>,
+    /// Source for tracks with no input, or whose device isn't open.
+    silent_buffer: Arc<dyn SampleSource + Send + Sync>,
 }
 
 #[derive(Clone, Copy)]
@@ -114,6 +189,40 @@ struct CommandOutcome {
     quit: bool,
 }
 
+/// What a `quit` or any other line did, from [`Daw::dispatch`].
+enum Dispatch {
+    /// Blank line; nothing ran.
+    Blank,
+    /// The command ran; show the outcome and keep reading.
+    Ran(CommandOutcome),
+    /// `quit`: the engine was told to stop. Show the outcome, wait for the audio thread and exit.
+    Quit(CommandOutcome),
+}
+
+/// Input devices the `input` commands list and open: the cpal host in the app, none in tests.
+trait InputDevices {
+    fn list(&self) -> Result<Vec<InputDeviceInfo>, DeviceError>;
+    fn open(
+        &self,
+        device_index: usize,
+        buffer: Arc<InputSampleBuffer>,
+    ) -> Result<cpal::Stream, DeviceError>;
+}
+
+impl InputDevices for cpal::Host {
+    fn list(&self) -> Result<Vec<InputDeviceInfo>, DeviceError> {
+        input_device_list(self)
+    }
+
+    fn open(
+        &self,
+        device_index: usize,
+        buffer: Arc<InputSampleBuffer>,
+    ) -> Result<cpal::Stream, DeviceError> {
+        open_input_stream(self, device_index, buffer)
+    }
+}
+
 /// Open input streams: keep streams alive and map device index -> buffer.
 struct OpenInputs {
     _streams: Vec<cpal::Stream>,
@@ -130,14 +239,14 @@ impl OpenInputs {
 
     fn ensure_device(
         &mut self,
-        host: &cpal::Host,
+        inputs: &dyn InputDevices,
         device_index: usize,
     ) -> Result<Arc<dyn SampleSource + Send + Sync>, DeviceError> {
         if let Some(buf) = self.device_to_buffer.get(&device_index) {
             return Ok(Arc::clone(buf));
         }
         let buffer: Arc<InputSampleBuffer> = Arc::new(InputSampleBuffer::new(2048));
-        let stream = open_input_stream(host, device_index, Arc::clone(&buffer))?;
+        let stream = inputs.open(device_index, Arc::clone(&buffer))?;
         self._streams.push(stream);
         let buffer_dyn: Arc<dyn SampleSource + Send + Sync> = buffer;
         self.device_to_buffer
@@ -150,6 +259,15 @@ impl OpenInputs {
     }
 }
 
+/// Where a session graph will run.
+#[derive(Clone, Copy, PartialEq)]
+enum GraphTarget {
+    /// Sent to the audio thread: live inputs, shared file positions, meters.
+    Live,
+    /// Rendered offline: device inputs are silent, files play from the start, no meters.
+    Offline,
+}
+
 // -----------------------------------------------------------------------------
 // Graph
 // -----------------------------------------------------------------------------
@@ -158,12 +276,16 @@ fn build_session_graph(
     session: &Session,
     silent_buffer: &Arc<dyn SampleSource + Send + Sync>,
     record_buffer: Option<Arc<RecordBuffer>>,
-) -> Option<CompiledGraph> {
+    target: GraphTarget,
+) -> Option<(AudioGraph, CompiledGraph)> {
     use std::iter::once;
     let tracks = &session.tracks;
     let open_inputs = &session.open_inputs;
     let master_gain = session.master_gain;
-    let meter_buffer = &session.meter_buffer;
+    let meter_buffer = match target {
+        GraphTarget::Live => &session.meter_buffer,
+        GraphTarget::Offline => &None,
+    };
     let sample_rate = session.output_sample_rate;
 
     let mut g = AudioGraph::new();
@@ -178,12 +300,13 @@ fn build_session_graph(
         } else {
             g
         };
-        return match &meter_buffer {
+        let compiled = match &meter_buffer {
             Some(mb) if mb.len() == 1 => g
                 .compile_with_meter(DEFAULT_FRAME_COUNT, Some((vec![1], Arc::clone(mb))))
                 .ok(),
             _ => g.compile(DEFAULT_FRAME_COUNT).ok(),
         };
+        return compiled.map(|c| (g, c));
     }
 
     const MAX_DELAY_MS: f32 = 2000.0;
@@ -193,6 +316,9 @@ fn build_session_graph(
             TrackSource::None => {
                 g.add_node(GraphNode::Input(InputNode::new(Arc::clone(silent_buffer))))
             }
+            TrackSource::Device(_) if target == GraphTarget::Offline => {
+                g.add_node(GraphNode::Input(InputNode::new(Arc::clone(silent_buffer))))
+            }
             TrackSource::Device(d) => {
                 let buf = open_inputs
                     .get_buffer(*d)
@@ -202,6 +328,10 @@ fn build_session_graph(
             TrackSource::Sine { freq_hz } => {
                 g.add_node(GraphNode::Sine(SineGenerator::new(*freq_hz, sample_rate)))
             }
+            TrackSource::File { samples, .. } if target == GraphTarget::Offline => {
+                let fresh = Arc::new(FilePlaybackBuffer::new(Arc::clone(samples)));
+                g.add_node(GraphNode::Input(InputNode::new(fresh)))
+            }
             TrackSource::File { buffer, .. } => {
                 g.add_node(GraphNode::Input(InputNode::new(Arc::clone(buffer))))
             }
@@ -230,6 +360,10 @@ fn build_session_graph(
         g.add_edge(head, gain);
         gain_node_ids.push(gain);
     }
+    let solo = session
+        .solo
+        .and_then(|tn| gain_node_ids.get(tn - 1))
+        .map(|&id| g.stable_id(id));
 
     let num_echo = tracks.iter().filter(|t| t.delay_ms.is_some()).count();
     let num_tremolo = tracks.iter().filter(|t| t.tremolo.is_some()).count();
@@ -254,7 +388,7 @@ fn build_session_graph(
     };
 
     // Node order: sources 0..n, echo, tremolo, overdrive, gains, mix, master [, record].
-    match &meter_buffer {
+    let compiled = match &meter_buffer {
         Some(mb) if mb.len() == n + 1 => {
             let base = n + num_echo + num_tremolo + num_overdrive;
             let tap_indices: Vec<usize> =
@@ -263,7 +397,11 @@ fn build_session_graph(
                 .ok()
         }
         _ => g.compile(DEFAULT_FRAME_COUNT).ok(),
-    }
+    };
+    compiled.map(|mut c| {
+        c.set_solo(solo);
+        (g, c)
+    })
 }
 
 // -----------------------------------------------------------------------------
@@ -271,9 +409,18 @@ fn build_session_graph(
 // -----------------------------------------------------------------------------
 
 fn recording_path() -> PathBuf {
+    desktop_wav_path("Recording")
+}
+
+fn bounce_path() -> PathBuf {
+    desktop_wav_path("Bounce")
+}
+
+fn desktop_wav_path(prefix: &str) -> PathBuf {
     let desktop = expand_tilde("~/Desktop");
     let filename = format!(
-        "Recording_{}.wav",
+        "{}_{}.wav",
+        prefix,
         chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
     );
     desktop.join(filename)
@@ -302,10 +449,12 @@ fn expand_tilde(path: &str) -> PathBuf {
 /// Parses 1-based track number; returns error message if out of range.
 fn parse_track_no(s: &str, num_tracks: usize) -> Result<usize, String> {
     let n: usize = s.parse().map_err(|_| "Invalid track number.".to_string())?;
-    if (1..=num_tracks.max(1)).contains(&n) {
+    if num_tracks == 0 {
+        Err("No tracks yet; use 'track create'.".to_string())
+    } else if (1..=num_tracks).contains(&n) {
         Ok(n)
     } else {
-        Err(format!("Track number must be 1–{}.", num_tracks.max(1)))
+        Err(format!("Track number must be 1–{}.", num_tracks))
     }
 }
 
@@ -322,8 +471,18 @@ fn source_display(source: &TrackSource) -> String {
     }
 }
 
-fn send_graph(cmd_tx: &capstan::command::CommandSender, compiled: CompiledGraph) {
-    let _ = cmd_tx.try_send(Command::SwapGraph(compiled));
+/// Sends `compiled` to the engine and, once queued, keeps `graph` (its source) for `show`.
+/// Returns false, sending nothing, if the command queue is full.
+fn send_graph(
+    cmd_tx: &capstan::command::CommandSender,
+    session: &mut Session,
+    (graph, compiled): (AudioGraph, CompiledGraph),
+) -> bool {
+    if cmd_tx.try_send(Command::SwapGraph(compiled)).is_err() {
+        return false;
+    }
+    session.live_graph = Some(graph);
+    true
 }
 
 // -----------------------------------------------------------------------------
@@ -360,6 +519,8 @@ fn draw_header(
     tracks: &[Track],
     peaks: &[f32],
     master_gain: f32,
+    clipping: bool,
+    solo: Option<usize>,
     prompt_row: u16,
 ) -> std::io::Result<()> {
     let mut line = 0u16;
@@ -387,14 +548,15 @@ fn draw_header(
         execute!(stdout, MoveTo(0, line), Clear(ClearType::CurrentLine))?;
         writeln!(
             stdout,
-            "   {}   | {:>10} | {:.2}  | {:>7} | {:>8} | {:>9} | {}",
+            "   {}   | {:>10} | {:.2}  | {:>7} | {:>8} | {:>9} | {}{}",
             i + 1,
             src,
             track.gain,
             echo_display,
             trem_display,
             ovd_display,
-            ascii_meter_with_db(peak)
+            ascii_meter_with_db(peak),
+            if solo == Some(i + 1) { " SOLO" } else { "" }
         )?;
         line += 1;
     }
@@ -403,13 +565,14 @@ fn draw_header(
     execute!(stdout, MoveTo(0, line), Clear(ClearType::CurrentLine))?;
     writeln!(
         stdout,
-        " master| {:>10} | {:.2}  | {:>7} | {:>8} | {:>9} | {}",
+        " master| {:>10} | {:.2}  | {:>7} | {:>8} | {:>9} | {}{}",
         "(mix)",
         master_gain,
         "-",
         "-",
         "-",
-        ascii_meter_with_db(master_peak)
+        ascii_meter_with_db(master_peak),
+        if clipping { " CLIP" } else { "" }
     )?;
     line += 1;
     for y in line..prompt_row {
@@ -475,7 +638,7 @@ fn draw_history(
 fn handle_command(
     session: &mut Session,
     parts: &[&str],
-    host: &cpal::Host,
+    inputs: &dyn InputDevices,
     cmd_tx: &capstan::command::CommandSender,
     silent_buffer: &Arc<dyn SampleSource + Send + Sync>,
 ) -> CommandOutcome {
@@ -494,14 +657,23 @@ fn handle_command(
                 let path = recording_path();
                 session.record_buffer = Some(Arc::new(RecordBuffer::new()));
                 session.record_buffer.as_ref().unwrap().set_armed(true);
-                if let Some(compiled) =
-                    build_session_graph(session, silent_buffer, session.record_buffer.clone())
-                {
-                    send_graph(cmd_tx, compiled);
-                    session.record_output_path = Some(path);
-                    session.recording = true;
-                    status_kind = StatusKind::Success;
-                    status_msg = "Recording... Press Enter to stop.".to_string();
+                if let Some(built) = build_session_graph(
+                    session,
+                    silent_buffer,
+                    session.record_buffer.clone(),
+                    GraphTarget::Live,
+                ) {
+                    if send_graph(cmd_tx, session, built) {
+                        session.record_output_path = Some(path);
+                        session.recording = true;
+                        status_kind = StatusKind::Success;
+                        status_msg = "Recording... Press Enter to stop.".to_string();
+                    } else {
+                        session.record_buffer.as_ref().unwrap().set_armed(false);
+                        session.record_buffer = None;
+                        status_kind = StatusKind::Error;
+                        status_msg = GRAPH_QUEUE_FULL_MSG.to_string();
+                    }
                 } else {
                     session.record_buffer.as_ref().unwrap().set_armed(false);
                     session.record_buffer = None;
@@ -510,12 +682,164 @@ fn handle_command(
                 }
             }
         }
+        ["bounce", seconds, flags @ ..] => {
+            match (seconds.parse::<f32>(), parse_bounce_options(flags)) {
+                (Ok(secs), Ok(options)) if secs > 0.0 => {
+                    match bounce_session(session, silent_buffer, secs, &options) {
+                        Ok(path) => {
+                            status_kind = StatusKind::Success;
+                            status_msg = format!("Bounced {} s to {}", secs, path.display());
+                        }
+                        Err(e) => {
+                            status_kind = StatusKind::Error;
+                            status_msg = format!("Bounce: {}", e);
+                        }
+                    }
+                }
+                (_, Err(e)) => {
+                    status_kind = StatusKind::Warning;
+                    status_msg = e;
+                }
+                _ => {
+                    status_kind = StatusKind::Warning;
+                    status_msg =
+                        "Usage: bounce <seconds> [--normalize] [--tail] [--fade-ms <ms>] [--bit-depth 16|24|32]".to_string();
+                }
+            }
+        }
+        ["status"] => {
+            // The reply arrives as Event::State and is printed by the main loop.
+            if cmd_tx.try_send(Command::QueryState).is_err() {
+                status_kind = StatusKind::Warning;
+                status_msg = "Command queue full; try again.".to_string();
+            }
+        }
+        ["ramp", "gain", target, ms] => match (target.parse::<f32>(), ms.parse::<f32>()) {
+            (Ok(target), Ok(duration_ms)) if duration_ms >= 0.0 => {
+                let ramp = Command::RampGain {
+                    target,
+                    duration_ms,
+                };
+                if cmd_tx.try_send(ramp).is_ok() {
+                    status_kind = StatusKind::Success;
+                    status_msg =
+                        format!("Ramping master gain to {} over {} ms", target, duration_ms);
+                } else {
+                    status_kind = StatusKind::Warning;
+                    status_msg = "Command queue full; try again.".to_string();
+                }
+            }
+            _ => {
+                status_kind = StatusKind::Warning;
+                status_msg = "Usage: ramp gain <target> <ms>".to_string();
+            }
+        },
+        ["panic" | "!"] => {
+            if cmd_tx.try_send(Command::Panic).is_ok() {
+                status_kind = StatusKind::Success;
+                status_msg = "Panic: cleared every tail and envelope.".to_string();
+            } else {
+                status_kind = StatusKind::Warning;
+                status_msg = "Command queue full; try again.".to_string();
+            }
+        }
+        [cmd @ ("mute" | "unmute")] => {
+            let mute = *cmd == "mute";
+            if cmd_tx.try_send(Command::SetMute(mute)).is_ok() {
+                status_kind = StatusKind::Success;
+                status_msg = if mute {
+                    "Output muted."
+                } else {
+                    "Output unmuted."
+                }
+                .to_string();
+            } else {
+                status_kind = StatusKind::Warning;
+                status_msg = "Command queue full; try again.".to_string();
+            }
+        }
+        ["preset", name] => {
+            match capstan::presets::graph_by_name(name, session.output_sample_rate) {
+                Some(graph) => match graph.compile(DEFAULT_FRAME_COUNT) {
+                    Ok(compiled) => {
+                        if send_graph(cmd_tx, session, (graph, compiled)) {
+                            status_kind = StatusKind::Success;
+                            status_msg =
+                                format!("Playing preset '{}'. Edit a track to return.", name);
+                        } else {
+                            status_kind = StatusKind::Error;
+                            status_msg = GRAPH_QUEUE_FULL_MSG.to_string();
+                        }
+                    }
+                    Err(e) => {
+                        status_kind = StatusKind::Error;
+                        status_msg = format!("Preset '{}' failed to compile: {}", name, e);
+                    }
+                },
+                None => {
+                    status_kind = StatusKind::Warning;
+                    status_msg = format!(
+                        "Unknown preset '{}'. Try: {}",
+                        name,
+                        capstan::presets::PRESET_NAMES.join(", ")
+                    );
+                }
+            }
+        }
+        ["patch", path] => match std::fs::read_to_string(path)
+            .map_err(|e| format!("Can't read patch '{}': {}", path, e))
+            .and_then(|text| parse_patch(&text, session.output_sample_rate))
+            .and_then(|graph| {
+                let compiled = graph
+                    .compile(DEFAULT_FRAME_COUNT)
+                    .map_err(|e| format!("Patch '{}' failed to compile: {}", path, e))?;
+                Ok((graph, compiled))
+            }) {
+            Ok(built) => {
+                if send_graph(cmd_tx, session, built) {
+                    status_kind = StatusKind::Success;
+                    status_msg = format!("Playing patch '{}'. Edit a track to return.", path);
+                } else {
+                    status_kind = StatusKind::Error;
+                    status_msg = GRAPH_QUEUE_FULL_MSG.to_string();
+                }
+            }
+            Err(e) => {
+                status_kind = StatusKind::Error;
+                status_msg = e;
+            }
+        },
+        ["solo", "off"] => {
+            session.solo = None;
+            session_changed = true;
+            status_kind = StatusKind::Success;
+            status_msg = "Solo off.".to_string();
+        }
+        ["solo", track_no] => match parse_track_no(track_no, n) {
+            Ok(tn) => {
+                session.solo = Some(tn);
+                session_changed = true;
+                status_kind = StatusKind::Success;
+                status_msg = format!("Soloing track {} (bypasses the mix and master).", tn);
+            }
+            Err(e) => {
+                status_kind = StatusKind::Warning;
+                status_msg = e;
+            }
+        },
         ["quit" | "q"] => {
             quit = true;
         }
         ["help" | "h" | "?"] => {
             status_msg = HELP_MSG.to_string();
         }
+        ["show"] => match &session.live_graph {
+            Some(graph) => status_msg = graph.describe().trim_end().to_string(),
+            None => {
+                status_kind = StatusKind::Warning;
+                status_msg = "No graph sent yet.".to_string();
+            }
+        },
         ["track", "create"] => {
             session.tracks.push(Track {
                 source: TrackSource::None,
@@ -531,6 +855,11 @@ fn handle_command(
         ["track", "delete", no] => match parse_track_no(no, n) {
             Ok(tn) => {
                 session.tracks.remove(tn - 1);
+                session.solo = match session.solo {
+                    Some(s) if s == tn => None,
+                    Some(s) if s > tn => Some(s - 1),
+                    other => other,
+                };
                 session_changed = true;
                 status_kind = StatusKind::Success;
                 status_msg = format!("Deleted track {}.", tn);
@@ -540,7 +869,7 @@ fn handle_command(
                 status_msg = e;
             }
         },
-        ["input", "--list" | "-l"] => match input_device_list(host) {
+        ["input", "--list" | "-l"] => match inputs.list() {
             Ok(devices) => {
                 status_kind = StatusKind::Success;
                 status_msg = if devices.is_empty() {
@@ -565,7 +894,7 @@ fn handle_command(
         },
         ["input", track_no, "--device", dev] => {
             if let (Ok(tn), Ok(d)) = (parse_track_no(track_no, n), dev.parse::<usize>()) {
-                match session.open_inputs.ensure_device(host, d) {
+                match session.open_inputs.ensure_device(inputs, d) {
                     Ok(_) => {
                         session.tracks[tn - 1].source = TrackSource::Device(d);
                         session_changed = true;
@@ -583,7 +912,7 @@ fn handle_command(
             }
         }
         ["input", track_no, "--sine", freq] => {
-            if let (Ok(tn), Ok(f)) = (parse_track_no(track_no, n), freq.parse::<f32>()) {
+            if let (Ok(tn), Some(f)) = (parse_track_no(track_no, n), parse_frequency(freq)) {
                 if (0.0..=20_000.0).contains(&f) {
                     session.tracks[tn - 1].source = TrackSource::Sine { freq_hz: f };
                     session_changed = true;
@@ -597,7 +926,7 @@ fn handle_command(
                 status_kind = StatusKind::Warning;
                 status_msg = parse_track_no(track_no, n)
                     .err()
-                    .unwrap_or_else(|| "Usage: input <track_no> --sine <freq_hz>".to_string());
+                    .unwrap_or_else(|| "Usage: input <track_no> --sine <freq_hz|note>".to_string());
             }
         }
         _ if parts.len() >= 4 && parts[0] == "input" && parts[2] == "--file" => {
@@ -606,9 +935,14 @@ fn handle_command(
                 let path = PathBuf::from(&path_str);
                 match load_wav_at_rate(&path, session.output_sample_rate) {
                     Ok(samples) => {
+                        let samples = Arc::new(samples);
                         let buffer: Arc<dyn SampleSource + Send + Sync> =
-                            Arc::new(FilePlaybackBuffer::new(Arc::new(samples)));
-                        session.tracks[tn - 1].source = TrackSource::File { path, buffer };
+                            Arc::new(FilePlaybackBuffer::new(Arc::clone(&samples)));
+                        session.tracks[tn - 1].source = TrackSource::File {
+                            path,
+                            samples,
+                            buffer,
+                        };
                         session_changed = true;
                         status_kind = StatusKind::Success;
                         status_msg = format!("Track {} → file {}.", tn, path_str);
@@ -753,8 +1087,11 @@ fn handle_command(
 
     if session_changed {
         session.meter_buffer = Some(Arc::new(MeterBuffer::new(session.tracks.len() + 1)));
-        if let Some(compiled) = build_session_graph(session, silent_buffer, None) {
-            send_graph(cmd_tx, compiled);
+        if let Some(built) = build_session_graph(session, silent_buffer, None, GraphTarget::Live) {
+            if !send_graph(cmd_tx, session, built) {
+                status_kind = StatusKind::Error;
+                status_msg = GRAPH_QUEUE_FULL_MSG.to_string();
+            }
         } else {
             status_kind = StatusKind::Error;
             status_msg = "Failed to compile graph.".to_string();
@@ -768,6 +1105,202 @@ fn handle_command(
     }
 }
 
+impl Daw {
+    /// Splits a typed or scripted line into words and runs it through [`handle_command`].
+    ///
+    /// `quit` sends `Command::Stop` so the engine fades out and ends the stream; if the command
+    /// queue is full it signals `shutdown_tx` instead.
+    fn dispatch(
+        &mut self,
+        line: &str,
+        cmd_tx: &capstan::command::CommandSender,
+        shutdown_tx: &Sender<()>,
+    ) -> Dispatch {
+        let parts: Vec<&str> = line.split_ascii_whitespace().collect();
+        if parts.is_empty() {
+            return Dispatch::Blank;
+        }
+        let outcome = handle_command(
+            &mut self.session,
+            &parts,
+            self.inputs.as_ref(),
+            cmd_tx,
+            &self.silent_buffer,
+        );
+        if !outcome.quit {
+            return Dispatch::Ran(outcome);
+        }
+        if cmd_tx.try_send(Command::Stop).is_err() {
+            let _ = shutdown_tx.send(());
+        }
+        Dispatch::Quit(outcome)
+    }
+
+    /// Sends the first graph: the `--preset` or `--patch` graph if one was given (as the command
+    /// line `initial`, e.g. `["preset", "bell"]`) and it builds, otherwise the session's own
+    /// graph. Returns the history lines for the initial attempt, if any.
+    fn send_startup_graph(
+        &mut self,
+        initial: &[&str],
+        cmd_tx: &capstan::command::CommandSender,
+    ) -> Vec<String> {
+        let session = &mut self.session;
+        let mut history = Vec::new();
+        if !initial.is_empty() {
+            let outcome = handle_command(
+                session,
+                initial,
+                self.inputs.as_ref(),
+                cmd_tx,
+                &self.silent_buffer,
+            );
+            history.push(format!("> --{}", initial.join(" ")));
+            history.extend(outcome_lines(&outcome).into_iter().rev());
+            if matches!(outcome.status_kind, StatusKind::Success) {
+                return history;
+            }
+        }
+        if let Some(built) =
+            build_session_graph(session, &self.silent_buffer, None, GraphTarget::Live)
+        {
+            if !send_graph(cmd_tx, session, built) {
+                history.push(format!("{}{}", ERROR_PREFIX, GRAPH_QUEUE_FULL_MSG));
+            }
+        }
+        history
+    }
+}
+
+/// Formats a command outcome for the history pane, top to bottom: the prefixed first line, then
+/// any further lines of a multi-line message (e.g. `show`) indented under it.
+fn outcome_lines(outcome: &CommandOutcome) -> Vec<String> {
+    let prefix = match outcome.status_kind {
+        StatusKind::Success => SUCCESS_PREFIX,
+        StatusKind::Warning => WARNING_PREFIX,
+        StatusKind::Error => ERROR_PREFIX,
+        StatusKind::Neutral => "  ",
+    };
+    let mut lines = outcome.status_msg.lines();
+    let first = format!("{}{}", prefix, lines.next().unwrap_or(""));
+    std::iter::once(first)
+        .chain(lines.map(|line| format!("  {}", line)))
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+// Scripts
+// -----------------------------------------------------------------------------
+
+/// Parses a `--script` file: one command per line, blank lines and `#` comments skipped,
+/// `sleep <ms>` turned into a pause.
+fn parse_script(text: &str) -> Result<Vec<ScriptStep>, String> {
+    let mut steps = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["sleep", ms] => {
+                let ms: u64 = ms
+                    .parse()
+                    .map_err(|_| format!("line {}: sleep needs milliseconds", i + 1))?;
+                steps.push(ScriptStep::Sleep(Duration::from_millis(ms)));
+            }
+            ["sleep", ..] => return Err(format!("line {}: usage: sleep <ms>", i + 1)),
+            _ => steps.push(ScriptStep::Line(line.to_string())),
+        }
+    }
+    Ok(steps)
+}
+
+// -----------------------------------------------------------------------------
+// Patches
+// -----------------------------------------------------------------------------
+
+/// Parses a `--patch` / `patch` file: JSON in the [`capstan::patch`] format, e.g.
+///
+/// ```json
+/// { "nodes": [{ "kind": "sine", "frequency": 220 },
+///             { "kind": "echo", "delay_ms": 300, "feedback": 0.4 },
+///             { "kind": "gain", "gain": 0.5 }],
+///   "edges": [[0, 1], [1, 2]] }
+/// ```
+#[cfg(feature = "serde")]
+fn parse_patch(text: &str, sample_rate: u32) -> Result<AudioGraph, String> {
+    let patch: capstan::patch::Patch =
+        serde_json::from_str(text).map_err(|e| format!("Bad patch: {}", e))?;
+    patch
+        .to_graph(sample_rate)
+        .map_err(|e| format!("Bad patch: {}", e))
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_patch(_text: &str, _sample_rate: u32) -> Result<AudioGraph, String> {
+    Err(PATCH_NEEDS_SERDE_MSG.to_string())
+}
+
+// -----------------------------------------------------------------------------
+// Bounce
+// -----------------------------------------------------------------------------
+
+/// Peak level for `bounce --normalize` (full scale).
+const BOUNCE_NORMALIZE_PEAK: f32 = 1.0;
+
+/// Parses the flags after `bounce <seconds>`.
+fn parse_bounce_options(flags: &[&str]) -> Result<RenderOptions, String> {
+    let mut options = RenderOptions::default();
+    let mut flags = flags.iter();
+    while let Some(&flag) = flags.next() {
+        match flag {
+            "--normalize" => options.normalize = Some(BOUNCE_NORMALIZE_PEAK),
+            "--tail" => options.tail = true,
+            "--fade-ms" => {
+                let ms = flags
+                    .next()
+                    .and_then(|v| v.parse::<f32>().ok())
+                    .filter(|ms| *ms >= 0.0)
+                    .ok_or_else(|| "--fade-ms needs a non-negative number".to_string())?;
+                options.fade_in_ms = ms;
+                options.fade_out_ms = ms;
+            }
+            "--bit-depth" => {
+                options.bit_depth = match flags.next() {
+                    Some(&"16") => BitDepth::I16,
+                    Some(&"24") => BitDepth::I24,
+                    Some(&"32") => BitDepth::F32,
+                    _ => return Err("--bit-depth must be 16, 24 or 32".to_string()),
+                };
+            }
+            other => return Err(format!("Unknown bounce option: {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+/// Renders the session offline for `seconds` and writes it next to recordings. Returns the path.
+fn bounce_session(
+    session: &Session,
+    silent_buffer: &Arc<dyn SampleSource + Send + Sync>,
+    seconds: f32,
+    options: &RenderOptions,
+) -> Result<PathBuf, String> {
+    let (_, mut compiled) = build_session_graph(session, silent_buffer, None, GraphTarget::Offline)
+        .ok_or_else(|| "failed to compile graph".to_string())?;
+    let frames = (seconds * session.output_sample_rate as f32).round() as usize;
+    let path = bounce_path();
+    let _ = path.parent().map(std::fs::create_dir_all);
+    render_to_wav(
+        &mut compiled,
+        session.output_sample_rate,
+        frames,
+        &path,
+        options,
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
 // -----------------------------------------------------------------------------
 // Recording
 // -----------------------------------------------------------------------------
@@ -798,40 +1331,88 @@ fn stop_recording_and_save(
         }
     }
     session.recording = false;
-    if let Some(compiled) = build_session_graph(session, silent_buffer, None) {
-        send_graph(cmd_tx, compiled);
+    if let Some(built) = build_session_graph(session, silent_buffer, None, GraphTarget::Live) {
+        if !send_graph(cmd_tx, session, built) {
+            history.push(format!("{}{}", ERROR_PREFIX, GRAPH_QUEUE_FULL_MSG));
+        }
     }
     Ok(())
 }
 
+/// Writes `evt` to stdout as one JSON line and flushes, so a reader sees it immediately.
+#[cfg(feature = "serde")]
+fn write_json_event(evt: &capstan::event::Event) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    serde_json::to_writer(&mut out, evt)?;
+    writeln!(out)?;
+    out.flush()
+}
+
 // -----------------------------------------------------------------------------
 // Main
 // -----------------------------------------------------------------------------
 
 fn main() -> std::io::Result<()> {
     let cli = Cli::parse();
+    let mut script: VecDeque<ScriptStep> = match &cli.script {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)?;
+            parse_script(&text)
+                .map_err(|e| std::io::Error::other(format!("{}: {}", path.display(), e)))?
+                .into()
+        }
+        None => VecDeque::new(),
+    };
+    let mut script_resume_at: Option<Instant> = None;
+    #[cfg(not(feature = "serde"))]
+    if cli.patch.is_some() {
+        return Err(std::io::Error::other(PATCH_NEEDS_SERDE_MSG));
+    }
+    if cli.json_events && !cfg!(feature = "serde") {
+        return Err(std::io::Error::other(
+            "--json-events needs the serde feature: cargo run --example daw --features serde",
+        ));
+    }
+    #[cfg_attr(not(feature = "serde"), allow(unused_mut))]
+    let mut json_events = cli.json_events;
 
     let (cmd_tx, cmd_rx) = command_channel(cli.channel_capacity);
     let (evt_tx, evt_rx) = event_channel(cli.channel_capacity);
     let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
     let (audio_result_tx, audio_result_rx) = std::sync::mpsc::channel();
-    let host = capstan::cpal::default_host();
 
+    let audio_options = AudioOptions {
+        safe_mode: cli.safe_mode,
+        out_channels: cli
+            .out_channels
+            .iter()
+            .map(|&c| usize::from(c) - 1)
+            .collect(),
+        swap_crossfade_ms: cli.crossfade_ms,
+        flush_denormals: cli.flush_denormals,
+        max_block: cli.max_block,
+    };
     let audio_handle = thread::spawn(move || {
-        let result = run_audio(cmd_rx, evt_tx, shutdown_rx, None);
+        let result = run_audio_with_options(cmd_rx, evt_tx, shutdown_rx, None, &audio_options);
         let _ = audio_result_tx.send(result);
     });
 
-    let silent_buffer: Arc<dyn SampleSource + Send + Sync> = Arc::new(InputSampleBuffer::new(2048));
-    let mut session = Session {
-        tracks: Vec::new(),
-        master_gain: 0.8,
-        open_inputs: OpenInputs::new(),
-        meter_buffer: Some(Arc::new(MeterBuffer::new(1))),
-        output_sample_rate: capstan::default_output_sample_rate().unwrap_or(48_000),
-        recording: false,
-        record_buffer: None,
-        record_output_path: None,
+    let mut daw = Daw {
+        session: Session {
+            tracks: Vec::new(),
+            master_gain: 0.8,
+            open_inputs: OpenInputs::new(),
+            meter_buffer: Some(Arc::new(MeterBuffer::new(1))),
+            output_sample_rate: capstan::default_output_sample_rate().unwrap_or(48_000),
+            recording: false,
+            record_buffer: None,
+            record_output_path: None,
+            clipping: false,
+            solo: None,
+            live_graph: None,
+        },
+        inputs: Box::new(capstan::cpal::default_host()),
+        silent_buffer: Arc::new(InputSampleBuffer::new(2048)),
     };
 
     let mut input_line = String::new();
@@ -841,13 +1422,22 @@ fn main() -> std::io::Result<()> {
     let mut history_index: Option<usize> = None;
 
     enable_raw_mode().map_err(std::io::Error::other)?;
-    let mut stdout = io::stdout();
-    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)).map_err(std::io::Error::other)?;
-    stdout.flush()?;
+    // With --json-events, stdout carries only event lines.
+    let mut term: Box<dyn Write> = if json_events {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    execute!(term, Clear(ClearType::All), MoveTo(0, 0)).map_err(std::io::Error::other)?;
+    term.flush()?;
 
-    if let Some(compiled) = build_session_graph(&session, &silent_buffer, None) {
-        send_graph(&cmd_tx, compiled);
-    }
+    let patch = cli.patch.as_deref().map(|p| p.to_string_lossy());
+    let initial = match (&cli.preset, &patch) {
+        (Some(name), _) => vec!["preset", name.as_str()],
+        (None, Some(path)) => vec!["patch", path.as_ref()],
+        (None, None) => Vec::new(),
+    };
+    history.extend(daw.send_startup_graph(&initial, &cmd_tx));
 
     loop {
         if let Ok(Err(e)) = audio_result_rx.try_recv() {
@@ -857,87 +1447,79 @@ fn main() -> std::io::Result<()> {
             return Err(std::io::Error::other(e.to_string()));
         }
 
-        let pr = prompt_row(&session.tracks);
-        let peaks = session
+        let pr = prompt_row(&daw.session.tracks);
+        let peaks = daw
+            .session
             .meter_buffer
             .as_ref()
             .map(|m| m.read_peaks())
             .unwrap_or_default();
         draw_header(
-            &mut stdout,
-            &session.tracks,
+            &mut term,
+            &daw.session.tracks,
             &peaks,
-            session.master_gain,
+            daw.session.master_gain,
+            daw.session.clipping,
+            daw.session.solo,
             pr,
         )?;
-        execute!(stdout, MoveTo(0, pr), Clear(ClearType::CurrentLine))?;
-        write!(stdout, "> {}", input_line)?;
-        draw_history(&mut stdout, &history, pr + 1, HISTORY_LINES)?;
+        execute!(term, MoveTo(0, pr), Clear(ClearType::CurrentLine))?;
+        write!(term, "> {}", input_line)?;
+        draw_history(&mut term, &history, pr + 1, HISTORY_LINES)?;
         let cursor_col = (2 + cursor_pos.min(input_line.len())).min(u16::MAX as usize) as u16;
-        execute!(stdout, MoveTo(cursor_col, pr), Show)?;
-        stdout.flush()?;
+        execute!(term, MoveTo(cursor_col, pr), Show)?;
+        term.flush()?;
 
-        if event::poll(Duration::from_millis(HEADER_REDRAW_MS)).map_err(std::io::Error::other)? {
+        let mut quit_requested = false;
+        if !daw.session.recording && script_resume_at.is_none_or(|t| Instant::now() >= t) {
+            script_resume_at = None;
+            match script.pop_front() {
+                Some(ScriptStep::Sleep(d)) => script_resume_at = Some(Instant::now() + d),
+                Some(ScriptStep::Line(line)) => {
+                    let dispatch = daw.dispatch(&line, &cmd_tx, &shutdown_tx);
+                    if let Dispatch::Ran(outcome) | Dispatch::Quit(outcome) = &dispatch {
+                        history.push(format!("> {}", line));
+                        history.extend(outcome_lines(outcome).into_iter().rev());
+                    }
+                    quit_requested = matches!(dispatch, Dispatch::Quit(_));
+                }
+                None => {}
+            }
+        }
+
+        if !quit_requested
+            && event::poll(Duration::from_millis(HEADER_REDRAW_MS))
+                .map_err(std::io::Error::other)?
+        {
             if let Ok(Event::Key(ke)) = event::read() {
                 if ke.kind != KeyEventKind::Press {
                     continue;
                 }
                 match ke.code {
                     KeyCode::Enter => {
-                        if session.recording {
+                        if daw.session.recording {
                             stop_recording_and_save(
-                                &mut session,
+                                &mut daw.session,
                                 &cmd_tx,
-                                &silent_buffer,
+                                &daw.silent_buffer,
                                 &mut history,
                             )?;
                         } else {
                             let line = input_line.trim().to_string();
                             input_line.clear();
                             cursor_pos = 0;
-                            if !line.is_empty() {
-                                let parts: Vec<&str> = line.split_ascii_whitespace().collect();
-                                let outcome = handle_command(
-                                    &mut session,
-                                    &parts,
-                                    &host,
-                                    &cmd_tx,
-                                    &silent_buffer,
-                                );
+                            let dispatch = daw.dispatch(&line, &cmd_tx, &shutdown_tx);
+                            if let Dispatch::Ran(outcome) | Dispatch::Quit(outcome) = &dispatch {
                                 history.push(format!("> {}", line));
-                                let result_line = match outcome.status_kind {
-                                    StatusKind::Success => {
-                                        format!("{}{}", SUCCESS_PREFIX, outcome.status_msg)
-                                    }
-                                    StatusKind::Warning => {
-                                        format!("{}{}", WARNING_PREFIX, outcome.status_msg)
-                                    }
-                                    StatusKind::Error => {
-                                        format!("{}{}", ERROR_PREFIX, outcome.status_msg)
-                                    }
-                                    StatusKind::Neutral => format!("  {}", outcome.status_msg),
-                                };
-                                history.push(result_line);
+                                // History is drawn newest first; push the bottom line first.
+                                history.extend(outcome_lines(outcome).into_iter().rev());
                                 command_history.push(line);
                                 if command_history.len() > COMMAND_HISTORY_CAP {
                                     command_history.remove(0);
                                 }
                                 history_index = None;
-
-                                if outcome.quit {
-                                    let _ = cmd_tx.try_send(Command::Quit);
-                                    let _ = shutdown_tx.send(());
-                                    disable_raw_mode().map_err(std::io::Error::other)?;
-                                    let _ = audio_handle.join();
-                                    if let Ok(Err(e)) = audio_result_rx.recv() {
-                                        eprintln!("Audio error: {}", e);
-                                    }
-                                    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))
-                                        .map_err(std::io::Error::other)?;
-                                    stdout.flush()?;
-                                    return Ok(());
-                                }
                             }
+                            quit_requested = matches!(dispatch, Dispatch::Quit(_));
                         }
                     }
                     KeyCode::Left => {
@@ -946,21 +1528,18 @@ fn main() -> std::io::Result<()> {
                     KeyCode::Right => {
                         cursor_pos = (cursor_pos + 1).min(input_line.len());
                     }
-                    KeyCode::Up => {
-                        if !command_history.is_empty() {
-                            if let Some(i) = history_index {
-                                if i + 1 < command_history.len() {
-                                    history_index = Some(i + 1);
-                                    input_line = command_history
-                                        [command_history.len() - 1 - (i + 1)]
-                                        .clone();
-                                    cursor_pos = input_line.len();
-                                }
-                            } else {
-                                history_index = Some(0);
-                                input_line = command_history.last().cloned().unwrap_or_default();
+                    KeyCode::Up if !command_history.is_empty() => {
+                        if let Some(i) = history_index {
+                            if i + 1 < command_history.len() {
+                                history_index = Some(i + 1);
+                                input_line =
+                                    command_history[command_history.len() - 1 - (i + 1)].clone();
                                 cursor_pos = input_line.len();
                             }
+                        } else {
+                            history_index = Some(0);
+                            input_line = command_history.last().cloned().unwrap_or_default();
+                            cursor_pos = input_line.len();
                         }
                     }
                     KeyCode::Down => {
@@ -993,11 +1572,511 @@ fn main() -> std::io::Result<()> {
             }
         }
 
+        if quit_requested {
+            // dispatch sent Stop, which fades out and ends the stream; the shutdown channel is the
+            // fallback if the engine can't get there (device stalled).
+            let result = audio_result_rx.recv_timeout(STOP_TIMEOUT).or_else(|_| {
+                let _ = shutdown_tx.send(());
+                audio_result_rx.recv()
+            });
+            disable_raw_mode().map_err(std::io::Error::other)?;
+            let _ = audio_handle.join();
+            if let Ok(Err(e)) = result {
+                eprintln!("Audio error: {}", e);
+            }
+            execute!(term, Clear(ClearType::All), MoveTo(0, 0)).map_err(std::io::Error::other)?;
+            term.flush()?;
+            return Ok(());
+        }
+
         while let Some(evt) = evt_rx.try_recv() {
-            if let capstan::event::Event::StreamStarted(sr) = evt {
-                session.output_sample_rate = sr;
-                history.push(format!("{}Output sample rate: {} Hz", SUCCESS_PREFIX, sr));
+            #[cfg(feature = "serde")]
+            if json_events {
+                // Returning here would leave the terminal raw and the stream running.
+                if let Err(e) = write_json_event(&evt) {
+                    history.push(format!(
+                        "{}Can't write --json-events ({}); event output stopped.",
+                        ERROR_PREFIX, e
+                    ));
+                    json_events = false;
+                }
+            }
+            match evt {
+                capstan::event::Event::StreamStarted(sr) => {
+                    daw.session.output_sample_rate = sr;
+                    history.push(format!("{}Output sample rate: {} Hz", SUCCESS_PREFIX, sr));
+                }
+                capstan::event::Event::ProcessorPanicked => {
+                    history.push(format!(
+                        "{}A node panicked; the block was silenced.",
+                        ERROR_PREFIX
+                    ));
+                }
+                capstan::event::Event::ParamClamped {
+                    param,
+                    requested,
+                    applied,
+                } => {
+                    history.push(format!(
+                        "{}{} {} is out of range; using {}.",
+                        WARNING_PREFIX, param, requested, applied
+                    ));
+                }
+                capstan::event::Event::ClipState(on) => daw.session.clipping = on,
+                capstan::event::Event::UnexpectedSilence => {
+                    history.push(format!(
+                        "{}Output has been silent for a while; check the graph with 'show'.",
+                        WARNING_PREFIX
+                    ));
+                }
+                capstan::event::Event::BlockSizeMismatch { compiled, actual } => {
+                    history.push(format!(
+                        "{}Device blocks are {} samples; graphs are compiled for {}.",
+                        WARNING_PREFIX, actual, compiled
+                    ));
+                }
+                capstan::event::Event::ChannelMismatch { expected, got } => {
+                    history.push(format!(
+                        "{}Graph is {}-channel but the engine renders {}; muted.",
+                        ERROR_PREFIX, got, expected
+                    ));
+                }
+                capstan::event::Event::State {
+                    gain,
+                    freq,
+                    graph_nodes,
+                    paused,
+                } => {
+                    history.push(format!(
+                        "  engine: gain {:.2} | freq {} Hz | {} graph nodes | paused {}",
+                        gain, freq, graph_nodes, paused
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_session() -> Session {
+        Session {
+            tracks: Vec::new(),
+            master_gain: 0.8,
+            open_inputs: OpenInputs::new(),
+            meter_buffer: None,
+            output_sample_rate: 48_000,
+            recording: false,
+            record_buffer: None,
+            record_output_path: None,
+            clipping: false,
+            solo: None,
+            live_graph: None,
+        }
+    }
+
+    /// No input devices, so tests never touch the audio host.
+    struct NoInputs;
+
+    impl InputDevices for NoInputs {
+        fn list(&self) -> Result<Vec<InputDeviceInfo>, DeviceError> {
+            Ok(Vec::new())
+        }
+
+        fn open(
+            &self,
+            device_index: usize,
+            _buffer: Arc<InputSampleBuffer>,
+        ) -> Result<cpal::Stream, DeviceError> {
+            Err(DeviceError::NoDeviceAtIndex(device_index))
+        }
+    }
+
+    fn test_daw() -> Daw {
+        Daw {
+            session: test_session(),
+            inputs: Box::new(NoInputs),
+            silent_buffer: Arc::new(InputSampleBuffer::new(64)),
+        }
+    }
+
+    /// Runs one line through the same path as typed input. `None` for blank lines.
+    fn dispatch(
+        line: &str,
+        daw: &mut Daw,
+        cmd_tx: &capstan::command::CommandSender,
+    ) -> Option<CommandOutcome> {
+        let (shutdown_tx, _shutdown_rx) = std::sync::mpsc::channel();
+        match daw.dispatch(line, cmd_tx, &shutdown_tx) {
+            Dispatch::Blank => None,
+            Dispatch::Ran(outcome) | Dispatch::Quit(outcome) => Some(outcome),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_gain_sets_master_and_sends_graph() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        let outcome = dispatch("gain 0.5", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Success));
+        assert!(!outcome.quit);
+        assert_eq!(daw.session.master_gain, 0.5);
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        assert!(cmd_rx.try_recv().is_none());
+
+        // Out-of-range levels are clamped, not rejected.
+        dispatch("gain 9", &mut daw, &cmd_tx).unwrap();
+        assert_eq!(daw.session.master_gain, 2.0);
+    }
+
+    #[test]
+    fn test_dispatch_sends_engine_commands() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        dispatch("preset bell", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        dispatch("mute", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SetMute(true))));
+        dispatch("ramp gain 0.25 100", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(
+            cmd_rx.try_recv(),
+            Some(Command::RampGain { target, duration_ms }) if target == 0.25 && duration_ms == 100.0
+        ));
+        dispatch("status", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::QueryState)));
+        dispatch("!", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::Panic)));
+    }
+
+    #[test]
+    fn test_dispatch_solo_rebuilds_with_track_soloed() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        dispatch("track create", &mut daw, &cmd_tx).unwrap();
+        dispatch("track create", &mut daw, &cmd_tx).unwrap();
+        while cmd_rx.try_recv().is_some() {}
+
+        dispatch("solo 2", &mut daw, &cmd_tx).unwrap();
+        assert_eq!(daw.session.solo, Some(2));
+        match cmd_rx.try_recv() {
+            Some(Command::SwapGraph(g)) => assert!(g.solo().is_some()),
+            other => panic!("expected a graph, got {:?}", other),
+        }
+        // Deleting an earlier track keeps the same track soloed under its new number.
+        dispatch("track delete 1", &mut daw, &cmd_tx).unwrap();
+        assert_eq!(daw.session.solo, Some(1));
+        dispatch("solo off", &mut daw, &cmd_tx).unwrap();
+        assert_eq!(daw.session.solo, None);
+        let mut last = None;
+        while let Some(cmd) = cmd_rx.try_recv() {
+            last = Some(cmd);
+        }
+        assert!(matches!(last, Some(Command::SwapGraph(g)) if g.solo().is_none()));
+    }
+
+    #[test]
+    fn test_dispatch_sine_input_takes_hz_or_note() {
+        let (cmd_tx, _cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        dispatch("track create", &mut daw, &cmd_tx).unwrap();
+        dispatch("input 1 --sine A4", &mut daw, &cmd_tx).unwrap();
+        assert!(
+            matches!(daw.session.tracks[0].source, TrackSource::Sine { freq_hz } if freq_hz == 440.0)
+        );
+        dispatch("input 1 --sine 220", &mut daw, &cmd_tx).unwrap();
+        assert!(
+            matches!(daw.session.tracks[0].source, TrackSource::Sine { freq_hz } if freq_hz == 220.0)
+        );
+        let outcome = dispatch("input 1 --sine H4", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Warning));
+    }
+
+    #[test]
+    fn test_dispatch_show_lists_the_last_sent_graph() {
+        let (cmd_tx, _cmd_rx) = command_channel(16);
+        let mut daw = test_daw();
+        let outcome = dispatch("show", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Warning));
+
+        for line in [
+            "track create",
+            "track create",
+            "input 1 --sine 220",
+            "input 2 --sine 330",
+        ] {
+            dispatch(line, &mut daw, &cmd_tx).unwrap();
+        }
+        let outcome = dispatch("show", &mut daw, &cmd_tx).unwrap();
+        let text = &outcome.status_msg;
+        assert!(text.contains("[0] Sine Frequency=220"), "{text}");
+        assert!(text.contains("[2] Sine Frequency=330"), "{text}");
+        assert!(text.contains("[4] Mixer"), "{text}");
+        assert!(text.contains("1 -> 4, 2 -> 3, 3 -> 4"), "{text}");
+        let lines = outcome_lines(&outcome);
+        assert_eq!(lines.len(), text.lines().count());
+        assert!(lines.iter().all(|line| line.starts_with("  ")));
+    }
+
+    #[test]
+    fn test_startup_preset_sends_exactly_one_swap_graph() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+
+        let mut daw = test_daw();
+        let history = daw.send_startup_graph(&["preset", "bell"], &cmd_tx);
+        assert_eq!(history[0], "> --preset bell");
+        assert!(history.iter().any(|line| line.starts_with(SUCCESS_PREFIX)));
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        assert!(cmd_rx.try_recv().is_none(), "only the preset is sent");
+        assert!(daw
+            .session
+            .live_graph
+            .as_ref()
+            .unwrap()
+            .describe()
+            .contains("Echo"));
+    }
+
+    #[test]
+    fn test_startup_falls_back_to_session_graph() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+
+        let mut daw = test_daw();
+        let history = daw.send_startup_graph(&["preset", "kazoo"], &cmd_tx);
+        assert!(history.iter().any(|line| line.starts_with(WARNING_PREFIX)));
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        assert!(cmd_rx.try_recv().is_none());
+
+        let missing = ["patch", "/nonexistent/patch.json"];
+        let history = test_daw().send_startup_graph(&missing, &cmd_tx);
+        assert!(history.iter().any(|line| line.starts_with(ERROR_PREFIX)));
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        assert!(cmd_rx.try_recv().is_none());
+
+        assert!(test_daw().send_startup_graph(&[], &cmd_tx).is_empty());
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+    }
+
+    #[test]
+    fn test_full_queue_is_an_error_and_startup_still_falls_back() {
+        let (cmd_tx, _cmd_rx) = command_channel(1);
+        cmd_tx.try_send(Command::NoOp).unwrap();
+
+        let mut daw = test_daw();
+        let outcome = dispatch("preset bell", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Error));
+        assert_eq!(outcome.status_msg, GRAPH_QUEUE_FULL_MSG);
+        assert!(daw.session.live_graph.is_none());
+
+        // The preset is not taken for sent, so the session graph is tried (and fails) too.
+        let history = daw.send_startup_graph(&["preset", "bell"], &cmd_tx);
+        assert!(!history.iter().any(|line| line.starts_with(SUCCESS_PREFIX)));
+        let errors = history.iter().filter(|line| line.starts_with(ERROR_PREFIX));
+        assert_eq!(errors.count(), 2, "{history:?}");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_startup_patch_sends_swap_graph() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let path = std::env::temp_dir().join(format!("capstan_patch_{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{ "nodes": [{ "kind": "sine", "frequency": 220 },
+                          { "kind": "echo", "delay_ms": 300, "feedback": 0.4 },
+                          { "kind": "gain", "gain": 0.5 }],
+                "edges": [[0, 1], [1, 2]] }"#,
+        )
+        .unwrap();
+
+        let mut daw = test_daw();
+        let path_str = path.to_string_lossy();
+        let history = daw.send_startup_graph(&["patch", &path_str], &cmd_tx);
+        assert!(history.iter().any(|line| line.starts_with(SUCCESS_PREFIX)));
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+        assert!(cmd_rx.try_recv().is_none(), "only the patch is sent");
+        let live = daw.session.live_graph.as_ref().unwrap().describe();
+        assert!(live.contains("Sine") && live.contains("Echo"), "{}", live);
+
+        // A patch that can't be queued is an error, not "Playing patch".
+        let (full_tx, _full_rx) = command_channel(1);
+        full_tx.try_send(Command::NoOp).unwrap();
+        let outcome = dispatch(&format!("patch {}", path_str), &mut test_daw(), &full_tx);
+        std::fs::remove_file(&path).unwrap();
+        let outcome = outcome.unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Error));
+        assert_eq!(outcome.status_msg, GRAPH_QUEUE_FULL_MSG);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_parse_patch_reports_bad_specs() {
+        let parse = |text: &str| parse_patch(text, 48_000).map(|g| g.node_count());
+        assert_eq!(
+            parse(r#"{"nodes": [{"kind": "mixer", "gains": [1, 0.5]}], "edges": []}"#),
+            Ok(1)
+        );
+        let cases = [
+            ("not json", "expected ident"),
+            (r#"{"edges": []}"#, "missing field `nodes`"),
+            (
+                r#"{"nodes": [{"kind": "kazoo"}], "edges": []}"#,
+                "unknown variant `kazoo`",
+            ),
+            (
+                r#"{"nodes": [{"kind": "sine", "drive": 2}], "edges": []}"#,
+                "unknown field `drive`",
+            ),
+            (
+                r#"{"nodes": [{"kind": "gain", "gain": "loud"}], "edges": []}"#,
+                "expected f32",
+            ),
+            (
+                r#"{"nodes": [{"kind": "mixer"}], "edges": []}"#,
+                "missing field `gains`",
+            ),
+            (
+                r#"{"nodes": [{"kind": "sine"}], "edges": [[0, 1]]}"#,
+                "edge [0, 1] is not a pair of node indices",
+            ),
+        ];
+        for (text, expected) in cases {
+            let err = parse(text).unwrap_err();
+            assert!(err.starts_with("Bad patch: "), "{}", err);
+            assert!(err.contains(expected), "{} -> {}", text, err);
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "serde"))]
+    fn test_patch_needs_serde() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let path =
+            std::env::temp_dir().join(format!("capstan_noserde_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{ "nodes": [], "edges": [] }"#).unwrap();
+        let outcome = dispatch(
+            &format!("patch {}", path.to_string_lossy()),
+            &mut test_daw(),
+            &cmd_tx,
+        );
+        std::fs::remove_file(&path).unwrap();
+        let outcome = outcome.unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Error));
+        assert!(outcome.status_msg.contains(PATCH_NEEDS_SERDE_MSG));
+        assert!(cmd_rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_dispatch_malformed_lines_send_nothing() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        let (shutdown_tx, _shutdown_rx) = std::sync::mpsc::channel();
+        assert!(matches!(
+            daw.dispatch("   ", &cmd_tx, &shutdown_tx),
+            Dispatch::Blank
+        ));
+        for line in [
+            "gain loud",
+            "gain",
+            "track delete 3",
+            "echo 1 250",
+            "ramp gain 0.5 -10",
+            "preset kazoo",
+            "solo 1",
+            "bounce soon",
+            "frobnicate",
+        ] {
+            let outcome = dispatch(line, &mut daw, &cmd_tx).unwrap();
+            assert!(
+                !matches!(outcome.status_kind, StatusKind::Success),
+                "{line:?} should not succeed"
+            );
+            assert!(!outcome.status_msg.is_empty(), "{line:?} should explain");
+            assert!(!outcome.quit);
+            assert!(cmd_rx.try_recv().is_none(), "{line:?} sent a command");
+        }
+        assert_eq!(daw.session.master_gain, 0.8);
+        assert!(daw.session.tracks.is_empty());
+    }
+
+    #[test]
+    fn test_dispatch_quit_sends_stop() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let mut daw = test_daw();
+        for line in ["quit", "q"] {
+            assert!(matches!(
+                daw.dispatch(line, &cmd_tx, &shutdown_tx),
+                Dispatch::Quit(_)
+            ));
+            assert!(matches!(cmd_rx.try_recv(), Some(Command::Stop)));
+            assert!(shutdown_rx.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn test_dispatch_quit_with_full_queue_signals_shutdown() {
+        let (cmd_tx, cmd_rx) = command_channel(1);
+        cmd_tx.try_send(Command::NoOp).unwrap();
+        let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
+        let mut daw = test_daw();
+        assert!(matches!(
+            daw.dispatch("quit", &cmd_tx, &shutdown_tx),
+            Dispatch::Quit(_)
+        ));
+        assert!(shutdown_rx.try_recv().is_ok());
+        assert!(matches!(cmd_rx.try_recv(), Some(Command::NoOp)));
+        assert!(cmd_rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_dispatch_input_devices_come_from_the_daw() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        let outcome = dispatch("input --list", &mut daw, &cmd_tx).unwrap();
+        assert_eq!(outcome.status_msg, "(no input devices)");
+        dispatch("track create", &mut daw, &cmd_tx).unwrap();
+        while cmd_rx.try_recv().is_some() {}
+
+        let outcome = dispatch("input 1 --device 0", &mut daw, &cmd_tx).unwrap();
+        assert!(matches!(outcome.status_kind, StatusKind::Error));
+        assert!(outcome.status_msg.contains("no input device at index 0"));
+        assert!(cmd_rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_parse_script_steps() {
+        let steps = parse_script("# warm up\ngain 0.2\n\nsleep 50\nquit\n").unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                ScriptStep::Line("gain 0.2".to_string()),
+                ScriptStep::Sleep(Duration::from_millis(50)),
+                ScriptStep::Line("quit".to_string()),
+            ]
+        );
+        assert!(parse_script("sleep soon").is_err());
+    }
+
+    #[test]
+    fn test_script_gain_then_quit_dispatches_commands() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let mut daw = test_daw();
+        let mut quit = false;
+        for step in parse_script("gain 0.2\nquit").unwrap() {
+            let ScriptStep::Line(line) = step else {
+                continue;
+            };
+            let outcome = dispatch(&line, &mut daw, &cmd_tx).unwrap();
+            quit = outcome.quit;
+            if !quit {
+                assert_eq!(daw.session.master_gain, 0.2);
+                assert!(matches!(cmd_rx.try_recv(), Some(Command::SwapGraph(_))));
+                assert!(cmd_rx.try_recv().is_none());
             }
         }
+        assert!(quit, "quit line ends the script");
     }
 }
@@ -20,6 +20,7 @@ use clap::Parser;
 
 const FRAME_COUNT: usize = 4096;
 const INPUT_RING_CAPACITY: usize = 2048;
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Parser, Debug)]
 #[command(name = "memo")]
@@ -112,11 +113,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     thread::sleep(Duration::from_millis(150));
     let samples = record_buf.drain();
 
-    let _ = cmd_tx.try_send(Command::Quit);
-    let _ = shutdown_tx.send(());
+    // Stop fades out and makes run_audio return, dropping the stream; the shutdown channel is
+    // the fallback if the engine can't get there (command ring full, device stalled).
+    let _ = cmd_tx.try_send(Command::Stop);
+    let result = audio_result_rx.recv_timeout(STOP_TIMEOUT).or_else(|_| {
+        let _ = shutdown_tx.send(());
+        audio_result_rx.recv()
+    });
 
     let _ = audio_handle.join();
-    match audio_result_rx.recv() {
+    match result {
         Ok(Ok(())) => {}
         Ok(Err(e)) => return Err(e.into()),
         Err(_) => {
@@ -0,0 +1,157 @@
+//! Spectral analysis helpers for tests and tuning: a small in-place radix-2 FFT plus
+//! [`dominant_frequency`] and [`band_energy`]. Allocates; control thread only.
+
+use std::f32::consts::PI;
+
+/// In-place iterative radix-2 FFT. `re.len()` must equal `im.len()` and be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+    // Bit-reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Power spectrum |X[k]|^2 for k in 0..=N/2 of `samples`, optionally Hann-windowed,
+/// zero-padded to the next power of two N. Returns (spectrum, N).
+fn power_spectrum(samples: &[f32], hann: bool) -> (Vec<f32>, usize) {
+    let n = samples.len().max(2).next_power_of_two();
+    let len = samples.len();
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| {
+            if hann {
+                s * (0.5 - 0.5 * f32::cos(2.0 * PI * i as f32 / len as f32))
+            } else {
+                s
+            }
+        })
+        .chain(std::iter::repeat(0.0))
+        .take(n)
+        .collect();
+    let mut im = vec![0.0f32; n];
+    fft(&mut re, &mut im);
+    let power = (0..=n / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect();
+    (power, n)
+}
+
+/// Frequency in Hz of the strongest spectral peak (DC excluded), refined by parabolic
+/// interpolation between bins. Returns 0.0 for empty or silent input.
+pub fn dominant_frequency(samples: &[f32], sample_rate: u32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let (power, n) = power_spectrum(samples, true);
+    let (peak, &peak_power) = power
+        .iter()
+        .enumerate()
+        .skip(1)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap_or((0, &0.0));
+    if peak_power == 0.0 {
+        return 0.0;
+    }
+    let mut bin = peak as f32;
+    if peak + 1 < power.len() {
+        // Parabola through the log magnitudes of the peak and its neighbours.
+        let (a, b, c) = (
+            power[peak - 1].max(f32::MIN_POSITIVE).ln(),
+            peak_power.ln(),
+            power[peak + 1].max(f32::MIN_POSITIVE).ln(),
+        );
+        let denom = a - 2.0 * b + c;
+        if denom != 0.0 {
+            bin += 0.5 * (a - c) / denom;
+        }
+    }
+    bin * sample_rate as f32 / n as f32
+}
+
+/// Energy of `samples` between `lo` and `hi` Hz (bins with lo <= f < hi). Scaled so that
+/// summing over 0..Nyquist gives the signal's total energy, sum(x^2) (Parseval).
+pub fn band_energy(samples: &[f32], sample_rate: u32, lo: f32, hi: f32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let (power, n) = power_spectrum(samples, false);
+    let bin_hz = sample_rate as f32 / n as f32;
+    power
+        .iter()
+        .enumerate()
+        .filter(|&(k, _)| {
+            let f = k as f32 * bin_hz;
+            f >= lo && f < hi
+        })
+        .map(|(k, &p)| {
+            // One-sided: every bin except DC and Nyquist stands in for its mirror image too.
+            let weight = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+            weight * p / n as f32
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{band_energy, dominant_frequency};
+
+    fn sine(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| f32::sin(2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32))
+            .collect()
+    }
+
+    #[test]
+    fn test_dominant_frequency_of_440_sine() {
+        let f = dominant_frequency(&sine(440.0, 48_000, 8192), 48_000);
+        assert!((f - 440.0).abs() < 1.0, "got {f}");
+    }
+
+    #[test]
+    fn test_band_energy_concentrates_in_signal_band() {
+        let samples = sine(1000.0, 48_000, 8192);
+        let total: f32 = samples.iter().map(|s| s * s).sum();
+        let in_band = band_energy(&samples, 48_000, 900.0, 1100.0);
+        let below = band_energy(&samples, 48_000, 0.0, 500.0);
+        let above = band_energy(&samples, 48_000, 2000.0, 24_000.0);
+        assert!(in_band > 0.95 * total, "in band {in_band} of {total}");
+        assert!(below < 0.01 * total && above < 0.01 * total);
+        let all = band_energy(&samples, 48_000, 0.0, 24_001.0);
+        assert!((all - total).abs() < 1e-3 * total);
+    }
+
+    #[test]
+    fn test_silence_has_no_dominant_frequency() {
+        assert_eq!(dominant_frequency(&[0.0; 256], 48_000), 0.0);
+        assert_eq!(dominant_frequency(&[], 48_000), 0.0);
+    }
+}
@@ -1,4 +1,4 @@
-//! Fixed-size audio buffer for real-time use. Allocated once, reused forever; no allocation after construction.
+//! Fixed-size audio buffers for real-time use. Allocated once, reused forever; no allocation after construction.
 
 /// Fixed-capacity buffer of f32 samples. Safe to use on the audio thread (no allocation, no locks).
 #[derive(Debug)]
@@ -33,6 +33,21 @@ impl AudioBuffer {
         AudioBuffer { storage }
     }
 
+    /// Creates a buffer holding a copy of `data`. Allocates once.
+    pub fn from_slice(data: &[f32]) -> Self {
+        AudioBuffer {
+            storage: data.into(),
+        }
+    }
+
+    /// Creates a buffer that takes ownership of `data`. Reuses its allocation unless it has
+    /// spare capacity, which `into_boxed_slice` has to shrink away.
+    pub fn from_vec(data: Vec<f32>) -> Self {
+        AudioBuffer {
+            storage: data.into_boxed_slice(),
+        }
+    }
+
     /// Returns the number of samples (frames) in the buffer.
     pub fn len(&self) -> usize {
         self.storage.len()
@@ -54,9 +69,68 @@ impl AudioBuffer {
     }
 }
 
+/// One allocation backing a fixed number of equal-length scratch slices, one per graph node.
+/// Slice `i` covers `[i * frame_count, (i + 1) * frame_count)` of the storage, so the slices never
+/// overlap and recompiling a graph costs one allocation however many nodes it has.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScratchPool {
+    storage: Box<[f32]>,
+    frame_count: usize,
+}
+
+impl ScratchPool {
+    /// Creates a zeroed pool of `slots` slices of `frame_count` samples each.
+    pub fn new(slots: usize, frame_count: usize) -> Self {
+        ScratchPool {
+            storage: vec![0.0f32; slots * frame_count].into_boxed_slice(),
+            frame_count,
+        }
+    }
+
+    /// Number of slices in the pool.
+    pub fn slots(&self) -> usize {
+        self.storage
+            .len()
+            .checked_div(self.frame_count)
+            .unwrap_or(0)
+    }
+
+    /// Length of every slice (frames per block).
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Offset of slice `slot` in the backing storage.
+    pub fn offset(&self, slot: usize) -> usize {
+        slot * self.frame_count
+    }
+
+    /// Slice `slot`.
+    pub fn slot(&self, slot: usize) -> &[f32] {
+        let start = self.offset(slot);
+        &self.storage[start..start + self.frame_count]
+    }
+
+    /// Slice `slot` for writing, every slice before it for reading (as one contiguous region;
+    /// index it with [`offset`](Self::offset)), and every slice after it for writing (offsets
+    /// relative to `offset(slot + 1)`). This is what a node in topological order needs: its own
+    /// output, the outputs of the nodes that ran before it, and any temporaries kept at the end.
+    pub fn split_at_slot_mut(&mut self, slot: usize) -> (&[f32], &mut [f32], &mut [f32]) {
+        let start = self.offset(slot);
+        let (head, tail) = self.storage.split_at_mut(start);
+        let (out, after) = tail.split_at_mut(self.frame_count);
+        (head, out, after)
+    }
+
+    /// The whole backing storage.
+    pub fn as_slice(&self) -> &[f32] {
+        &self.storage
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::AudioBuffer;
+    use super::{AudioBuffer, ScratchPool};
 
     #[test]
     /// Test that the buffer is created with the correct length.
@@ -90,4 +164,43 @@ mod tests {
         buffer.as_mut_slice()[0] = 1.0;
         assert_eq!(buffer.as_slice()[0], 1.0);
     }
+
+    #[test]
+    /// Test that from_slice copies length and contents.
+    fn test_from_slice_copies_contents() {
+        let data = [0.25, -0.5, 1.0];
+        let buffer = AudioBuffer::from_slice(&data);
+        assert_eq!(buffer.len(), data.len());
+        assert_eq!(buffer.as_slice(), &data);
+        assert!(AudioBuffer::from_slice(&[]).is_empty());
+    }
+
+    #[test]
+    /// Test that from_vec keeps the vector's allocation instead of copying.
+    fn test_from_vec_reuses_allocation() {
+        let data = vec![0.1f32; 64];
+        assert_eq!(data.capacity(), data.len());
+        let ptr = data.as_ptr();
+        let buffer = AudioBuffer::from_vec(data);
+        assert_eq!(buffer.as_slice().as_ptr(), ptr);
+        assert_eq!(buffer, AudioBuffer::from_slice(&[0.1; 64]));
+    }
+
+    #[test]
+    /// Test that pool slots are disjoint, in order, and writes to one leave the others alone.
+    fn test_scratch_pool_slots_are_disjoint() {
+        let mut pool = ScratchPool::new(3, 4);
+        assert_eq!(pool.slots(), 3);
+        assert_eq!(pool.as_slice().len(), 12);
+        for slot in 0..3 {
+            let offset = pool.offset(slot);
+            let (head, out, after) = pool.split_at_slot_mut(slot);
+            assert_eq!(head.len(), offset);
+            assert_eq!(after.len(), (2 - slot) * 4);
+            out.fill(slot as f32 + 1.0);
+        }
+        assert_eq!(pool.slot(0), [1.0; 4]);
+        assert_eq!(pool.slot(1), [2.0; 4]);
+        assert_eq!(pool.slot(2), [3.0; 4]);
+    }
 }
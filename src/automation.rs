@@ -0,0 +1,444 @@
+//! Parameter automation: the engine can log every command it applies, with the sample it was
+//! applied at, into a [`CommandLog`]; a [`CommandPlayer`] replays such a timeline.
+
+use std::sync::atomic::{fence, AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use crate::command::Command;
+use crate::graph::StableId;
+
+/// A [`Command`] as recorded by a [`CommandLog`]. Every command the engine applies has one, with
+/// two exceptions that keep the log allocation-free: a `SwapGraph` is recorded without its graph
+/// (the engine owns it), and a `Batch` is recorded as the commands in it, each at the batch's
+/// sample and in batch order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoggedCommand {
+    NoOp,
+    SetGain(f32),
+    SetGainAt {
+        gain: f32,
+        at_sample: u64,
+    },
+    RampGain {
+        target: f32,
+        duration_ms: f32,
+    },
+    Pause,
+    Resume,
+    Stop,
+    SetFrequency(f32),
+    SetMute(bool),
+    Freeze(bool),
+    SetMasterLimiter(bool),
+    SoloNode(Option<StableId>),
+    Panic,
+    /// A graph was swapped in; which one is not recorded.
+    SwapGraph,
+    QueryState,
+}
+
+impl LoggedCommand {
+    /// The log entry for `cmd`, or `None` for a `Batch` (the engine logs its commands instead).
+    pub fn from_command(cmd: &Command) -> Option<Self> {
+        Some(match *cmd {
+            Command::NoOp => LoggedCommand::NoOp,
+            Command::SetGain(gain) => LoggedCommand::SetGain(gain),
+            Command::SetGainAt { gain, at_sample } => LoggedCommand::SetGainAt { gain, at_sample },
+            Command::RampGain {
+                target,
+                duration_ms,
+            } => LoggedCommand::RampGain {
+                target,
+                duration_ms,
+            },
+            Command::Pause => LoggedCommand::Pause,
+            Command::Resume => LoggedCommand::Resume,
+            Command::Stop => LoggedCommand::Stop,
+            Command::SetFrequency(hz) => LoggedCommand::SetFrequency(hz),
+            Command::SetMute(muted) => LoggedCommand::SetMute(muted),
+            Command::Freeze(frozen) => LoggedCommand::Freeze(frozen),
+            Command::SetMasterLimiter(enabled) => LoggedCommand::SetMasterLimiter(enabled),
+            Command::SoloNode(id) => LoggedCommand::SoloNode(id),
+            Command::Panic => LoggedCommand::Panic,
+            Command::SwapGraph(_) => LoggedCommand::SwapGraph,
+            Command::QueryState => LoggedCommand::QueryState,
+            Command::Batch(_) => return None,
+        })
+    }
+
+    /// The command to send when replaying this entry, or `None` for `SwapGraph`, whose graph was
+    /// not recorded.
+    pub fn into_command(self) -> Option<Command> {
+        Some(match self {
+            LoggedCommand::NoOp => Command::NoOp,
+            LoggedCommand::SetGain(gain) => Command::SetGain(gain),
+            LoggedCommand::SetGainAt { gain, at_sample } => Command::SetGainAt { gain, at_sample },
+            LoggedCommand::RampGain {
+                target,
+                duration_ms,
+            } => Command::RampGain {
+                target,
+                duration_ms,
+            },
+            LoggedCommand::Pause => Command::Pause,
+            LoggedCommand::Resume => Command::Resume,
+            LoggedCommand::Stop => Command::Stop,
+            LoggedCommand::SetFrequency(hz) => Command::SetFrequency(hz),
+            LoggedCommand::SetMute(muted) => Command::SetMute(muted),
+            LoggedCommand::Freeze(frozen) => Command::Freeze(frozen),
+            LoggedCommand::SetMasterLimiter(enabled) => Command::SetMasterLimiter(enabled),
+            LoggedCommand::SoloNode(id) => Command::SoloNode(id),
+            LoggedCommand::Panic => Command::Panic,
+            LoggedCommand::SwapGraph => return None,
+            LoggedCommand::QueryState => Command::QueryState,
+        })
+    }
+
+    /// Packs the command into two words: the tag in bits 0..8 of the first, a flag in bit 8 and
+    /// an `f32` in bits 32..64; a second `f32` or a `u64` in the other.
+    fn encode(self) -> [u64; 2] {
+        let head = |tag: u64, flag: bool, value: f32| {
+            tag | (flag as u64) << 8 | (value.to_bits() as u64) << 32
+        };
+        match self {
+            LoggedCommand::NoOp => [head(0, false, 0.0), 0],
+            LoggedCommand::SetGain(gain) => [head(1, false, gain), 0],
+            LoggedCommand::SetGainAt { gain, at_sample } => [head(2, false, gain), at_sample],
+            LoggedCommand::RampGain {
+                target,
+                duration_ms,
+            } => [head(3, false, target), duration_ms.to_bits() as u64],
+            LoggedCommand::Pause => [head(4, false, 0.0), 0],
+            LoggedCommand::Resume => [head(5, false, 0.0), 0],
+            LoggedCommand::Stop => [head(6, false, 0.0), 0],
+            LoggedCommand::SetFrequency(hz) => [head(7, false, hz), 0],
+            LoggedCommand::SetMute(muted) => [head(8, muted, 0.0), 0],
+            LoggedCommand::Freeze(frozen) => [head(9, frozen, 0.0), 0],
+            LoggedCommand::SetMasterLimiter(enabled) => [head(10, enabled, 0.0), 0],
+            LoggedCommand::SoloNode(id) => {
+                [head(11, id.is_some(), 0.0), id.map_or(0, StableId::as_u64)]
+            }
+            LoggedCommand::Panic => [head(12, false, 0.0), 0],
+            LoggedCommand::SwapGraph => [head(13, false, 0.0), 0],
+            LoggedCommand::QueryState => [head(14, false, 0.0), 0],
+        }
+    }
+
+    /// Inverse of [`encode`](Self::encode).
+    fn decode([head, tail]: [u64; 2]) -> Self {
+        let flag = head & 1 << 8 != 0;
+        let value = f32::from_bits((head >> 32) as u32);
+        match head & 0xFF {
+            0 => LoggedCommand::NoOp,
+            1 => LoggedCommand::SetGain(value),
+            2 => LoggedCommand::SetGainAt {
+                gain: value,
+                at_sample: tail,
+            },
+            3 => LoggedCommand::RampGain {
+                target: value,
+                duration_ms: f32::from_bits(tail as u32),
+            },
+            4 => LoggedCommand::Pause,
+            5 => LoggedCommand::Resume,
+            6 => LoggedCommand::Stop,
+            7 => LoggedCommand::SetFrequency(value),
+            8 => LoggedCommand::SetMute(flag),
+            9 => LoggedCommand::Freeze(flag),
+            10 => LoggedCommand::SetMasterLimiter(flag),
+            11 => LoggedCommand::SoloNode(flag.then(|| StableId::from_u64(tail))),
+            12 => LoggedCommand::Panic,
+            13 => LoggedCommand::SwapGraph,
+            14 => LoggedCommand::QueryState,
+            tag => unreachable!("command log slot holds unknown tag {tag}"),
+        }
+    }
+}
+
+/// Fixed-capacity ring of `(sample, command)` pairs written by the audio thread (the
+/// [`Engine`](crate::engine::Engine)) and drained by the control thread. Preallocated; when full,
+/// the newest entry overwrites the oldest, so the audio thread never waits or fails. Disarmed by
+/// default.
+///
+/// Works like [`ScopeRing`](crate::ring_buffer::ScopeRing): slots are atomic words, so `push`
+/// may overwrite a slot while `drain` reads it without a data race. `push` claims an index before
+/// writing its slot; `drain` notices such a lap, skips to the oldest entry still held and counts
+/// the skipped ones in [`overwritten`](Self::overwritten), so it never returns a torn entry.
+pub struct CommandLog {
+    armed: AtomicBool,
+    /// Per slot: the sample, then the [`LoggedCommand::encode`] words.
+    slots: Box<[[AtomicU64; 3]]>,
+    /// Producer: one past the newest index whose slot write has started.
+    claimed: AtomicUsize,
+    /// Producer: one past the newest index whose slot is written.
+    published: AtomicUsize,
+    /// Consumer: next index to read.
+    read_pos: AtomicUsize,
+    /// Consumer: entries overwritten before `drain` got to them.
+    overwritten: AtomicUsize,
+}
+
+impl CommandLog {
+    /// Creates a disarmed log holding the latest `capacity` commands.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        CommandLog {
+            armed: AtomicBool::new(false),
+            slots: (0..capacity)
+                .map(|_| std::array::from_fn(|_| AtomicU64::new(0)))
+                .collect(),
+            claimed: AtomicUsize::new(0),
+            published: AtomicUsize::new(0),
+            read_pos: AtomicUsize::new(0),
+            overwritten: AtomicUsize::new(0),
+        }
+    }
+
+    /// Arm (true) or disarm (false) logging. Call from the control thread.
+    pub fn set_armed(&self, armed: bool) {
+        self.armed.store(armed, Ordering::Relaxed);
+    }
+
+    /// Returns true when logging is armed.
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Relaxed)
+    }
+
+    /// Call from the audio thread: records `cmd` as applied at sample `at` when armed,
+    /// overwriting the oldest entry if the log is full (lock-free, no alloc).
+    #[inline]
+    pub fn push(&self, at: u64, cmd: LoggedCommand) {
+        if !self.is_armed() {
+            return;
+        }
+        let write = self.published.load(Ordering::Relaxed);
+        self.claimed.store(write.wrapping_add(1), Ordering::Relaxed);
+        // Orders the claim before the slot write, for the consumer's lap check.
+        fence(Ordering::Release);
+        let slot = &self.slots[write % self.slots.len()];
+        let [head, tail] = cmd.encode();
+        slot[0].store(at, Ordering::Relaxed);
+        slot[1].store(head, Ordering::Relaxed);
+        slot[2].store(tail, Ordering::Relaxed);
+        self.published
+            .store(write.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Takes every entry still held, oldest first. Call from the control thread.
+    pub fn drain(&self) -> Vec<(u64, LoggedCommand)> {
+        let cap = self.slots.len();
+        let mut read = self.read_pos.load(Ordering::Relaxed);
+        let mut out = Vec::new();
+        loop {
+            let write = self.published.load(Ordering::Acquire);
+            if write.wrapping_sub(read) > cap {
+                // Overwritten while we were away: skip to the oldest entry still held.
+                self.skip(&mut read, write.wrapping_sub(cap));
+            }
+            if read == write {
+                break;
+            }
+            let slot = &self.slots[read % cap];
+            let at = slot[0].load(Ordering::Relaxed);
+            let words = [
+                slot[1].load(Ordering::Relaxed),
+                slot[2].load(Ordering::Relaxed),
+            ];
+            // If any load saw a newer entry, the claim for it is visible after this fence.
+            fence(Ordering::Acquire);
+            let claimed = self.claimed.load(Ordering::Relaxed);
+            if claimed.wrapping_sub(read) > cap {
+                self.skip(&mut read, claimed.wrapping_sub(cap));
+                continue;
+            }
+            out.push((at, LoggedCommand::decode(words)));
+            read = read.wrapping_add(1);
+        }
+        self.read_pos.store(read, Ordering::Relaxed);
+        out
+    }
+
+    /// Moves the consumer's `read` to `to`, counting the entries passed over.
+    fn skip(&self, read: &mut usize, to: usize) {
+        self.overwritten
+            .fetch_add(to.wrapping_sub(*read), Ordering::Relaxed);
+        *read = to;
+    }
+
+    /// Number of entries overwritten before [`drain`](Self::drain) took them. Call from any
+    /// thread.
+    pub fn overwritten(&self) -> usize {
+        self.overwritten.load(Ordering::Relaxed)
+    }
+}
+
+/// Replays a timeline from [`CommandLog::drain`] on the control thread: poll it with the
+/// engine's current sample (e.g. from a [`SampleCounter`](crate::engine::SampleCounter)) and
+/// send what comes due. Timestamps are on the clock `now` is read from, so to replay a session
+/// from a later start, shift the timeline by that start first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandPlayer {
+    timeline: Vec<(u64, LoggedCommand)>,
+    next: usize,
+}
+
+impl CommandPlayer {
+    /// Creates a player for `timeline`, sorted by sample (commands at the same sample keep
+    /// their order).
+    pub fn new(mut timeline: Vec<(u64, LoggedCommand)>) -> Self {
+        timeline.sort_by_key(|&(at, _)| at);
+        CommandPlayer { timeline, next: 0 }
+    }
+
+    /// Commands due at or before sample `now` that have not been returned yet, in order.
+    pub fn due(&mut self, now: u64) -> &[(u64, LoggedCommand)] {
+        let start = self.next;
+        while self.next < self.timeline.len() && self.timeline[self.next].0 <= now {
+            self.next += 1;
+        }
+        &self.timeline[start..self.next]
+    }
+
+    /// True once every command has been returned by [`due`](Self::due).
+    pub fn is_finished(&self) -> bool {
+        self.next == self.timeline.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandLog, CommandPlayer, LoggedCommand};
+    use crate::command::Command;
+    use crate::graph::StableId;
+
+    #[test]
+    fn test_log_ignores_pushes_until_armed() {
+        let log = CommandLog::with_capacity(4);
+        log.push(0, LoggedCommand::SetGain(0.5));
+        assert!(log.drain().is_empty());
+        log.set_armed(true);
+        log.push(10, LoggedCommand::SetGain(0.5));
+        assert_eq!(log.drain(), [(10, LoggedCommand::SetGain(0.5))]);
+        assert!(log.drain().is_empty());
+    }
+
+    #[test]
+    fn test_full_log_overwrites_oldest_and_counts_them() {
+        let log = CommandLog::with_capacity(2);
+        log.set_armed(true);
+        for at in 0..5 {
+            log.push(at, LoggedCommand::SetMute(at % 2 == 1));
+        }
+        assert_eq!(
+            log.drain(),
+            [
+                (3, LoggedCommand::SetMute(true)),
+                (4, LoggedCommand::SetMute(false))
+            ]
+        );
+        assert_eq!(log.overwritten(), 3);
+        log.push(5, LoggedCommand::SetMute(true));
+        assert_eq!(log.drain(), [(5, LoggedCommand::SetMute(true))]);
+    }
+
+    #[test]
+    fn test_every_logged_command_survives_the_slot_encoding() {
+        let commands = [
+            LoggedCommand::NoOp,
+            LoggedCommand::SetGain(-0.25),
+            LoggedCommand::SetGainAt {
+                gain: 0.5,
+                at_sample: u64::MAX,
+            },
+            LoggedCommand::RampGain {
+                target: 1.5,
+                duration_ms: 20.0,
+            },
+            LoggedCommand::Pause,
+            LoggedCommand::Resume,
+            LoggedCommand::Stop,
+            LoggedCommand::SetFrequency(440.0),
+            LoggedCommand::SetMute(true),
+            LoggedCommand::Freeze(true),
+            LoggedCommand::SetMasterLimiter(false),
+            LoggedCommand::SoloNode(Some(StableId::from_u64(7))),
+            LoggedCommand::SoloNode(None),
+            LoggedCommand::Panic,
+            LoggedCommand::SwapGraph,
+            LoggedCommand::QueryState,
+        ];
+        for cmd in commands {
+            assert_eq!(LoggedCommand::decode(cmd.encode()), cmd);
+            match cmd.into_command() {
+                Some(back) => assert_eq!(LoggedCommand::from_command(&back), Some(cmd)),
+                None => assert_eq!(cmd, LoggedCommand::SwapGraph),
+            }
+        }
+        assert_eq!(
+            LoggedCommand::from_command(&Command::Batch(Default::default())),
+            None
+        );
+    }
+
+    #[test]
+    /// A consumer racing an overwriting producer only sees whole entries, in push order.
+    fn test_threaded_drain_never_sees_torn_entries() {
+        use std::sync::Arc;
+        use std::thread;
+
+        const COUNT: u64 = 200_000;
+        let log = Arc::new(CommandLog::with_capacity(16));
+        log.set_armed(true);
+        let producer = {
+            let log = Arc::clone(&log);
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    log.push(
+                        i,
+                        LoggedCommand::SetGainAt {
+                            gain: i as f32,
+                            at_sample: i,
+                        },
+                    );
+                }
+            })
+        };
+        let mut next = 0;
+        let mut seen = 0u64;
+        while next < COUNT {
+            for (at, cmd) in log.drain() {
+                assert!(at >= next, "{at} after {}", next - 1);
+                assert_eq!(
+                    cmd,
+                    LoggedCommand::SetGainAt {
+                        gain: at as f32,
+                        at_sample: at,
+                    }
+                );
+                next = at + 1;
+                seen += 1;
+            }
+            thread::yield_now();
+        }
+        producer.join().unwrap();
+        assert_eq!(seen + log.overwritten() as u64, COUNT);
+    }
+
+    #[test]
+    fn test_player_returns_commands_as_they_come_due() {
+        let mut player = CommandPlayer::new(vec![
+            (500, LoggedCommand::SetMute(true)),
+            (0, LoggedCommand::SetGain(0.5)),
+            (100, LoggedCommand::SetFrequency(220.0)),
+        ]);
+        assert_eq!(player.due(0), [(0, LoggedCommand::SetGain(0.5))]);
+        assert!(player.due(99).is_empty());
+        assert_eq!(
+            player.due(600),
+            [
+                (100, LoggedCommand::SetFrequency(220.0)),
+                (500, LoggedCommand::SetMute(true))
+            ]
+        );
+        assert!(player.is_finished());
+    }
+}
@@ -2,19 +2,151 @@
 
 use std::sync::Arc;
 
-use crate::graph::CompiledGraph;
+use crate::graph::{CompiledGraph, StableId};
 use crate::ring_buffer::RingBuffer;
 
 /// Instruction from the control thread to the audio thread.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     NoOp,
-    /// Control says: set gain to this value (applies to hardcoded chain when no graph is set).
+    /// Set the engine's master gain, applied to the output after the graph.
     SetGain(f32),
-    Quit,
+    /// Set the gain when the engine's sample clock reaches `at_sample`, mid-block if need be.
+    /// A time already past applies at once. Only one change is pending at a time; a newer one
+    /// replaces it.
+    SetGainAt {
+        gain: f32,
+        at_sample: u64,
+    },
+    /// Ramp the master gain linearly from its current value to `target` over `duration_ms`.
+    /// A new ramp (or SetGain) replaces one in progress.
+    RampGain {
+        target: f32,
+        duration_ms: f32,
+    },
+    /// Fade the output to silence and stop running the graph, keeping the stream open; graph
+    /// state is kept, and `Resume` fades back in where it left off.
+    Pause,
+    /// Undo `Pause`.
     Resume,
+    /// Fade out like `Pause`, then end the stream: `run_audio` returns and
+    /// [`StreamHandle::is_stopped`](crate::StreamHandle::is_stopped) turns true. Final; `Resume`
+    /// does not undo it.
+    Stop,
+    /// Set the engine's frequency (reported by QueryState) and retune every oscillator in the
+    /// running graph to it: each node that takes [`ParamId::Frequency`](crate::graph::ParamId)
+    /// (Sine, Quadrature). They all get the same pitch, so a chord collapses to one note; to
+    /// retune a single voice, change it with [`CompiledGraph::node_mut`] and swap the graph in.
+    SetFrequency(f32),
+    /// Mute (`true`) or unmute the output with a short declick ramp. Unlike `Pause`, the graph
+    /// keeps running while muted, so unmuting picks up where the signal would be.
+    SetMute(bool),
+    /// Freeze (`true`): loop the last rendered block instead of running the graph, for glitch
+    /// and ambient effects. `false` resumes the graph where it stopped.
+    Freeze(bool),
+    /// Turn the engine's master brickwall limiter (0 dBFS, after the master gain) on or off.
+    /// On by default.
+    SetMasterLimiter(bool),
+    /// Debug aid: output one node of the running graph instead of its last node, bypassing
+    /// everything downstream (see [`CompiledGraph::set_solo`]). `None` restores normal routing.
+    /// Applies to the running graph only; a swapped-in graph plays unsoloed unless it was
+    /// soloed before sending.
+    SoloNode(Option<StableId>),
+    /// All-notes-off: clear the signal memory of every node in the running graph (echo tails,
+    /// filter ringing, envelopes; see [`Processor::reset`](crate::processor::Processor::reset)),
+    /// end any swap crossfade and Freeze, and fade the output back in from silence. Parameters
+    /// and the master gain are kept.
+    Panic,
     /// Swap in a new compiled graph; the previous one (if any) is returned via Event::GraphSwapped.
     SwapGraph(CompiledGraph),
+    /// Ask the engine to report its current state via Event::State.
+    QueryState,
+    /// Several parameter changes applied together, in order, within one callback: no other
+    /// command and no rendered sample comes between them.
+    Batch(CommandBatch),
+}
+
+/// Most commands a [`CommandBatch`] holds. Inline storage keeps `Command` fixed-size.
+pub const MAX_BATCH_COMMANDS: usize = 4;
+
+/// Parameter change that can go in a [`CommandBatch`]: the small, `Copy` subset of [`Command`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamCommand {
+    SetGain(f32),
+    SetGainAt { gain: f32, at_sample: u64 },
+    RampGain { target: f32, duration_ms: f32 },
+    SetFrequency(f32),
+    SetMute(bool),
+    Freeze(bool),
+    SetMasterLimiter(bool),
+}
+
+impl From<ParamCommand> for Command {
+    fn from(cmd: ParamCommand) -> Self {
+        match cmd {
+            ParamCommand::SetGain(gain) => Command::SetGain(gain),
+            ParamCommand::SetGainAt { gain, at_sample } => Command::SetGainAt { gain, at_sample },
+            ParamCommand::RampGain {
+                target,
+                duration_ms,
+            } => Command::RampGain {
+                target,
+                duration_ms,
+            },
+            ParamCommand::SetFrequency(hz) => Command::SetFrequency(hz),
+            ParamCommand::SetMute(muted) => Command::SetMute(muted),
+            ParamCommand::Freeze(frozen) => Command::Freeze(frozen),
+            ParamCommand::SetMasterLimiter(enabled) => Command::SetMasterLimiter(enabled),
+        }
+    }
+}
+
+/// Up to [`MAX_BATCH_COMMANDS`] parameter changes, stored inline (no allocation).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandBatch {
+    commands: [ParamCommand; MAX_BATCH_COMMANDS],
+    len: usize,
+}
+
+impl CommandBatch {
+    /// Creates an empty batch.
+    pub fn new() -> Self {
+        CommandBatch {
+            commands: [ParamCommand::SetMute(false); MAX_BATCH_COMMANDS],
+            len: 0,
+        }
+    }
+
+    /// Appends `cmd`. Returns `Err(cmd)` if the batch is full.
+    pub fn push(&mut self, cmd: ParamCommand) -> Result<(), ParamCommand> {
+        if self.len == MAX_BATCH_COMMANDS {
+            return Err(cmd);
+        }
+        self.commands[self.len] = cmd;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The batched commands, in the order they were pushed.
+    pub fn commands(&self) -> &[ParamCommand] {
+        &self.commands[..self.len]
+    }
+
+    /// Number of commands pushed so far, at most [`MAX_BATCH_COMMANDS`].
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if nothing has been pushed; applying an empty batch does nothing.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Default for CommandBatch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Producer side of the command channel. Only the control thread should hold this.
@@ -65,6 +197,21 @@ mod tests {
         assert_eq!(command1, command2);
     }
 
+    #[test]
+    fn test_command_batch_is_bounded() {
+        use super::{CommandBatch, ParamCommand, MAX_BATCH_COMMANDS};
+
+        let mut batch = CommandBatch::new();
+        for i in 0..MAX_BATCH_COMMANDS {
+            batch.push(ParamCommand::SetGain(i as f32)).unwrap();
+        }
+        assert_eq!(
+            batch.push(ParamCommand::SetMute(true)),
+            Err(ParamCommand::SetMute(true))
+        );
+        assert_eq!(batch.commands()[1], ParamCommand::SetGain(1.0));
+    }
+
     #[test]
     fn test_command_channel_send_recv() {
         let (sender, receiver) = command_channel(4);
@@ -0,0 +1,257 @@
+//! Impulse-response convolution (e.g. convolution reverb), built on `rustfft`. Only compiled with
+//! the `convolution` feature.
+//!
+//! Zero-latency partitioned convolution: the first `block` taps of the IR run as a direct FIR,
+//! and the rest is split into `block`-sized partitions convolved by FFT (uniformly partitioned
+//! overlap-save with a frequency-domain delay line). The FFT part has exactly one block of
+//! latency, which is the offset of the first FFT partition, so nothing is delayed overall.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex32;
+use rustfft::{Fft, FftPlanner};
+
+use crate::file_feeder::{load_wav_at_rate, FileFeederError};
+use crate::processor::Processor;
+
+/// Convolves its input with an impulse response. All buffers and FFT plans are set up in `new`;
+/// `process` does not allocate and accepts any block length.
+#[derive(Clone)]
+pub struct Convolver {
+    /// Partition size in samples (power of two); FFT size is twice this.
+    block: usize,
+    /// First `block` taps of the IR, applied directly.
+    head: Vec<f32>,
+    /// Last `block` inputs (ring), for the direct FIR.
+    history: Vec<f32>,
+    history_pos: usize,
+    /// Spectra of IR partitions 1.. (each zero-padded to the FFT size).
+    partitions: Vec<Vec<Complex32>>,
+    /// Spectra of past input blocks, newest at `fdl_pos`; one slot per partition.
+    fdl: Vec<Vec<Complex32>>,
+    fdl_pos: usize,
+    /// Previous and current input block for overlap-save.
+    prev_block: Vec<f32>,
+    cur_block: Vec<f32>,
+    fill: usize,
+    /// FFT-partition output for the block being filled.
+    tail_out: Vec<f32>,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    work: Vec<Complex32>,
+    acc: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+
+impl Convolver {
+    /// Creates a convolver for `ir` with `block`-sample partitions (rounded up to a power of
+    /// two). Match `block` to the graph's block size; larger partitions mean fewer FFTs but a
+    /// longer direct FIR per sample.
+    pub fn new(ir: &[f32], block: usize) -> Self {
+        let block = block.max(1).next_power_of_two();
+        let n = 2 * block;
+        let mut planner = FftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(n);
+        let ifft = planner.plan_fft_inverse(n);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+        let mut scratch = vec![Complex32::default(); scratch_len];
+
+        let head: Vec<f32> = ir.iter().take(block).copied().collect();
+        let partitions: Vec<Vec<Complex32>> = ir
+            .chunks(block)
+            .skip(1)
+            .map(|part| {
+                let mut spectrum = vec![Complex32::default(); n];
+                for (s, &x) in spectrum.iter_mut().zip(part) {
+                    s.re = x;
+                }
+                fft.process_with_scratch(&mut spectrum, &mut scratch);
+                spectrum
+            })
+            .collect();
+        let fdl = vec![vec![Complex32::default(); n]; partitions.len()];
+        Convolver {
+            block,
+            head,
+            history: vec![0.0; block],
+            history_pos: 0,
+            partitions,
+            fdl,
+            fdl_pos: 0,
+            prev_block: vec![0.0; block],
+            cur_block: vec![0.0; block],
+            fill: 0,
+            tail_out: vec![0.0; block],
+            fft,
+            ifft,
+            work: vec![Complex32::default(); n],
+            acc: vec![Complex32::default(); n],
+            scratch,
+        }
+    }
+
+    /// Loads the IR from a WAV file (downmixed to mono, resampled to `sample_rate`).
+    pub fn from_wav(
+        path: impl AsRef<Path>,
+        sample_rate: u32,
+        block: usize,
+    ) -> Result<Self, FileFeederError> {
+        let ir = load_wav_at_rate(path, sample_rate)?;
+        Ok(Self::new(&ir, block))
+    }
+
+    /// Partition size in samples.
+    pub fn block(&self) -> usize {
+        self.block
+    }
+
+    /// Runs when `cur_block` is full: pushes its spectrum into the delay line and computes the
+    /// FFT partitions' output for the next block.
+    fn finish_block(&mut self) {
+        let (b, n) = (self.block, 2 * self.block);
+        let slots = self.partitions.len();
+        if slots > 0 {
+            for (w, &x) in self
+                .work
+                .iter_mut()
+                .zip(self.prev_block.iter().chain(&self.cur_block))
+            {
+                *w = Complex32::new(x, 0.0);
+            }
+            self.fft
+                .process_with_scratch(&mut self.work, &mut self.scratch);
+            self.fdl_pos = (self.fdl_pos + 1) % slots;
+            self.fdl[self.fdl_pos].copy_from_slice(&self.work);
+
+            self.acc.fill(Complex32::default());
+            for (p, h) in self.partitions.iter().enumerate() {
+                // Partition p + 1 pairs with the input block p blocks before the newest.
+                let x = &self.fdl[(self.fdl_pos + slots - p) % slots];
+                for ((a, &xi), &hi) in self.acc.iter_mut().zip(x).zip(h) {
+                    *a += xi * hi;
+                }
+            }
+            self.ifft
+                .process_with_scratch(&mut self.acc, &mut self.scratch);
+            let scale = 1.0 / n as f32;
+            for (out, y) in self.tail_out.iter_mut().zip(&self.acc[b..]) {
+                *out = y.re * scale;
+            }
+        }
+        std::mem::swap(&mut self.prev_block, &mut self.cur_block);
+        self.fill = 0;
+    }
+}
+
+impl Processor for Convolver {
+    /// The IR length (less the sample that lines up with the input).
+    fn tail_samples(&self) -> usize {
+        (self.head.len() + self.partitions.len() * self.block).saturating_sub(1)
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let b = self.block;
+        for (i, out) in output.iter_mut().enumerate() {
+            let x = inp.get(i).copied().unwrap_or(0.0);
+            self.history[self.history_pos] = x;
+            let mut y = 0.0;
+            for (k, &h) in self.head.iter().enumerate() {
+                y += h * self.history[(self.history_pos + b - k) % b];
+            }
+            self.history_pos = (self.history_pos + 1) % b;
+            *out = y + self.tail_out[self.fill];
+            self.cur_block[self.fill] = x;
+            self.fill += 1;
+            if self.fill == b {
+                self.finish_block();
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.history_pos = 0;
+        for spectrum in &mut self.fdl {
+            spectrum.fill(Complex32::default());
+        }
+        self.fdl_pos = 0;
+        self.prev_block.fill(0.0);
+        self.cur_block.fill(0.0);
+        self.fill = 0;
+        self.tail_out.fill(0.0);
+    }
+}
+
+impl std::fmt::Debug for Convolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Convolver")
+            .field("block", &self.block)
+            .field(
+                "ir_len",
+                &(self.head.len() + self.partitions.len() * self.block),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Two convolvers are equal if they were built from the same IR and partition size.
+impl PartialEq for Convolver {
+    fn eq(&self, other: &Self) -> bool {
+        self.block == other.block && self.head == other.head && self.partitions == other.partitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Convolver;
+    use crate::processor::Processor;
+    use crate::rng::SmallRng;
+
+    /// Deterministic test signal in [-1, 1).
+    fn signal(len: usize, seed: u64) -> Vec<f32> {
+        let mut rng = SmallRng::from_seed(seed);
+        (0..len).map(|_| rng.next_f32_unit()).collect()
+    }
+
+    #[test]
+    fn test_unit_impulse_passes_input_through() {
+        let input = signal(1000, 7);
+        let mut conv = Convolver::new(&[1.0], 64);
+        let mut out = vec![0.0f32; 1000];
+        conv.process(&[&input], &mut out);
+        for (o, x) in out.iter().zip(&input) {
+            assert!((o - x).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_long_ir_matches_direct_convolution() {
+        // IR spans several partitions; process in uneven chunks to cross block boundaries.
+        let ir = signal(300, 3);
+        let input = signal(1000, 11);
+        let mut expected = vec![0.0f32; 1000];
+        for (n, e) in expected.iter_mut().enumerate() {
+            for (k, &h) in ir.iter().enumerate().take(n + 1) {
+                *e += h * input[n - k];
+            }
+        }
+        let mut conv = Convolver::new(&ir, 64);
+        let mut out = vec![0.0f32; 1000];
+        let mut start = 0;
+        for len in [100, 37, 64, 299, 500].iter().cycle() {
+            if start >= 1000 {
+                break;
+            }
+            let end = (start + len).min(1000);
+            conv.process(&[&input[start..end]], &mut out[start..end]);
+            start = end;
+        }
+        for (i, (o, e)) in out.iter().zip(&expected).enumerate() {
+            assert!((o - e).abs() < 1e-3, "sample {i}: {o} vs {e}");
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Hardware flush-to-zero for the render. Denormal floats (magnitudes below ~1e-38) can make
+//! arithmetic 10–100x slower on some CPUs, and any recursive node whose state decays toward zero
+//! passes through them. Nodes snap their own state (see `nodes::flush_denormals`); this guard
+//! instead sets the CPU's flush-to-zero / denormals-are-zero mode for a whole callback.
+//!
+//! Supported on x86_64 (MXCSR FTZ + DAZ) and aarch64 (FPCR FZ). Elsewhere the guard does
+//! nothing.
+//!
+//! # Caveats
+//!
+//! The mode register is per thread. The guard saves it on creation and restores it on drop, so
+//! code running on the same thread after the render (including the host's own audio code) sees
+//! the mode it had before; the guard is `!Send` so it cannot be dropped on another thread.
+//!
+//! Rust assumes the default floating-point environment, so code run under the guard is outside
+//! the language's guarantees: the compiler may constant-fold a denormal result that the CPU
+//! would flush, and library math may behave differently. Keep the guarded region to the render
+//! itself, where snapping tiny values to zero is the intent.
+
+use std::marker::PhantomData;
+
+/// Sets flush-to-zero mode on the current thread while alive; restores the previous mode when
+/// dropped. Create it on the thread that renders, and keep it on the stack.
+#[must_use = "the mode is restored as soon as the guard is dropped"]
+pub struct DenormalGuard {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    saved: u64,
+    /// Keeps the guard on the thread whose mode it saved.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl DenormalGuard {
+    /// True if this target has a flush-to-zero mode the guard can set.
+    pub const fn is_supported() -> bool {
+        cfg!(any(target_arch = "x86_64", target_arch = "aarch64"))
+    }
+
+    /// Turns flush-to-zero on for the current thread.
+    pub fn new() -> Self {
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            let saved = arch::read();
+            arch::write(saved | arch::FLUSH_BITS);
+            DenormalGuard {
+                saved,
+                _not_send: PhantomData,
+            }
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        DenormalGuard {
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Default for DenormalGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DenormalGuard {
+    fn drop(&mut self) {
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        arch::write(self.saved);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use std::arch::asm;
+
+    /// MXCSR flush-to-zero (bit 15) and denormals-are-zero (bit 6).
+    pub const FLUSH_BITS: u64 = 0x8040;
+
+    pub fn read() -> u64 {
+        let mut csr: u32 = 0;
+        // SAFETY: stmxcsr only stores the SSE control register (always present on x86_64) to
+        // the given address, which points at a live local.
+        unsafe { asm!("stmxcsr [{}]", in(reg) &mut csr, options(nostack, preserves_flags)) };
+        csr as u64
+    }
+
+    pub fn write(value: u64) {
+        let csr = value as u32;
+        // SAFETY: ldmxcsr loads the SSE control register from a live local; `value` is always a
+        // previously read value, optionally with the FTZ/DAZ bits set, so no reserved bit is set.
+        unsafe { asm!("ldmxcsr [{}]", in(reg) &csr, options(nostack, readonly, preserves_flags)) };
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use std::arch::asm;
+
+    /// FPCR flush-to-zero (bit 24).
+    pub const FLUSH_BITS: u64 = 1 << 24;
+
+    pub fn read() -> u64 {
+        let fpcr: u64;
+        // SAFETY: reading FPCR is allowed at EL0 and has no side effects.
+        unsafe { asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack, preserves_flags)) };
+        fpcr
+    }
+
+    pub fn write(value: u64) {
+        // SAFETY: writing FPCR is allowed at EL0; `value` is a previously read value, optionally
+        // with FZ set.
+        unsafe { asm!("msr fpcr, {}", in(reg) value, options(nomem, nostack, preserves_flags)) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DenormalGuard;
+    use std::hint::black_box;
+
+    /// 1e-20 * 1e-20 = 1e-40: a denormal unless flushed.
+    fn tiny_product() -> f32 {
+        black_box(1e-20f32) * black_box(1e-20f32)
+    }
+
+    #[test]
+    fn test_guard_flushes_denormals_and_restores_on_drop() {
+        assert_ne!(tiny_product(), 0.0, "denormals are kept by default");
+        {
+            let _guard = DenormalGuard::new();
+            if DenormalGuard::is_supported() {
+                assert_eq!(tiny_product(), 0.0);
+            } else {
+                assert_ne!(tiny_product(), 0.0, "no-op where unsupported");
+            }
+            // Normal numbers are untouched either way.
+            assert_eq!(black_box(0.5f32) * black_box(0.25f32), 0.125);
+        }
+        assert_ne!(tiny_product(), 0.0, "previous mode restored");
+    }
+
+    #[test]
+    fn test_nested_guards_restore_outer_mode() {
+        let outer = DenormalGuard::new();
+        drop(DenormalGuard::new());
+        if DenormalGuard::is_supported() {
+            assert_eq!(
+                tiny_product(),
+                0.0,
+                "inner drop keeps the outer guard's mode"
+            );
+        }
+        drop(outer);
+        assert_ne!(tiny_product(), 0.0);
+    }
+
+    /// Feedback comb (a one-line reverb) with no denormal snapping of its own, as a user's
+    /// `Dynamic` node might be. Its tail keeps the level it was seeded with, within 20%, for the
+    /// length of the render below, so a subnormal seed keeps it on the subnormal path.
+    #[derive(Clone)]
+    struct RawComb {
+        line: Vec<f32>,
+        pos: usize,
+    }
+
+    impl crate::processor::Processor for RawComb {
+        fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+            for out in output.iter_mut() {
+                let y = self.line[self.pos];
+                self.line[self.pos] = y * 0.9999;
+                self.pos = (self.pos + 1) % self.line.len();
+                *out = y;
+            }
+        }
+
+        fn num_inputs(&self) -> usize {
+            0
+        }
+    }
+
+    /// Fastest of a few flush-to-zero renders of a comb tail seeded at `level`.
+    fn tail_render_time(level: f32) -> std::time::Duration {
+        use crate::command::Command;
+        use crate::engine::Engine;
+        use crate::event::event_channel;
+        use crate::graph::{AudioGraph, GraphNode};
+        use std::time::Instant;
+
+        let (evt_tx, _evt_rx) = event_channel(4);
+        (0..5)
+            .map(|_| {
+                let mut g = AudioGraph::new();
+                g.add_node(GraphNode::Dynamic(Box::new(RawComb {
+                    line: vec![level; 97],
+                    pos: 0,
+                })));
+                let mut engine = Engine::new(48_000, 440.0, 1.0);
+                engine.set_flush_denormals(true);
+                engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+                let mut buf = vec![0.0f32; 512];
+                let start = Instant::now();
+                for _ in 0..200 {
+                    engine.tick(black_box(&mut buf));
+                }
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "wall-clock timing; flaky on loaded CI machines. Run with --ignored when tuning"]
+    fn test_subnormal_tail_renders_about_as_fast_as_a_normal_one() {
+        if !DenormalGuard::is_supported() {
+            return;
+        }
+        // 1e-39 is below f32::MIN_POSITIVE, so the comb runs on denormals unless flushed.
+        let normal = tail_render_time(0.5);
+        let subnormal = tail_render_time(1e-39);
+        assert!(
+            subnormal < normal * 3,
+            "subnormal tail took {subnormal:?}, normal {normal:?}"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Dither for float-to-integer conversion. Rounding a quiet signal straight to integers turns
+//! the quantization error into distortion that tracks the signal; adding triangular-PDF noise
+//! first makes the error signal-independent (plain hiss) at the cost of a slightly higher floor.
+
+use crate::rng::SmallRng;
+
+/// Adds TPDF dither of +/- 1 LSB to `sample`, which must already be scaled to the target
+/// integer range (1.0 = one LSB). Round the result to get the quantized value.
+#[inline]
+pub fn apply_tpdf(sample: f32, rng: &mut SmallRng) -> f32 {
+    // Each unit value spans 2 LSB; halving the difference gives a triangle over -1..1 LSB.
+    sample + (rng.next_f32_unit() - rng.next_f32_unit()) * 0.5
+}
+
+/// Quantizes `sample` (1.0 = full scale) to a signed `bits`-wide integer with TPDF dither,
+/// clamping out-of-range samples to full scale.
+#[inline]
+pub fn quantize_tpdf(sample: f32, bits: u16, rng: &mut SmallRng) -> i32 {
+    let max = ((1i32 << (bits - 1)) - 1) as f32;
+    apply_tpdf(sample * max, rng).round().clamp(-max - 1.0, max) as i32
+}
+
+/// A sample for a signed 16-bit output device, dithered.
+#[inline]
+pub fn to_i16(sample: f32, rng: &mut SmallRng) -> i16 {
+    quantize_tpdf(sample, 16, rng) as i16
+}
+
+/// A sample for an unsigned 16-bit output device (silence at 32768), dithered.
+#[inline]
+pub fn to_u16(sample: f32, rng: &mut SmallRng) -> u16 {
+    (quantize_tpdf(sample, 16, rng) + 32768) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_tpdf, to_i16, to_u16};
+    use crate::rng::SmallRng;
+
+    /// Largest per-phase mean quantization error of a `period`-sample sine at `amp` LSB,
+    /// averaged over many periods. Signal-correlated error (distortion) shows up as a
+    /// nonzero mean at some phase; decorrelated error averages out.
+    fn worst_phase_error(amp: f32, dither: bool) -> f32 {
+        const PERIOD: usize = 16;
+        const PERIODS: usize = 4096;
+        let mut rng = SmallRng::default();
+        let mut sums = [0.0f32; PERIOD];
+        for i in 0..PERIOD * PERIODS {
+            let x = amp * (2.0 * std::f32::consts::PI * i as f32 / PERIOD as f32).sin();
+            let q = if dither { apply_tpdf(x, &mut rng) } else { x }.round();
+            sums[i % PERIOD] += q - x;
+        }
+        sums.iter()
+            .map(|s| (s / PERIODS as f32).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_tpdf_decorrelates_low_level_quantization_error() {
+        // A sine below one LSB rounds to a crude square wave without dither.
+        assert!(worst_phase_error(0.7, false) > 0.2);
+        assert!(worst_phase_error(0.7, true) < 0.05);
+    }
+
+    #[test]
+    fn test_16_bit_conversion_clamps_and_centers() {
+        let mut rng = SmallRng::default();
+        assert_eq!(to_i16(2.0, &mut rng), i16::MAX);
+        assert_eq!(to_i16(-2.0, &mut rng), i16::MIN);
+        assert_eq!(to_u16(2.0, &mut rng), u16::MAX);
+        assert_eq!(to_u16(-2.0, &mut rng), 0);
+        for _ in 0..1000 {
+            assert!((-1..=1).contains(&to_i16(0.0, &mut rng)));
+            assert!((32767..=32769).contains(&to_u16(0.0, &mut rng)));
+        }
+    }
+
+    #[test]
+    fn test_tpdf_stays_within_one_lsb() {
+        let mut rng = SmallRng::from_seed(1);
+        for _ in 0..10_000 {
+            let d = apply_tpdf(0.0, &mut rng);
+            assert!(d > -1.0 && d < 1.0);
+        }
+    }
+}
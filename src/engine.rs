@@ -1,13 +1,244 @@
 //! Engine: runs a compiled graph on the audio thread,
 //! draining commands at the top of each callback.
 
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::automation::{CommandLog, LoggedCommand};
 use crate::command::{Command, CommandReceiver};
+use crate::denormal::DenormalGuard;
 use crate::event::{Event, EventSender};
-use crate::graph::CompiledGraph;
-use crate::nodes::GainProcessor;
+use crate::graph::{CompiledGraph, ParamId};
+use crate::nodes::Limiter;
+use crate::processor::Processor;
+
+/// Length of the fade-out on `Pause` and `Stop`, in milliseconds. Long enough to avoid a click,
+/// short enough to feel instant.
+const FADE_OUT_MS: f32 = 5.0;
+/// Release time of the master limiter, in milliseconds.
+const MASTER_LIMITER_RELEASE_MS: f32 = 100.0;
+/// Largest master gain a command may set (+12 dB); more is almost certainly a typo.
+pub const MAX_GAIN: f32 = 4.0;
+/// Lowest frequency `SetFrequency` accepts (Hz).
+pub const MIN_FREQUENCY_HZ: f32 = 1.0;
+/// Highest master gain, and limiter ceiling, in safe mode: -12 dBFS.
+pub const SAFE_MODE_MAX_GAIN: f32 = 0.251_188_64;
+/// Length of the soft start: the fade-in when the engine first renders and on `Resume`.
+const FADE_IN_MS: f32 = 10.0;
+
+/// Largest compiled block size the engine's [`BlockAdapter`] can buffer. Graphs compiled for
+/// more frames than this are rendered in sub-blocks without adaptation.
+const MAX_ADAPTER_FRAMES: usize = 8192;
+
+/// Longest block `Command::Freeze` can capture, in samples; longer blocks are cut to this.
+const MAX_FREEZE_FRAMES: usize = 8192;
+
+/// Peak above which the clip indicator turns on (0 dBFS).
+const CLIP_ON_LEVEL: f32 = 1.0;
+/// Peak the output must stay under, for `CLIP_HOLD_BLOCKS` callbacks in a row, before the clip
+/// indicator turns off again.
+const CLIP_OFF_LEVEL: f32 = 0.9;
+/// Quiet callbacks needed to clear the clip indicator; the hysteresis keeps it from flickering.
+const CLIP_HOLD_BLOCKS: u32 = 20;
+
+/// Clip light with hysteresis: on as soon as a block peaks over `CLIP_ON_LEVEL`, off only after
+/// `CLIP_HOLD_BLOCKS` consecutive blocks peaking under `CLIP_OFF_LEVEL`.
+#[derive(Debug, Default)]
+struct ClipIndicator {
+    on: bool,
+    quiet_blocks: u32,
+}
+
+impl ClipIndicator {
+    /// Feeds one block's peak; returns the new state when it changes.
+    fn update(&mut self, peak: f32) -> Option<bool> {
+        if peak > CLIP_ON_LEVEL {
+            self.quiet_blocks = 0;
+            if !self.on {
+                self.on = true;
+                return Some(true);
+            }
+        } else if self.on {
+            if peak < CLIP_OFF_LEVEL {
+                self.quiet_blocks += 1;
+                if self.quiet_blocks >= CLIP_HOLD_BLOCKS {
+                    self.on = false;
+                    self.quiet_blocks = 0;
+                    return Some(false);
+                }
+            } else {
+                self.quiet_blocks = 0;
+            }
+        }
+        None
+    }
+}
+
+/// How long a running graph must output nothing but zeros before `Event::UnexpectedSilence` is
+/// sent, in milliseconds of audio (whatever the callback size).
+const SILENCE_WATCHDOG_MS: f32 = 1000.0;
+
+/// Notices a graph that has gone completely silent although nothing asked for silence.
+#[derive(Debug)]
+struct SilenceWatchdog {
+    /// Unexpectedly silent frames in a row.
+    silent_frames: u64,
+    /// `SILENCE_WATCHDOG_MS` in frames at the engine's sample rate.
+    limit_frames: u64,
+    reported: bool,
+}
+
+impl SilenceWatchdog {
+    fn new(sample_rate: u32) -> Self {
+        SilenceWatchdog {
+            silent_frames: 0,
+            limit_frames: ((SILENCE_WATCHDOG_MS / 1000.0 * sample_rate as f32) as u64).max(1),
+            reported: false,
+        }
+    }
+
+    /// Feeds one callback's peak over `frames` frames, and whether silence is expected (muted,
+    /// paused, no graph, zero gain). Returns true once `SILENCE_WATCHDOG_MS` of unexpected
+    /// silence has run in a row; again only after the output has made a sound.
+    fn update(&mut self, peak: f32, frames: usize, expected: bool) -> bool {
+        if peak != 0.0 {
+            self.silent_frames = 0;
+            self.reported = false;
+        } else if expected {
+            self.silent_frames = 0;
+        } else if !self.reported {
+            self.silent_frames += frames as u64;
+            if self.silent_frames >= self.limit_frames {
+                self.reported = true;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Adapts device callbacks of any length to a graph's compiled block size. A callback up to one
+/// block long is rendered as it is. A longer one is rendered as whole blocks straight into the
+/// output; for its trailing partial block, one full block is rendered into an internal buffer
+/// and the remainder is kept for the next callback. The buffer is allocated in `new`.
+#[derive(Debug)]
+pub struct BlockAdapter {
+    buffer: Box<[f32]>,
+    /// Samples rendered into `buffer` (one block).
+    filled: usize,
+    /// Samples of `buffer` already handed out.
+    read: usize,
+}
+
+impl BlockAdapter {
+    /// Creates an adapter for graphs compiled with at most `max_frames` frames per block.
+    pub fn new(max_frames: usize) -> Self {
+        BlockAdapter {
+            buffer: vec![0.0; max_frames].into_boxed_slice(),
+            filled: 0,
+            read: 0,
+        }
+    }
+
+    /// Samples rendered ahead of time, waiting for the next callback.
+    pub fn pending(&self) -> usize {
+        self.filled - self.read
+    }
+
+    /// Drops any pending samples, e.g. because the graph that rendered them was replaced.
+    pub fn clear(&mut self) {
+        self.filled = 0;
+        self.read = 0;
+    }
+
+    /// Fills `output` from `graph`: leftover samples first, then whole blocks, then the head of
+    /// one more block whose tail is kept. What is left after the leftovers is rendered at its
+    /// own length when it fits in one block, so a device calling back with blocks no longer than
+    /// the compiled size never has the graph read its inputs (live input, recording) ahead of
+    /// what has arrived.
+    pub fn render(&mut self, graph: &mut CompiledGraph, output: &mut [f32]) {
+        let leftover = self.pending().min(output.len());
+        output[..leftover].copy_from_slice(&self.buffer[self.read..self.read + leftover]);
+        self.read += leftover;
+        let rest = &mut output[leftover..];
+
+        let block = graph.frame_count();
+        if block == 0 {
+            rest.fill(0.0);
+            return;
+        }
+        if rest.len() <= block {
+            if !rest.is_empty() {
+                graph.process(rest);
+            }
+            return;
+        }
+        if block > self.buffer.len() {
+            for chunk in rest.chunks_mut(block) {
+                graph.process(chunk);
+            }
+            return;
+        }
+        let mut chunks = rest.chunks_exact_mut(block);
+        for chunk in &mut chunks {
+            graph.process(chunk);
+        }
+        let tail = chunks.into_remainder();
+        if !tail.is_empty() {
+            graph.process(&mut self.buffer[..block]);
+            tail.copy_from_slice(&self.buffer[..tail.len()]);
+            self.filled = block;
+            self.read = tail.len();
+        }
+    }
+}
+
+/// Control-thread view of how many samples the engine has produced: everything passed to
+/// `tick`, which includes every `process_audio` call, silent blocks included (but not direct
+/// `render_block` calls). Lock-free; cheap to clone.
+#[derive(Clone, Debug, Default)]
+pub struct SampleCounter(Arc<AtomicU64>);
+
+/// Control-thread view of whether the engine has finished a `Command::Stop` (faded out and
+/// ended). Lock-free; cheap to clone.
+#[derive(Clone, Debug, Default)]
+pub struct StopSignal(Arc<AtomicBool>);
+
+impl StopSignal {
+    /// True once the engine has stopped. Never goes back to false.
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+impl SampleCounter {
+    /// Samples rendered so far. Monotonic.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Linear master gain ramp started by `Command::RampGain`.
+#[derive(Clone, Copy, Debug)]
+struct GainRamp {
+    target: f32,
+    step: f32,
+    remaining: u64,
+}
+
+/// Progress of a crossfaded `SwapGraph` (see [`Engine::set_swap_crossfade_ms`]); the previous
+/// graph plays from the engine's idle graph slot meanwhile.
+struct Crossfade {
+    /// Samples left in the blend; 0 once the new graph plays alone.
+    remaining: usize,
+    /// Length of the blend in samples.
+    total: usize,
+}
 
 /// Engine state: optional compiled graph (when set, it is run); otherwise silence.
-/// SetGain updates a stored gain (for future use, e.g. master gain).
+/// `master_gain` is applied to every rendered block, after the graph (or the silent fallback);
+/// SetGain sets it and SetGainAt schedules that change for a given sample of the engine's clock.
+/// QueryState reports the gain and frequency, graph size, and paused flag via `Event::State`.
 ///
 /// # Example
 ///
@@ -37,37 +268,361 @@ use crate::nodes::GainProcessor;
 /// assert!(peak > 0.0 && peak <= 0.32);
 /// ```
 pub struct Engine {
-    gain_processor: GainProcessor,
-    should_quit: bool,
-    current_graph: Option<CompiledGraph>,
+    /// Master gain stage applied after the graph; set by SetGain / SetGainAt.
+    master_gain: f32,
+    /// Gain ramp in progress from RampGain: `master_gain` moves by `step` per sample until
+    /// `remaining` reaches zero, then lands exactly on `target`.
+    gain_ramp: Option<GainRamp>,
+    /// Output sample rate (Hz), for converting millisecond durations.
+    sample_rate: u32,
+    /// Brickwall limiter at 0 dBFS after `master_gain`, protecting ears and speakers while a
+    /// graph is being edited live.
+    master_limiter: Limiter,
+    /// Whether `master_limiter` runs; toggled by SetMasterLimiter, on by default.
+    limiter_enabled: bool,
+    /// Render under a [`DenormalGuard`] (hardware flush-to-zero); off by default.
+    flush_denormals: bool,
+    /// Hearing protection: gain capped at `SAFE_MODE_MAX_GAIN`, limiter forced on at that level.
+    safe_mode: bool,
+    /// Frequency the engine was configured with (Hz); reported by QueryState.
+    frequency_hz: f32,
+    /// Set by Pause, cleared by Resume: output fades to silence and the graph stops running.
+    paused: bool,
+    /// Set by Stop: like `paused`, but final; `stopped` is raised once the fade-out is done.
+    stopping: bool,
+    /// Shared with the control thread (see [`StopSignal`]).
+    stopped: Arc<AtomicBool>,
+    /// Output muted by `SetMute`; the graph still renders, only the fade target changes.
+    muted: bool,
+    /// Two graph slots: `graphs[active]` runs; the idle one holds the graph being crossfaded out
+    /// or one waiting to be sent back. SwapGraph moves the new graph into the idle slot and flips
+    /// `active` between blocks, so the old graph is never dropped on the audio thread.
+    graphs: [Option<CompiledGraph>; 2],
+    /// Index of the running graph's slot in `graphs`.
+    active: usize,
+    /// A replaced graph that found the idle slot still taken (the event queue was full); it is
+    /// sent back before the idle slot's graph. While it is held, `drain_commands` leaves further
+    /// commands queued, so no later swap has to free a graph on the audio thread.
+    retired: Option<CompiledGraph>,
+    /// Set by [`disable_graph`](Self::disable_graph) after a panic: the graph in the active slot
+    /// is no longer run, until the next SwapGraph replaces it.
+    graph_disabled: bool,
+    /// Samples over which SwapGraph blends from the old graph to the new one; 0 swaps instantly.
+    crossfade_samples: usize,
+    /// Progress of the blend out of the idle slot's graph, if a crossfade is running or has just
+    /// finished.
+    outgoing: Option<Crossfade>,
+    /// Block adapter for `outgoing`; swapped with `block_adapter` when a crossfade starts, so
+    /// the old graph keeps its partial block.
+    outgoing_adapter: BlockAdapter,
+    /// Preallocated scratch the outgoing graph renders into.
+    crossfade_buffer: Box<[f32]>,
+    /// Interleaved channel count of the buffers passed to `render_block`. A graph compiled for
+    /// a different count is not run.
+    channels: usize,
+    /// Samples rendered (or skipped while silent) since the engine started.
+    sample_clock: u64,
+    /// Total output samples from `tick` (and so `process_audio`), shared with the control thread.
+    samples_rendered: Arc<AtomicU64>,
+    /// Gain change waiting for `sample_clock` to reach its sample: `(gain, at_sample)`.
+    scheduled_gain: Option<(f32, u64)>,
+    /// Set by Freeze: `render_block` loops `frozen_buffer` instead of running the graph.
+    frozen: bool,
+    /// Copy of the last rendered graph block (before master gain), preallocated.
+    frozen_buffer: Box<[f32]>,
+    /// Valid samples in `frozen_buffer`.
+    frozen_len: usize,
+    /// Read position while frozen.
+    frozen_pos: usize,
+    /// Holds the rest of a partially used graph block between callbacks.
+    block_adapter: BlockAdapter,
+    /// Output gain applied by `process_audio`; ramps toward 0.0 while paused or stopping and 1.0
+    /// otherwise. Starts at 0.0 so the first rendered audio fades in instead of starting at full
+    /// level.
+    fade_gain: f32,
+    /// Per-sample increase of `fade_gain` while fading in.
+    fade_in_step: f32,
+    /// Per-sample decrease of `fade_gain` while fading out.
+    fade_out_step: f32,
+    /// Highest level of the current callback after master gain, before the limiter.
+    block_peak: f32,
+    /// Turns `block_peak` into `Event::ClipState` transitions.
+    clip_indicator: ClipIndicator,
+    /// Turns runs of all-zero output into `Event::UnexpectedSilence`.
+    silence_watchdog: SilenceWatchdog,
+    /// Where applied parameter commands are logged, if recording commands.
+    command_log: Option<Arc<CommandLog>>,
+    /// Whether `Event::BlockSizeMismatch` was already sent for the running graph; cleared by
+    /// `SwapGraph`.
+    block_mismatch_reported: bool,
 }
 
 impl Engine {
-    pub fn new(_sample_rate: u32, _frequency_hz: f32, initial_gain: f32) -> Self {
+    pub fn new(sample_rate: u32, frequency_hz: f32, initial_gain: f32) -> Self {
+        let ms_to_samples = |ms: f32| (ms / 1000.0 * sample_rate as f32).max(1.0);
         Engine {
-            gain_processor: GainProcessor::new(initial_gain),
-            should_quit: false,
-            current_graph: None,
+            master_gain: initial_gain,
+            gain_ramp: None,
+            sample_rate,
+            master_limiter: Limiter::new(1.0, MASTER_LIMITER_RELEASE_MS, sample_rate),
+            limiter_enabled: true,
+            safe_mode: false,
+            flush_denormals: false,
+            frequency_hz,
+            paused: false,
+            stopping: false,
+            stopped: Arc::new(AtomicBool::new(false)),
+            muted: false,
+            graphs: [None, None],
+            active: 0,
+            retired: None,
+            graph_disabled: false,
+            crossfade_samples: 0,
+            outgoing: None,
+            outgoing_adapter: BlockAdapter::new(MAX_ADAPTER_FRAMES),
+            crossfade_buffer: vec![0.0; MAX_ADAPTER_FRAMES].into_boxed_slice(),
+            channels: 1,
+            sample_clock: 0,
+            samples_rendered: Arc::new(AtomicU64::new(0)),
+            scheduled_gain: None,
+            frozen: false,
+            frozen_buffer: vec![0.0; MAX_FREEZE_FRAMES].into_boxed_slice(),
+            frozen_len: 0,
+            frozen_pos: 0,
+            block_adapter: BlockAdapter::new(MAX_ADAPTER_FRAMES),
+            fade_gain: 0.0,
+            fade_in_step: 1.0 / ms_to_samples(FADE_IN_MS),
+            fade_out_step: 1.0 / ms_to_samples(FADE_OUT_MS),
+            block_peak: 0.0,
+            clip_indicator: ClipIndicator::default(),
+            silence_watchdog: SilenceWatchdog::new(sample_rate),
+            command_log: None,
+            block_mismatch_reported: false,
+        }
+    }
+
+    /// Turns safe mode on or off. While on, the master gain (current and every later SetGain,
+    /// SetGainAt or RampGain target) is capped at [`SAFE_MODE_MAX_GAIN`], and the master limiter
+    /// runs at that ceiling and ignores SetMasterLimiter, so a runaway graph stays at -12 dBFS.
+    pub fn set_safe_mode(&mut self, enabled: bool) {
+        self.safe_mode = enabled;
+        if enabled {
+            self.master_gain = self.master_gain.min(SAFE_MODE_MAX_GAIN);
+            self.limiter_enabled = true;
+            self.master_limiter.ceiling = SAFE_MODE_MAX_GAIN;
+        } else {
+            self.master_limiter.ceiling = 1.0;
+        }
+    }
+
+    /// `gain` limited to 0..=[`MAX_GAIN`] (reporting `Event::ParamClamped` if that changed it;
+    /// NaN becomes 0), then capped silently in safe mode.
+    fn allowed_gain(&self, gain: f32, evt_tx: &EventSender) -> f32 {
+        let applied = clamp_param("gain", gain, 0.0, MAX_GAIN, evt_tx);
+        if self.safe_mode {
+            applied.min(SAFE_MODE_MAX_GAIN)
+        } else {
+            applied
         }
     }
 
-    /// Drain all currently pending commands and apply them.
+    /// Sets the interleaved channel count of the buffers this engine renders (default 1). The
+    /// device callback (`start_audio_with_options`) renders mono and fans it out, so it sets 1
+    /// whatever the device width; set it when driving `render_block` with interleaved buffers
+    /// directly.
+    pub fn set_channels(&mut self, channels: usize) {
+        self.channels = channels.max(1);
+    }
+
+    /// Renders each callback with the CPU in flush-to-zero mode ([`DenormalGuard`]), so decaying
+    /// feedback anywhere in the graph never runs on slow denormal arithmetic. The previous mode
+    /// is restored before `tick` returns. A no-op where the target has no such mode.
+    pub fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    /// Crossfade graph swaps: after a `SwapGraph`, the old and new graphs both run for `ms`
+    /// milliseconds and the output moves linearly from `old` to `new`
+    /// (`old * (1 - t) + new * t`), so differing waveforms don't click. The old graph is sent
+    /// back in `Event::GraphSwapped` once the blend is done. 0 (the default) swaps instantly.
+    /// Set it before moving the engine into the audio callback.
+    pub fn set_swap_crossfade_ms(&mut self, ms: f32) {
+        self.crossfade_samples = (ms.max(0.0) / 1000.0 * self.sample_rate as f32).round() as usize;
+    }
+
+    /// Record-commands mode: every command applied from now on (a batch as each command in it)
+    /// is pushed to `log` with the engine's sample clock, while the log is armed.
+    /// `None` stops recording. Set it before moving the engine into the audio callback.
+    pub fn set_command_log(&mut self, log: Option<Arc<CommandLog>>) {
+        self.command_log = log;
+    }
+
+    /// Handle for reading the number of samples rendered from another thread. Take it before
+    /// moving the engine into the audio callback.
+    pub fn sample_counter(&self) -> SampleCounter {
+        SampleCounter(Arc::clone(&self.samples_rendered))
+    }
+
+    /// Handle for checking from another thread whether a `Command::Stop` has completed. Take it
+    /// before moving the engine into the audio callback.
+    pub fn stop_signal(&self) -> StopSignal {
+        StopSignal(Arc::clone(&self.stopped))
+    }
+
+    /// Stops running the current graph (and any graph being crossfaded out): output is silence
+    /// until the next `SwapGraph`. For the device callback to call after a node panicked, so a
+    /// graph that is now in an unknown state does not panic again on every callback. Returns
+    /// true if a graph was running.
+    pub fn disable_graph(&mut self) -> bool {
+        self.outgoing = None;
+        let was_running = self.graphs[self.active].is_some() && !self.graph_disabled;
+        self.graph_disabled = true;
+        was_running
+    }
+
+    /// Drain all currently pending commands and apply them. While a retired graph is still
+    /// waiting to be sent back, commands stay queued for a later callback.
     pub fn drain_commands(&mut self, cmd_rx: &CommandReceiver, evt_tx: &EventSender) {
-        while let Some(cmd) = cmd_rx.try_recv() {
+        self.return_retired_graph(evt_tx);
+        while self.retired.is_none() {
+            let Some(cmd) = cmd_rx.try_recv() else {
+                break;
+            };
             self.apply_command(cmd, evt_tx);
         }
     }
 
     /// Render one block: run the compiled graph if set, else silence (no tone until user loads a graph).
+    /// A graph compiled for a different channel count (see [`set_channels`](Self::set_channels))
+    /// also renders silence; `Event::ChannelMismatch` was sent when it was swapped in.
+    /// A callback up to the compiled `frame_count` is rendered at its own length; longer callbacks
+    /// are rendered in place as consecutive sub-blocks (no copy), and a partial block is buffered
+    /// by the engine's [`BlockAdapter`]. Node state carries over, so the result is the same as
+    /// one large block.
+    /// The engine gain is applied last; a scheduled gain change splits the block at its sample.
     pub fn render_block(&mut self, output: &mut [f32]) {
-        if let Some(ref mut graph) = self.current_graph {
-            graph.process(output);
+        let split = match self.scheduled_gain {
+            Some((_, at)) => at
+                .saturating_sub(self.sample_clock)
+                .min(output.len() as u64) as usize,
+            None => output.len(),
+        };
+        if !self.frozen {
+            self.frozen_len = 0;
+        }
+        let (head, tail) = output.split_at_mut(split);
+        self.render_segment(head);
+        if !tail.is_empty() {
+            self.render_segment(tail);
+        }
+    }
+
+    fn render_segment(&mut self, output: &mut [f32]) {
+        if self.frozen {
+            self.play_frozen(output);
+        } else {
+            // A graph for another channel count would interleave wrongly: play silence instead.
+            match self.graphs[self.active] {
+                Some(ref mut graph)
+                    if !self.graph_disabled && graph.channels() == self.channels =>
+                {
+                    self.block_adapter.render(graph, output);
+                    self.blend_outgoing(output);
+                }
+                _ => output.fill(0.0),
+            }
+            self.capture(output);
+        }
+        if let Some(mut ramp) = self.gain_ramp.take() {
+            for s in output.iter_mut() {
+                if ramp.remaining > 0 {
+                    ramp.remaining -= 1;
+                    self.master_gain = if ramp.remaining == 0 {
+                        ramp.target
+                    } else {
+                        self.master_gain + ramp.step
+                    };
+                }
+                *s *= self.master_gain;
+            }
+            if ramp.remaining > 0 {
+                self.gain_ramp = Some(ramp);
+            }
         } else {
+            for s in output.iter_mut() {
+                *s *= self.master_gain;
+            }
+        }
+        self.block_peak = output
+            .iter()
+            .fold(self.block_peak, |peak, s| peak.max(s.abs()));
+        if self.limiter_enabled {
+            self.master_limiter.process_in_place(output);
+        }
+        self.advance_clock(output.len());
+    }
+
+    /// Mixes the graph being crossfaded out into `output`, which holds the new graph's samples:
+    /// `old * (1 - t) + new * t`, with `t` rising from 0 to 1 over the crossfade.
+    fn blend_outgoing(&mut self, output: &mut [f32]) {
+        let (Some(fade), Some(graph)) = (self.outgoing.as_mut(), &mut self.graphs[1 - self.active])
+        else {
+            return;
+        };
+        for chunk in output.chunks_mut(self.crossfade_buffer.len()) {
+            if fade.remaining == 0 {
+                break;
+            }
+            let old = &mut self.crossfade_buffer[..chunk.len()];
+            self.outgoing_adapter.render(graph, old);
+            for (s, &o) in chunk.iter_mut().zip(old.iter()) {
+                if fade.remaining == 0 {
+                    break;
+                }
+                let t = 1.0 - fade.remaining as f32 / fade.total as f32;
+                *s = o + (*s - o) * t;
+                fade.remaining -= 1;
+            }
+        }
+    }
+
+    /// Appends freshly rendered graph output to `frozen_buffer` (up to its capacity), so it
+    /// always holds the last block in case Freeze arrives.
+    fn capture(&mut self, output: &[f32]) {
+        let n = output.len().min(self.frozen_buffer.len() - self.frozen_len);
+        self.frozen_buffer[self.frozen_len..self.frozen_len + n].copy_from_slice(&output[..n]);
+        self.frozen_len += n;
+    }
+
+    /// Loops the captured block into `output`; silence if nothing was captured.
+    fn play_frozen(&mut self, output: &mut [f32]) {
+        if self.frozen_len == 0 {
             output.fill(0.0);
+            return;
+        }
+        for s in output.iter_mut() {
+            *s = self.frozen_buffer[self.frozen_pos];
+            self.frozen_pos = (self.frozen_pos + 1) % self.frozen_len;
+        }
+    }
+
+    /// Moves the sample clock forward and applies a scheduled gain change that has come due.
+    fn advance_clock(&mut self, frames: usize) {
+        self.sample_clock += frames as u64;
+        if let Some((gain, at)) = self.scheduled_gain {
+            if at <= self.sample_clock {
+                self.master_gain = gain;
+                self.gain_ramp = None;
+                self.scheduled_gain = None;
+            }
         }
     }
 
-    /// Full audio callback: drain commands, then either silence (if quit) or render.
+    /// Full audio callback: drain commands, report a callback length that differs from the
+    /// graph's block size, then [`tick`](Self::tick), then send back the old
+    /// graph once a swap crossfade is done, `Event::ClipState` if the clip indicator changed,
+    /// `Event::UnexpectedSilence` when a running graph has output only zeros for a while, and
+    /// `Event::StreamStopped` once a `Stop` has faded out.
     pub fn process_audio(
         &mut self,
         cmd_rx: &CommandReceiver,
@@ -75,33 +630,271 @@ impl Engine {
         output: &mut [f32],
     ) {
         self.drain_commands(cmd_rx, evt_tx);
-        if self.should_quit() {
-            for s in output.iter_mut() {
-                *s = 0.0;
+        self.check_block_size(output.len(), evt_tx);
+        let was_stopped = self.stopped.load(Ordering::Relaxed);
+        self.tick(output);
+        if self
+            .outgoing
+            .as_ref()
+            .is_some_and(|fade| fade.remaining == 0)
+        {
+            self.outgoing = None;
+        }
+        if self.outgoing.is_none() {
+            self.return_idle_graph(evt_tx);
+        }
+        if !was_stopped && self.stopped.load(Ordering::Relaxed) {
+            let _ = evt_tx.try_send(Event::StreamStopped);
+        }
+        if let Some(on) = self.clip_indicator.update(self.block_peak) {
+            let _ = evt_tx.try_send(Event::ClipState(on));
+        }
+        let expected = self.graphs[self.active].is_none()
+            || self.graph_disabled
+            || self.paused
+            || self.stopping
+            || self.muted
+            || self.master_gain == 0.0;
+        let frames = output.len() / self.channels;
+        if self
+            .silence_watchdog
+            .update(self.block_peak, frames, expected)
+        {
+            let _ = evt_tx.try_send(Event::UnexpectedSilence);
+        }
+    }
+
+    /// Sends the idle slot's graph back in `Event::GraphSwapped` for the control thread to drop,
+    /// after any retired graph. If the event queue is full, the graph stays in the slot and the
+    /// next callback retries.
+    fn return_idle_graph(&mut self, evt_tx: &EventSender) {
+        self.return_retired_graph(evt_tx);
+        if self.retired.is_some() {
+            return;
+        }
+        let idle = 1 - self.active;
+        if let Some(graph) = self.graphs[idle].take() {
+            if let Err(Event::GraphSwapped(graph)) = evt_tx.try_send(Event::GraphSwapped(graph)) {
+                self.graphs[idle] = Some(graph);
             }
+        }
+    }
+
+    /// Sends the retired graph back in `Event::GraphSwapped`, or keeps it if the queue is full.
+    fn return_retired_graph(&mut self, evt_tx: &EventSender) {
+        if let Some(graph) = self.retired.take() {
+            if let Err(Event::GraphSwapped(graph)) = evt_tx.try_send(Event::GraphSwapped(graph)) {
+                self.retired = Some(graph);
+            }
+        }
+    }
+
+    /// Sends `Event::BlockSizeMismatch` when a callback of `actual` samples does not match the
+    /// running graph's compiled block size, once per compiled graph. A device whose callback
+    /// length varies (441/442/441...) would otherwise report on every callback.
+    fn check_block_size(&mut self, actual: usize, evt_tx: &EventSender) {
+        let Some(compiled) = self.graphs[self.active]
+            .as_ref()
+            .map(CompiledGraph::frame_count)
+        else {
+            return;
+        };
+        if compiled == 0 || compiled == actual || self.block_mismatch_reported {
+            return;
+        }
+        self.block_mismatch_reported = true;
+        let _ = evt_tx.try_send(Event::BlockSizeMismatch { compiled, actual });
+    }
+
+    /// Renders one callback's worth of output without touching the command or event channels;
+    /// `process_audio` is this plus the command drain. Deterministic and I/O-free, so benchmarks
+    /// and regression tests can loop it. The first rendered audio fades in over ~10 ms (soft
+    /// start). After `Pause` or `Stop` the output fades to silence over a few milliseconds (then
+    /// the graph stops running); after `Resume` it fades back in. A `Stop` raises the
+    /// [`StopSignal`] once its fade-out is done.
+    pub fn tick(&mut self, output: &mut [f32]) {
+        let _ftz = self.flush_denormals.then(DenormalGuard::new);
+        self.block_peak = 0.0;
+        self.samples_rendered
+            .fetch_add(output.len() as u64, Ordering::Release);
+        // No graph yet: stay silent without using up the soft start.
+        let silenced = self.paused || self.stopping;
+        if self.graphs[self.active].is_none() || (silenced && self.fade_gain <= 0.0) {
+            if self.stopping {
+                self.stopped.store(true, Ordering::Release);
+            }
+            output.fill(0.0);
+            self.advance_clock(output.len());
+            return;
+        }
+        self.render_block(output);
+        self.apply_fade(output);
+    }
+
+    /// Ramps `fade_gain` toward its target (0.0 when paused, stopping or muted, else 1.0) one step
+    /// per sample, scaling `output` as it goes. No-op once the fade has settled at unity.
+    fn apply_fade(&mut self, output: &mut [f32]) {
+        let target = if self.paused || self.stopping || self.muted {
+            0.0
         } else {
-            self.render_block(output);
+            1.0
+        };
+        if self.fade_gain == target && target == 1.0 {
+            return;
+        }
+        for s in output.iter_mut() {
+            self.fade_gain = if self.fade_gain < target {
+                (self.fade_gain + self.fade_in_step).min(target)
+            } else {
+                (self.fade_gain - self.fade_out_step).max(target)
+            };
+            *s *= self.fade_gain;
         }
     }
 
     /// Apply a single command. SwapGraph sends the previous graph back via `evt_tx`.
     pub fn apply_command(&mut self, cmd: Command, evt_tx: &EventSender) {
+        if let Some(ref log) = self.command_log {
+            if let Some(logged) = LoggedCommand::from_command(&cmd) {
+                log.push(self.sample_clock, logged);
+            }
+        }
         match cmd {
-            Command::SetGain(gain) => self.gain_processor.gain = gain,
-            Command::Quit => self.should_quit = true,
-            Command::Resume => self.should_quit = false,
+            Command::SetGain(gain) => {
+                self.master_gain = self.allowed_gain(gain, evt_tx);
+                self.gain_ramp = None;
+            }
+            Command::RampGain {
+                target,
+                duration_ms,
+            } => {
+                let target = self.allowed_gain(target, evt_tx);
+                let samples = (duration_ms / 1000.0 * self.sample_rate as f32)
+                    .round()
+                    .max(1.0);
+                self.gain_ramp = Some(GainRamp {
+                    target,
+                    step: (target - self.master_gain) / samples,
+                    remaining: samples as u64,
+                });
+            }
+            Command::SetGainAt { gain, at_sample } => {
+                self.scheduled_gain = Some((self.allowed_gain(gain, evt_tx), at_sample));
+                self.advance_clock(0);
+            }
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::Stop => self.stopping = true,
+            Command::SetFrequency(hz) => {
+                let nyquist = self.sample_rate as f32 / 2.0;
+                // Exactly Nyquist would render silence, so stay just below it.
+                let below_nyquist = nyquist * (1.0 - f32::EPSILON);
+                let hz = clamp_param("frequency", hz, MIN_FREQUENCY_HZ, below_nyquist, evt_tx);
+                self.frequency_hz = hz;
+                if let Some(ref mut graph) = self.graphs[self.active] {
+                    graph.set_param_all(ParamId::Frequency, hz);
+                }
+            }
+            Command::SetMute(muted) => self.muted = muted,
+            Command::Freeze(frozen) => {
+                self.frozen = frozen;
+                self.frozen_pos = 0;
+            }
+            Command::SetMasterLimiter(enabled) => self.limiter_enabled = enabled || self.safe_mode,
             Command::NoOp => (),
+            Command::SoloNode(id) => {
+                if let Some(ref mut graph) = self.graphs[self.active] {
+                    graph.set_solo(id);
+                }
+            }
+            Command::Panic => {
+                for graph in self.graphs.iter_mut().flatten() {
+                    graph.reset();
+                }
+                // The graph fading out is sent back at the end of the callback.
+                self.outgoing = None;
+                self.block_adapter.clear();
+                self.frozen = false;
+                self.master_limiter.reset();
+                self.fade_gain = 0.0;
+            }
             Command::SwapGraph(new) => {
-                if let Some(prev) = self.current_graph.replace(new) {
-                    let _ = evt_tx.try_send(Event::GraphSwapped(prev));
+                if new.channels() != self.channels {
+                    let _ = evt_tx.try_send(Event::ChannelMismatch {
+                        expected: self.channels,
+                        got: new.channels(),
+                    });
+                }
+                // A crossfade still running is cut short: its old graph goes back now.
+                self.outgoing = None;
+                self.graph_disabled = false;
+                self.block_mismatch_reported = false;
+                self.return_idle_graph(evt_tx);
+                let crossfade = self.crossfade_samples > 0 && new.channels() == self.channels;
+                // Only if the event queue was full is the idle slot still taken; its graph then
+                // waits in `retired`. That slot is free unless `apply_command` is called directly
+                // while it is held (`drain_commands` stops first), and only then is a graph
+                // dropped here.
+                if let Some(stuck) = self.graphs[1 - self.active].take() {
+                    self.retired = Some(stuck);
+                }
+                self.graphs[1 - self.active] = Some(new);
+                self.active = 1 - self.active;
+                match &self.graphs[1 - self.active] {
+                    Some(prev) if crossfade && prev.channels() == self.channels => {
+                        std::mem::swap(&mut self.block_adapter, &mut self.outgoing_adapter);
+                        self.block_adapter.clear();
+                        self.outgoing = Some(Crossfade {
+                            remaining: self.crossfade_samples,
+                            total: self.crossfade_samples,
+                        });
+                    }
+                    Some(_) => self.return_idle_graph(evt_tx),
+                    None => (),
+                }
+            }
+            Command::Batch(batch) => {
+                for &cmd in batch.commands() {
+                    self.apply_command(cmd.into(), evt_tx);
                 }
             }
+            Command::QueryState => {
+                let _ = evt_tx.try_send(Event::State {
+                    gain: self.master_gain,
+                    freq: self.frequency_hz,
+                    graph_nodes: self.graphs[self.active]
+                        .as_ref()
+                        .map_or(0, CompiledGraph::node_count),
+                    paused: self.paused || self.stopping,
+                });
+            }
         }
     }
 
-    pub fn should_quit(&self) -> bool {
-        self.should_quit
+    /// True after `Pause` (until `Resume`) or `Stop`.
+    pub fn is_paused(&self) -> bool {
+        self.paused || self.stopping
+    }
+}
+
+/// Clamps `requested` to `min..=max` (NaN becomes `min`), sending `Event::ParamClamped` if
+/// the value changed.
+fn clamp_param(
+    param: &'static str,
+    requested: f32,
+    min: f32,
+    max: f32,
+    evt_tx: &EventSender,
+) -> f32 {
+    let applied = requested.max(min).min(max);
+    if applied != requested {
+        let _ = evt_tx.try_send(Event::ParamClamped {
+            param,
+            requested,
+            applied,
+        });
     }
+    applied
 }
 
 #[cfg(test)]
@@ -147,13 +940,13 @@ mod tests {
     }
 
     #[test]
-    fn test_apply_command_quit_sets_should_quit() {
+    fn test_apply_command_pause_sets_paused() {
         let (evt_tx, _) = event_channel(4);
         let mut engine = Engine::new(48_000, 440.0, 0.5);
-        assert!(!engine.should_quit());
+        assert!(!engine.is_paused());
 
-        engine.apply_command(Command::Quit, &evt_tx);
-        assert!(engine.should_quit());
+        engine.apply_command(Command::Pause, &evt_tx);
+        assert!(engine.is_paused());
     }
 
     #[test]
@@ -169,18 +962,18 @@ mod tests {
     }
 
     #[test]
-    fn test_process_audio_outputs_silence_when_quit() {
+    fn test_process_audio_outputs_silence_when_paused() {
         let (cmd_tx, cmd_rx) = command_channel(8);
         let (evt_tx, _) = event_channel(4);
         let mut engine = Engine::new(48_000, 440.0, 0.5);
         let mut buf = vec![1.0f32; 64];
 
-        cmd_tx.try_send(Command::Quit).unwrap();
+        cmd_tx.try_send(Command::Pause).unwrap();
         engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
 
         assert!(
             buf.iter().all(|&s| s == 0.0),
-            "process_audio should output silence when quit"
+            "process_audio should output silence when paused"
         );
     }
 
@@ -224,6 +1017,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_render_block_in_sub_blocks_matches_one_block() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+
+        let mut whole = g.compile(512).unwrap();
+        let mut expected = vec![0.0f32; 512];
+        whole.process(&mut expected);
+
+        // Compiled for 128 frames: a 512-frame callback runs as four sub-blocks.
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(128).unwrap()), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.render_block(&mut buf);
+        assert_eq!(buf, expected, "sine phase continues across sub-blocks");
+    }
+
+    #[test]
+    fn test_irregular_callback_sizes_stay_phase_continuous() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+
+        let mut whole = g.compile(512).unwrap();
+        let mut expected = vec![0.0f32; 350];
+        whole.process(&mut expected);
+
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(64).unwrap()), &evt_tx);
+        let mut rendered = Vec::new();
+        for len in [100, 50, 200] {
+            let mut buf = vec![0.0f32; len];
+            engine.render_block(&mut buf);
+            rendered.extend_from_slice(&buf);
+        }
+        assert_eq!(rendered.len(), 350);
+        assert_eq!(rendered, expected);
+        // 100 = 64 + 64 (36 used); 50 = 28 left over + 22 rendered as is; 200 = 3 * 64 + 64
+        // (8 used): 56 samples wait for the next callback.
+        assert_eq!(engine.block_adapter.pending(), 56);
+    }
+
+    #[test]
+    fn test_short_callbacks_do_not_read_live_input_ahead() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::InputSampleBuffer;
+        use crate::nodes::InputNode;
+        use std::sync::Arc;
+
+        let (evt_tx, _) = event_channel(4);
+        let input = Arc::new(InputSampleBuffer::new(4096));
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Input(InputNode::new(input.clone())));
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+
+        // The device delivers 128 frames per callback into a graph compiled for 512: each
+        // callback must read only what has arrived, or the rest underruns to zeros.
+        let mut buf = vec![0.0f32; 128];
+        for _ in 0..8 {
+            input.write_block(&[0.5; 128], 1);
+            engine.render_block(&mut buf);
+            assert!(buf.iter().all(|&s| s == 0.5), "no dropout");
+        }
+        assert_eq!(engine.block_adapter.pending(), 0);
+    }
+
     #[test]
     fn test_swap_graph_returns_old_via_event() {
         use crate::graph::{AudioGraph, GraphNode};
@@ -253,4 +1120,927 @@ mod tests {
         let old = evt_rx.try_recv().expect("should receive previous graph");
         assert!(matches!(old, crate::event::Event::GraphSwapped(_)));
     }
+
+    #[test]
+    fn test_query_state_reports_gain_and_graph() {
+        use crate::event::Event;
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::{GainProcessor, SineGenerator};
+
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 0.5);
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+        g.add_edge(sine, gain);
+
+        cmd_tx.try_send(Command::SetGain(0.3)).unwrap();
+        cmd_tx
+            .try_send(Command::SwapGraph(g.compile(64).unwrap()))
+            .unwrap();
+        cmd_tx.try_send(Command::QueryState).unwrap();
+        engine.drain_commands(&cmd_rx, &evt_tx);
+
+        assert_eq!(
+            evt_rx.try_recv(),
+            Some(Event::State {
+                gain: 0.3,
+                freq: 440.0,
+                graph_nodes: 2,
+                paused: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_tick_matches_process_audio_with_empty_channels() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (_cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut ticked = Engine::new(48_000, 440.0, 0.5);
+        let mut processed = Engine::new(48_000, 440.0, 0.5);
+        ticked.apply_command(Command::SwapGraph(g.compile(128).unwrap()), &evt_tx);
+        processed.apply_command(Command::SwapGraph(g.compile(128).unwrap()), &evt_tx);
+
+        // Several callbacks, so the soft start and an adapter-buffered partial block are covered.
+        for len in [128, 100, 300, 128] {
+            let mut a = vec![0.0f32; len];
+            let mut b = vec![0.0f32; len];
+            ticked.tick(&mut a);
+            processed.process_audio(&cmd_rx, &evt_tx, &mut b);
+            assert_eq!(a, b);
+        }
+        assert_eq!(
+            ticked.sample_counter().get(),
+            processed.sample_counter().get()
+        );
+    }
+
+    /// Graph whose output is a constant `level` (looped file buffer), so fades are easy to see.
+    fn dc_graph(level: f32) -> crate::graph::CompiledGraph {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::FilePlaybackBuffer;
+        use crate::nodes::InputNode;
+        use std::sync::Arc;
+
+        let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![level; 1024])));
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Input(InputNode::new(source)));
+        g.compile(512).unwrap()
+    }
+
+    #[test]
+    fn test_solo_node_applies_to_running_graph() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::FilePlaybackBuffer;
+        use crate::nodes::{GainProcessor, InputNode};
+        use std::sync::Arc;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![1.0; 1024])));
+        let input = g.add_node(GraphNode::Input(InputNode::new(source)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.25)));
+        g.add_edge(input, gain);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.tick(&mut buf); // soft start
+        engine.tick(&mut buf);
+        assert!(buf.iter().all(|&s| s == 0.25));
+
+        engine.apply_command(Command::SoloNode(Some(g.stable_id(input))), &evt_tx);
+        engine.tick(&mut buf);
+        assert!(
+            buf.iter().all(|&s| s == 1.0),
+            "input heard without the gain"
+        );
+        engine.apply_command(Command::SoloNode(None), &evt_tx);
+        engine.tick(&mut buf);
+        assert!(buf.iter().all(|&s| s == 0.25));
+    }
+
+    #[test]
+    fn test_swap_crossfade_blends_then_plays_new_graph() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.set_swap_crossfade_ms(20.0); // 960 samples
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf); // soft start
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 1.0));
+
+        cmd_tx.try_send(Command::SwapGraph(dc_graph(0.5))).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(buf[0], 1.0, "blend starts on the old graph");
+        assert!(
+            buf.windows(2).all(|w| w[1] < w[0]),
+            "moves toward the new graph"
+        );
+        let t = 100.0 / 960.0;
+        assert!((buf[100] - (1.0 * (1.0 - t) + 0.5 * t)).abs() < 1e-6);
+        assert!(evt_rx.try_recv().is_none(), "old graph still playing");
+
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        // Samples 512..960 finish the blend; the rest is the new graph alone.
+        assert!(buf[..448].iter().all(|&s| s > 0.5 && s < 1.0));
+        assert!(buf[448..].iter().all(|&s| s == 0.5));
+        assert!(matches!(
+            evt_rx.try_recv(),
+            Some(crate::event::Event::GraphSwapped(_))
+        ));
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 0.5));
+    }
+
+    #[test]
+    fn test_swap_flips_slot_and_returns_old_graph() {
+        use crate::event::Event;
+
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let first = engine.active;
+
+        // tests/no_alloc_swap.rs checks that this allocates nothing.
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        assert_eq!(engine.active, 1 - first);
+        assert!(engine.graphs[first].is_none(), "old slot emptied");
+
+        let mut out = vec![0.0f32; 512];
+        match evt_rx.try_recv() {
+            Some(Event::GraphSwapped(mut old)) => {
+                old.process(&mut out);
+                assert!(
+                    out.iter().all(|&s| s == 1.0),
+                    "the previous graph came back"
+                );
+            }
+            other => panic!("expected GraphSwapped, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_swap_with_full_event_queue_returns_old_graph_later() {
+        use crate::event::Event;
+
+        let (_, cmd_rx) = command_channel(4);
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        while evt_tx.try_send(Event::NoOp).is_ok() {}
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        assert!(
+            engine.graphs[1 - engine.active].is_some(),
+            "kept in the idle slot"
+        );
+
+        while evt_rx.try_recv().is_some() {}
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(matches!(evt_rx.try_recv(), Some(Event::GraphSwapped(_))));
+        assert!(engine.graphs[1 - engine.active].is_none());
+    }
+
+    #[test]
+    fn test_second_swap_with_full_event_queue_retires_old_graph() {
+        use crate::event::Event;
+
+        let (cmd_tx, cmd_rx) = command_channel(4);
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        while evt_tx.try_send(Event::NoOp).is_ok() {}
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        engine.apply_command(Command::SwapGraph(dc_graph(0.25)), &evt_tx);
+        assert!(
+            engine.retired.is_some(),
+            "the first graph waits in retirement"
+        );
+        assert!(engine.graphs[1 - engine.active].is_some());
+
+        // Commands wait while a graph is retired, so no later swap can push one out.
+        cmd_tx.try_send(Command::SetGain(0.5)).unwrap();
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(engine.master_gain, 1.0, "SetGain is still queued");
+
+        while evt_rx.try_recv().is_some() {}
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(
+            engine.master_gain, 0.5,
+            "applied once the retired graph went back"
+        );
+        let returned: Vec<usize> = std::iter::from_fn(|| evt_rx.try_recv())
+            .filter_map(|e| match e {
+                Event::GraphSwapped(g) => Some(g.node_count()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(returned.len(), 2, "both replaced graphs come back");
+        assert!(engine.retired.is_none() && engine.graphs[1 - engine.active].is_none());
+    }
+
+    #[test]
+    fn test_panic_silences_a_sustained_echo_and_clears_its_state() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::FilePlaybackBuffer;
+        use crate::nodes::{Echo, InputNode};
+        use std::sync::Arc;
+
+        // A short burst into an echo that barely decays: it rings for many seconds.
+        let mut burst = vec![0.0f32; 48_000];
+        burst[..256].fill(0.5);
+        let mut echo = Echo::new(10.0, 48_000);
+        echo.set_delay_ms(5.0);
+        echo.dry = 0.0;
+        echo.wet = 1.0;
+        echo.feedback = 0.99;
+        let mut g = AudioGraph::new();
+        let input = g.add_node(GraphNode::Input(InputNode::new(Arc::new(
+            FilePlaybackBuffer::new(Arc::new(burst)),
+        ))));
+        let echo = g.add_node(GraphNode::Echo(echo));
+        g.add_edge(input, echo);
+
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        for _ in 0..8 {
+            engine.tick(&mut buf);
+        }
+        assert!(buf.iter().any(|&s| s.abs() > 0.1), "echo still ringing");
+
+        engine.apply_command(Command::Panic, &evt_tx);
+        for _ in 0..8 {
+            engine.tick(&mut buf);
+            assert!(
+                buf.iter().all(|&s| s == 0.0),
+                "tail cleared, not just muted"
+            );
+        }
+        assert_eq!(engine.fade_gain, 1.0, "faded back in");
+    }
+
+    #[test]
+    fn test_silence_watchdog_reports_a_silent_graph_once() {
+        use crate::event::Event;
+
+        let run = |level: f32, block_len: usize, blocks: usize| {
+            let (_, cmd_rx) = command_channel(4);
+            let (evt_tx, evt_rx) = event_channel(8);
+            let mut engine = Engine::new(48_000, 440.0, 1.0);
+            engine.apply_command(Command::SwapGraph(dc_graph(level)), &evt_tx);
+            let mut buf = vec![0.0f32; block_len];
+            let mut first = None;
+            let mut count = 0;
+            for block in 1..=blocks {
+                engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+                while let Some(event) = evt_rx.try_recv() {
+                    if event == Event::UnexpectedSilence {
+                        first.get_or_insert(block);
+                        count += 1;
+                    }
+                }
+            }
+            (first, count)
+        };
+        // One second at 48 kHz, whatever the callback size: 94 blocks of 512, 750 of 64.
+        assert_eq!(run(0.0, 512, 300), (Some(94), 1));
+        assert_eq!(run(0.0, 64, 2000), (Some(750), 1));
+        assert_eq!(run(0.5, 512, 300), (None, 0));
+
+        // Muting is asked-for silence.
+        let (_, cmd_rx) = command_channel(4);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        engine.apply_command(Command::SetMute(true), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        for _ in 0..200 {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        }
+        assert!(evt_rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_swap_without_crossfade_is_instant() {
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.tick(&mut buf);
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        assert!(evt_rx.try_recv().is_some(), "old graph returned at once");
+        engine.tick(&mut buf);
+        assert!(buf.iter().all(|&s| s == 0.5));
+    }
+
+    #[test]
+    fn test_flush_denormals_zeroes_denormal_output() {
+        use crate::denormal::DenormalGuard;
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::FilePlaybackBuffer;
+        use crate::nodes::{GainProcessor, InputNode};
+        use std::sync::Arc;
+
+        // 1e-20 * 1e-20 = 1e-40, below the smallest normal f32.
+        let render = |flush| {
+            let (evt_tx, _) = event_channel(4);
+            let mut g = AudioGraph::new();
+            let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![1e-20; 1024])));
+            let input = g.add_node(GraphNode::Input(InputNode::new(source)));
+            let gain = g.add_node(GraphNode::Gain(GainProcessor::new(1e-20)));
+            g.add_edge(input, gain);
+            let mut engine = Engine::new(48_000, 440.0, 1.0);
+            engine.set_flush_denormals(flush);
+            engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+            let mut buf = vec![0.0f32; 512];
+            engine.tick(&mut buf); // soft start
+            engine.tick(&mut buf);
+            buf
+        };
+        assert!(render(false).iter().all(|&s| s != 0.0));
+        let flushed = render(true);
+        if DenormalGuard::is_supported() {
+            assert!(flushed.iter().all(|&s| s == 0.0));
+        } else {
+            assert!(flushed.iter().all(|&s| s != 0.0));
+        }
+        // The mode does not leak out of the callback.
+        assert_ne!(
+            std::hint::black_box(1e-20f32) * std::hint::black_box(1e-20f32),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_block_size_mismatch_reported_once() {
+        use crate::event::Event;
+
+        let (_, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 256];
+        for _ in 0..4 {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        }
+        assert_eq!(
+            evt_rx.try_recv(),
+            Some(Event::BlockSizeMismatch {
+                compiled: 512,
+                actual: 256
+            })
+        );
+        assert!(evt_rx.try_recv().is_none(), "reported only once");
+
+        // A different mismatch against the same graph is not reported again.
+        let mut buf = vec![0.0f32; 128];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(evt_rx.try_recv().is_none());
+
+        // A new graph reports again.
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        while let Some(event) = evt_rx.try_recv() {
+            assert!(matches!(event, Event::GraphSwapped(_)));
+        }
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(matches!(
+            evt_rx.try_recv(),
+            Some(Event::BlockSizeMismatch { actual: 128, .. })
+        ));
+    }
+
+    #[test]
+    fn test_alternating_block_sizes_reported_once() {
+        use crate::event::Event;
+
+        let (_, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(64);
+        let mut engine = Engine::new(44_100, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut short = vec![0.0f32; 441];
+        let mut long = vec![0.0f32; 442];
+        for _ in 0..20 {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut short);
+            engine.process_audio(&cmd_rx, &evt_tx, &mut long);
+        }
+        let mismatches = std::iter::from_fn(|| evt_rx.try_recv())
+            .filter(|e| matches!(e, Event::BlockSizeMismatch { compiled: 512, .. }))
+            .count();
+        assert_eq!(mismatches, 1);
+    }
+
+    #[test]
+    fn test_pause_fades_out_then_resume_fades_in() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf); // soft start
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 1.0), "steady state is unity");
+
+        cmd_tx.try_send(Command::Pause).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf[0] > 0.9, "fade starts near full level, not cut");
+        assert!(
+            buf.windows(2).all(|w| w[1] <= w[0]),
+            "fade-out is monotonic"
+        );
+        assert_eq!(buf[511], 0.0, "5 ms fade completes within the block");
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 0.0), "silent once faded out");
+
+        cmd_tx.try_send(Command::Resume).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf[0] < 0.1, "fade-in starts near silence");
+        assert!(buf.windows(2).all(|w| w[1] >= w[0]), "fade-in is monotonic");
+        assert_eq!(buf[511], 1.0, "back to full level");
+    }
+
+    #[test]
+    fn test_pause_keeps_the_stream_and_stop_ends_it() {
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        let stop = engine.stop_signal();
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+
+        // Pause: silent, but the graph stays loaded and the stream stays up.
+        cmd_tx.try_send(Command::Pause).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 0.0));
+        assert!(engine.is_paused());
+        assert!(!stop.is_set(), "pause must not end the stream");
+        assert!(evt_rx.try_recv().is_none());
+        cmd_tx.try_send(Command::Resume).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 1.0), "same graph picks up again");
+
+        // Stop: fades out, raises the signal and reports it once; Resume can't undo it.
+        cmd_tx.try_send(Command::Stop).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(stop.is_set());
+        assert!(matches!(
+            evt_rx.try_recv(),
+            Some(crate::event::Event::StreamStopped)
+        ));
+        cmd_tx.try_send(Command::Resume).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(buf.iter().all(|&s| s == 0.0));
+        assert!(stop.is_set());
+        assert!(evt_rx.try_recv().is_none(), "StreamStopped is sent once");
+    }
+
+    #[test]
+    fn test_first_block_soft_starts() {
+        let (_, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        let mut buf = vec![0.0f32; 512];
+        // Silent blocks before the first graph arrives don't consume the ramp.
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert!(
+            buf[0] < 0.01,
+            "first sample starts near silence, got {}",
+            buf[0]
+        );
+        assert!(buf.windows(2).all(|w| w[1] >= w[0]), "ramp is monotonic");
+        // 10 ms at 48 kHz = 480 samples.
+        assert!(
+            (buf[240] - 0.5).abs() < 0.01,
+            "halfway at 5 ms, got {}",
+            buf[240]
+        );
+        assert_eq!(buf[480], 1.0, "full level after 10 ms");
+    }
+
+    #[test]
+    fn test_set_gain_at_splits_block() {
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        engine.apply_command(
+            Command::SetGainAt {
+                gain: 0.5,
+                at_sample: 32,
+            },
+            &evt_tx,
+        );
+        let mut buf = vec![0.0f32; 64];
+        engine.render_block(&mut buf);
+        assert!(
+            buf[..32].iter().all(|&s| s == 1.0),
+            "old gain before sample 32"
+        );
+        assert!(
+            buf[32..].iter().all(|&s| s == 0.5),
+            "new gain from sample 32"
+        );
+
+        // A time already past applies immediately.
+        engine.apply_command(
+            Command::SetGainAt {
+                gain: 0.25,
+                at_sample: 10,
+            },
+            &evt_tx,
+        );
+        engine.render_block(&mut buf);
+        assert!(buf.iter().all(|&s| s == 0.25));
+    }
+
+    #[test]
+    fn test_sample_counter_counts_rendered_samples() {
+        let (_, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        let counter = engine.sample_counter();
+        assert_eq!(counter.get(), 0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 64];
+        let mut last = 0;
+        for _ in 0..3 {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+            let now = counter.get();
+            assert!(now > last, "counter is monotonic");
+            last = now;
+        }
+        assert_eq!(counter.get(), 192);
+    }
+
+    #[test]
+    fn test_mute_keeps_graph_running() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut reference = g.compile(512).unwrap();
+        let mut expected = vec![0.0f32; 512];
+
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        for _ in 0..2 {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+            reference.process(&mut expected);
+        }
+
+        cmd_tx.try_send(Command::SetMute(true)).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        reference.process(&mut expected);
+        assert!(buf[240..].iter().all(|&s| s == 0.0), "silent after 5 ms");
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        reference.process(&mut expected);
+        assert!(buf.iter().all(|&s| s == 0.0), "muted block is silent");
+
+        cmd_tx.try_send(Command::SetMute(false)).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        reference.process(&mut expected);
+        assert!(buf[0].abs() < 0.01, "unmute ramps in");
+        // Once the 10 ms ramp is done the sine matches one that never stopped.
+        assert_eq!(&buf[480..], &expected[480..]);
+    }
+
+    #[test]
+    fn test_set_gain_scales_active_graph_output() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut reference = g.compile(64).unwrap();
+        let mut expected = vec![0.0f32; 64];
+        reference.process(&mut expected);
+
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(64).unwrap()), &evt_tx);
+        engine.apply_command(Command::SetGain(0.5), &evt_tx);
+        let mut buf = vec![0.0f32; 64];
+        engine.render_block(&mut buf);
+        for (out, want) in buf.iter().zip(&expected) {
+            assert_eq!(*out, want * 0.5);
+        }
+    }
+
+    #[test]
+    fn test_master_limiter_caps_hot_graph() {
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        // +6 dB: a constant 2.0.
+        engine.apply_command(Command::SwapGraph(dc_graph(2.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 256];
+        engine.render_block(&mut buf);
+        assert!(buf.iter().all(|&s| s.abs() <= 1.0), "limited to 0 dBFS");
+
+        engine.apply_command(Command::SetMasterLimiter(false), &evt_tx);
+        engine.render_block(&mut buf);
+        assert!(
+            buf.iter().all(|&s| s == 2.0),
+            "bypassed: peaks pass through"
+        );
+    }
+
+    #[test]
+    fn test_clip_state_turns_on_once_and_off_after_hold() {
+        use super::CLIP_HOLD_BLOCKS;
+        use crate::event::Event;
+
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(64);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(2.0)), &evt_tx);
+        let clip_events = |evt_rx: &crate::event::EventReceiver| {
+            std::iter::from_fn(|| evt_rx.try_recv())
+                .filter_map(|e| match e {
+                    Event::ClipState(on) => Some(on),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(
+            clip_events(&evt_rx),
+            [true],
+            "one loud block turns the light on"
+        );
+
+        cmd_tx.try_send(Command::SetGain(0.1)).unwrap();
+        for block in 1..CLIP_HOLD_BLOCKS {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+            assert!(
+                clip_events(&evt_rx).is_empty(),
+                "still held at block {block}"
+            );
+        }
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(clip_events(&evt_rx), [false], "off after the hold");
+        for _ in 0..CLIP_HOLD_BLOCKS {
+            engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        }
+        assert!(clip_events(&evt_rx).is_empty(), "no repeats while quiet");
+    }
+
+    #[test]
+    fn test_command_log_records_sample_positions_in_order() {
+        use crate::automation::{CommandLog, LoggedCommand};
+        use crate::command::{CommandBatch, ParamCommand};
+        use std::sync::Arc;
+
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _) = event_channel(4);
+        let log = Arc::new(CommandLog::with_capacity(16));
+        log.set_armed(true);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.set_command_log(Some(Arc::clone(&log)));
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+
+        let mut buf = vec![0.0f32; 64];
+        cmd_tx.try_send(Command::SetGain(0.5)).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        cmd_tx.try_send(Command::SetFrequency(220.0)).unwrap();
+        cmd_tx.try_send(Command::QueryState).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        let mut batch = CommandBatch::new();
+        batch.push(ParamCommand::SetMute(true)).unwrap();
+        cmd_tx.try_send(Command::Batch(batch)).unwrap();
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+
+        assert_eq!(
+            log.drain(),
+            [
+                (0, LoggedCommand::SwapGraph),
+                (0, LoggedCommand::SetGain(0.5)),
+                (64, LoggedCommand::SetFrequency(220.0)),
+                (64, LoggedCommand::QueryState),
+                (128, LoggedCommand::SetMute(true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ramp_gain_is_linear() {
+        let (evt_tx, _) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 0.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        // 10 ms at 48 kHz = 480 samples.
+        engine.apply_command(
+            Command::RampGain {
+                target: 1.0,
+                duration_ms: 10.0,
+            },
+            &evt_tx,
+        );
+        let mut buf = vec![0.0f32; 512];
+        engine.render_block(&mut buf);
+        assert!((buf[239] - 0.5).abs() < 1e-3, "half way at the midpoint");
+        assert!(buf.windows(2).all(|w| w[1] >= w[0]));
+        assert!(buf[479..].iter().all(|&s| s == 1.0), "reaches the target");
+
+        // A new ramp replaces the old one, starting from the current gain.
+        engine.apply_command(
+            Command::RampGain {
+                target: 0.0,
+                duration_ms: 10.0,
+            },
+            &evt_tx,
+        );
+        engine.render_block(&mut buf);
+        assert!((buf[239] - 0.5).abs() < 1e-3);
+        assert_eq!(buf[511], 0.0);
+    }
+
+    #[test]
+    fn test_freeze_loops_last_block() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(300.0, 48_000)));
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(256).unwrap()), &evt_tx);
+
+        let mut last = vec![0.0f32; 256];
+        engine.render_block(&mut last);
+        engine.apply_command(Command::Freeze(true), &evt_tx);
+        let mut first = vec![0.0f32; 256];
+        let mut second = vec![0.0f32; 256];
+        engine.render_block(&mut first);
+        engine.render_block(&mut second);
+        assert_eq!(first, last, "frozen output is the captured block");
+        assert_eq!(second, first, "and repeats");
+
+        engine.apply_command(Command::Freeze(false), &evt_tx);
+        let mut live = vec![0.0f32; 256];
+        engine.render_block(&mut live);
+        assert_ne!(live, second, "graph output resumes");
+    }
+
+    #[test]
+    fn test_mono_graph_on_stereo_engine_reports_mismatch() {
+        use crate::event::Event;
+
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.set_channels(2);
+        engine.apply_command(Command::SwapGraph(dc_graph(0.5)), &evt_tx);
+        assert_eq!(
+            evt_rx.try_recv(),
+            Some(Event::ChannelMismatch {
+                expected: 2,
+                got: 1
+            })
+        );
+        let mut buf = vec![1.0f32; 512];
+        engine.render_block(&mut buf);
+        assert!(buf.iter().all(|&s| s == 0.0), "mismatched graph is muted");
+
+        engine.set_channels(1);
+        engine.render_block(&mut buf);
+        assert_eq!(buf[100], 0.5);
+    }
+
+    #[test]
+    fn test_safe_mode_caps_set_gain() {
+        use super::SAFE_MODE_MAX_GAIN;
+
+        let (evt_tx, _) = event_channel(4);
+        let mut normal = Engine::new(48_000, 440.0, 0.5);
+        normal.apply_command(Command::SetGain(1.0), &evt_tx);
+        assert_eq!(normal.master_gain, 1.0);
+
+        let mut safe = Engine::new(48_000, 440.0, 1.0);
+        safe.set_safe_mode(true);
+        assert_eq!(
+            safe.master_gain, SAFE_MODE_MAX_GAIN,
+            "initial gain is clamped"
+        );
+        safe.apply_command(Command::SetMasterLimiter(false), &evt_tx);
+        assert!(safe.limiter_enabled, "limiter stays on");
+        safe.apply_command(Command::SetGain(0.1), &evt_tx);
+        assert_eq!(safe.master_gain, 0.1);
+        safe.apply_command(Command::SetGain(1.0), &evt_tx);
+        assert_eq!(safe.master_gain, SAFE_MODE_MAX_GAIN);
+
+        // Even a graph far over full scale comes out at the safe ceiling.
+        safe.apply_command(Command::SwapGraph(dc_graph(8.0)), &evt_tx);
+        let mut buf = vec![0.0f32; 512];
+        safe.render_block(&mut buf);
+        assert!(buf.iter().all(|s| s.abs() <= SAFE_MODE_MAX_GAIN + 1e-6));
+    }
+
+    #[test]
+    fn test_batch_applies_together_in_one_callback() {
+        use crate::command::{CommandBatch, ParamCommand};
+        use crate::event::Event;
+
+        let (cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+        let mut batch = CommandBatch::new();
+        batch.push(ParamCommand::SetGain(0.25)).unwrap();
+        batch.push(ParamCommand::SetFrequency(880.0)).unwrap();
+        cmd_tx.try_send(Command::Batch(batch)).unwrap();
+
+        // One callback picks up the whole batch: every sample of it sees the new gain.
+        let mut buf = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut buf);
+        assert_eq!(engine.master_gain, 0.25);
+        assert_eq!(buf[511], 0.25, "soft start done; gain already applied");
+        engine.apply_command(Command::QueryState, &evt_tx);
+        match evt_rx.try_recv() {
+            Some(Event::State { gain, freq, .. }) => {
+                assert_eq!(gain, 0.25);
+                assert_eq!(freq, 880.0);
+            }
+            other => panic!("expected State, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_frequency_retunes_graph_oscillators() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::{GainProcessor, SineGenerator};
+
+        let (evt_tx, _) = event_channel(4);
+        let sine_gain = |hz| {
+            let mut g = AudioGraph::new();
+            let sine = g.add_node(GraphNode::Sine(SineGenerator::new(hz, 48_000)));
+            let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+            g.add_edge(sine, gain);
+            g.compile(64).unwrap()
+        };
+        let mut reference = sine_gain(880.0);
+        let mut expected = vec![0.0f32; 64];
+        reference.process(&mut expected);
+
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(sine_gain(220.0)), &evt_tx);
+        engine.apply_command(Command::SetFrequency(880.0), &evt_tx);
+        let mut buf = vec![0.0f32; 64];
+        engine.render_block(&mut buf);
+        assert_eq!(buf, expected, "the sine plays at the new pitch");
+        assert_eq!(engine.frequency_hz, 880.0);
+    }
+
+    #[test]
+    fn test_out_of_range_params_are_clamped_and_reported() {
+        use crate::event::Event;
+
+        let (evt_tx, evt_rx) = event_channel(4);
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SetFrequency(30_000.0), &evt_tx);
+        match evt_rx.try_recv() {
+            Some(Event::ParamClamped {
+                param: "frequency",
+                requested,
+                applied,
+            }) => {
+                assert_eq!(requested, 30_000.0);
+                assert!(applied < 24_000.0 && applied > 23_999.0);
+                assert_eq!(engine.frequency_hz, applied);
+            }
+            other => panic!("expected ParamClamped, got {:?}", other),
+        }
+        engine.apply_command(Command::SetGain(50.0), &evt_tx);
+        assert_eq!(
+            evt_rx.try_recv(),
+            Some(Event::ParamClamped {
+                param: "gain",
+                requested: 50.0,
+                applied: 4.0
+            })
+        );
+
+        engine.apply_command(Command::SetFrequency(880.0), &evt_tx);
+        engine.apply_command(Command::SetGain(2.0), &evt_tx);
+        assert_eq!(evt_rx.try_recv(), None, "valid values are not reported");
+        assert_eq!(engine.frequency_hz, 880.0);
+        assert_eq!(engine.master_gain, 2.0);
+    }
 }
@@ -6,14 +6,79 @@ use crate::graph::CompiledGraph;
 use crate::ring_buffer::RingBuffer;
 
 /// Notification from the audio thread to the control thread.
+///
+/// With the `serde` feature, events serialize adjacently tagged, e.g.
+/// `{"type":"CpuLoad","value":0.5}` or `{"type":"ClipState","value":true}`; unit variants have
+/// no `value`, and `GraphSwapped` carries the returned graph's node count.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Event {
     NoOp,
     /// Previous compiled graph (control thread should drop it for deallocation).
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_node_count"))]
     GraphSwapped(CompiledGraph),
+    /// The engine finished a `Command::Stop`; the stream is about to end.
     StreamStopped,
     /// Output stream is starting; use this sample rate for file feeders and graph (Hz).
     StreamStarted(u32),
+    /// Reply to Command::QueryState: the engine's gain, frequency, node count of the running
+    /// graph (0 if none), and whether it is paused (or stopping).
+    State {
+        gain: f32,
+        freq: f32,
+        graph_nodes: usize,
+        paused: bool,
+    },
+    /// A node panicked during the audio callback. The panic was caught, the block was replaced
+    /// with silence, and the stream kept running; the graph stays silent until the next
+    /// SwapGraph.
+    ProcessorPanicked,
+    /// Smoothed fraction of the real-time budget the output callback spends rendering
+    /// (1.0 = a block takes as long as it plays; above that, audio drops out). Sent a few
+    /// times per second.
+    CpuLoad(f32),
+    /// A swapped-in graph was compiled for a different channel count than the engine renders
+    /// (`expected`); it renders silence until a matching graph is swapped in.
+    ChannelMismatch {
+        expected: usize,
+        got: usize,
+    },
+    /// A command asked for an out-of-range value; `applied` was used instead of `requested`.
+    /// `param` is `"gain"` or `"frequency"`.
+    ParamClamped {
+        param: &'static str,
+        requested: f32,
+        applied: f32,
+    },
+    /// Clip indicator changed: `true` when the output (before the master limiter) went over
+    /// 0 dBFS, `false` once it has stayed below 0.9 (about -1 dBFS) for a number of callbacks.
+    /// Sent only on changes, so it can drive a UI light directly.
+    ClipState(bool),
+    /// The device callback delivers `actual` samples but the running graph was compiled for
+    /// `compiled`. Shorter callbacks are rendered at their own length. Longer ones are split
+    /// into compiled blocks and the last one is rendered ahead and buffered, so a graph reading
+    /// live input reads past what the device has delivered and hears gaps. Compile with the
+    /// device's block size to avoid both that and uneven work per callback. Sent once per
+    /// compiled graph.
+    BlockSizeMismatch {
+        compiled: usize,
+        actual: usize,
+    },
+    /// The running graph has output nothing but zeros for a second of audio although nothing
+    /// asked for silence (not muted, paused or at zero gain), e.g. because its last node is
+    /// cut off from the sources. Sent once; armed again when sound returns.
+    UnexpectedSilence,
+}
+
+/// Serializes `GraphSwapped` as the returned graph's node count; compiled graphs have no
+/// serialized form.
+#[cfg(feature = "serde")]
+fn serialize_node_count<S: serde::Serializer>(
+    graph: &CompiledGraph,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(graph.node_count() as u64)
 }
 
 /// Producer side of the event channel. Only the audio thread should hold this.
@@ -63,6 +128,40 @@ mod tests {
         assert_eq!(event1, event2);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_event_serializes_as_json_line() {
+        let json = |event: &Event| serde_json::to_string(event).unwrap();
+        assert_eq!(
+            json(&Event::CpuLoad(0.5)),
+            r#"{"type":"CpuLoad","value":0.5}"#
+        );
+        assert_eq!(
+            json(&Event::StreamStarted(48_000)),
+            r#"{"type":"StreamStarted","value":48000}"#
+        );
+        assert_eq!(json(&Event::StreamStopped), r#"{"type":"StreamStopped"}"#);
+        assert_eq!(
+            json(&Event::State {
+                gain: 0.5,
+                freq: 440.0,
+                graph_nodes: 2,
+                paused: false,
+            }),
+            r#"{"type":"State","value":{"gain":0.5,"freq":440.0,"graph_nodes":2,"paused":false}}"#
+        );
+        // Non-finite numbers have no JSON form and become null.
+        assert!(json(&Event::CpuLoad(f32::NAN)).contains(r#""value":null"#));
+        let mut graph = crate::graph::AudioGraph::new();
+        graph.add_node(crate::graph::GraphNode::Sine(
+            crate::nodes::SineGenerator::new(440.0, 48_000),
+        ));
+        assert_eq!(
+            json(&Event::GraphSwapped(graph.compile(64).unwrap())),
+            r#"{"type":"GraphSwapped","value":1}"#
+        );
+    }
+
     #[test]
     fn test_event_channel_send_recv() {
         let (sender, receiver) = event_channel(4);
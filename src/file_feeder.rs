@@ -22,12 +22,10 @@ impl std::fmt::Display for FileFeederError {
 
 impl std::error::Error for FileFeederError {}
 
-/// Load WAV from path into mono f32. Returns (mono_samples, file_sample_rate).
-fn load_wav_mono(path: &Path) -> Result<(Vec<f32>, u32), FileFeederError> {
+/// Load WAV from path as interleaved f32. Returns (samples, channels, file_sample_rate).
+fn load_wav_samples(path: &Path) -> Result<(Vec<f32>, usize, u32), FileFeederError> {
     let reader = WavReader::open(path).map_err(FileFeederError::Open)?;
     let spec = reader.spec();
-    let file_rate = spec.sample_rate;
-    let channels = spec.channels as usize;
     let samples: Vec<f32> = match spec.sample_format {
         hound::SampleFormat::Int => {
             let max = 1 << (spec.bits_per_sample - 1);
@@ -43,14 +41,20 @@ fn load_wav_mono(path: &Path) -> Result<(Vec<f32>, u32), FileFeederError> {
             .map(|s| s.clamp(-1.0, 1.0))
             .collect(),
     };
+    if samples.is_empty() {
+        return Err(FileFeederError::Format("file has no samples".to_string()));
+    }
+    Ok((samples, spec.channels.max(1) as usize, spec.sample_rate))
+}
+
+/// Load WAV from path into mono f32. Returns (mono_samples, file_sample_rate).
+fn load_wav_mono(path: &Path) -> Result<(Vec<f32>, u32), FileFeederError> {
+    let (samples, channels, file_rate) = load_wav_samples(path)?;
     let mono: Vec<f32> = if channels == 2 {
         samples.chunks(2).map(|c| (c[0] + c[1]) * 0.5).collect()
     } else {
         samples
     };
-    if mono.is_empty() {
-        return Err(FileFeederError::Format("file has no samples".to_string()));
-    }
     Ok((mono, file_rate))
 }
 
@@ -107,3 +111,32 @@ pub fn load_wav_at_rate(
     let (mono, file_rate) = load_wav_mono(path.as_ref())?;
     Ok(resample_to_rate(&mono, file_rate, target_sample_rate))
 }
+
+/// Loads a WAV file keeping all channels: returns interleaved f32 samples at
+/// `target_sample_rate` (each channel resampled separately) and the channel count.
+/// Use with [`crate::nodes::FilePlayer`].
+pub fn load_wav_interleaved_at_rate(
+    path: impl AsRef<Path>,
+    target_sample_rate: u32,
+) -> Result<(Vec<f32>, usize), FileFeederError> {
+    let (samples, channels, file_rate) = load_wav_samples(path.as_ref())?;
+    if file_rate == target_sample_rate || channels == 1 {
+        let samples = if channels == 1 {
+            resample_to_rate(&samples, file_rate, target_sample_rate)
+        } else {
+            samples
+        };
+        return Ok((samples, channels));
+    }
+    let resampled: Vec<Vec<f32>> = (0..channels)
+        .map(|c| {
+            let channel: Vec<f32> = samples.iter().skip(c).step_by(channels).copied().collect();
+            resample_to_rate(&channel, file_rate, target_sample_rate)
+        })
+        .collect();
+    let frames = resampled[0].len();
+    let interleaved = (0..frames)
+        .flat_map(|i| resampled.iter().map(move |ch| ch[i]))
+        .collect();
+    Ok((interleaved, channels))
+}
@@ -1,18 +1,21 @@
 //! Graph types: node identity, AudioGraph (control-thread), and CompiledGraph.
 
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::sync::Arc;
 
-use crate::audio_buffer::AudioBuffer;
+use crate::audio_buffer::ScratchPool;
 use crate::meter::MeterBuffer;
 use crate::nodes::{
-    BiquadFilter, DelayLine, Echo, GainProcessor, InputNode, Mixer, Overdrive, RecordNode,
-    SineGenerator, Tremolo,
+    scale_into, AutoGain, AutoPanner, Balance, BiquadFilter, Chorus, DeInterleave, DelayLine, Echo,
+    FilePlayer, GainProcessor, InputNode, Interleave, Limiter, MeterTap, Metronome, Mixer, MonoSum,
+    Noise, Overdrive, PingPongDelay, QuadratureOsc, RecordNode, SampleHold, ScopeTap,
+    SineGenerator, Slew, Tremolo,
 };
-use crate::processor::Processor;
+use crate::processor::{DynProcessor, Processor};
 
 /// Identifies a node in the audio graph. Newtype so we don't confuse node indices with other integers.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NodeId(usize);
 
 impl NodeId {
@@ -27,6 +30,75 @@ impl NodeId {
     }
 }
 
+/// Identity of a node that survives compilation. [`NodeId`] is a position, and the compiled graph
+/// stores nodes in topological order, so positions differ between an `AudioGraph` and its
+/// `CompiledGraph`; a `StableId` is assigned once by [`AudioGraph::add_node`] and is the same
+/// in every graph compiled from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StableId(u64);
+
+impl StableId {
+    /// Returns the raw value, for logging and debugging.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Rebuilds an id from [`as_u64`](Self::as_u64), for the command log.
+    pub(crate) fn from_u64(raw: u64) -> Self {
+        StableId(raw)
+    }
+}
+
+/// A node parameter that can be read and set while the graph is compiled (see
+/// [`GraphNode::params`]). Which ones a node has depends on its type; units are the node's own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamId {
+    /// Linear gain: Gain, MonoSum, and Noise's amplitude.
+    Gain,
+    /// Oscillator frequency in Hz (Sine, Quadrature).
+    Frequency,
+    /// Balance's left-channel gain.
+    LeftGain,
+    /// Balance's right-channel gain.
+    RightGain,
+    /// Gain of one Mixer input.
+    InputGain(usize),
+    /// Delay time in milliseconds (Delay, Echo, PingPong).
+    DelayMs,
+    /// Echo's dry level.
+    Dry,
+    /// Echo's wet level.
+    Wet,
+    /// Feedback amount (Echo, PingPong).
+    Feedback,
+    /// Dry/wet mix (PingPong, Chorus).
+    Mix,
+    /// LFO or clock rate in Hz (Tremolo, AutoPan, Chorus, SampleHold).
+    Rate,
+    /// Modulation depth: 0..=1 for Tremolo and AutoPan, milliseconds for Chorus.
+    Depth,
+    /// Overdrive's drive amount.
+    Drive,
+    /// Metronome tempo in beats per minute.
+    Bpm,
+    /// Slew's maximum upward change per second.
+    RiseRate,
+    /// Slew's maximum downward change per second.
+    FallRate,
+    /// AutoGain's target RMS level.
+    TargetRms,
+    /// AutoGain's attack time in milliseconds.
+    AttackMs,
+    /// AutoGain's release time in milliseconds.
+    ReleaseMs,
+    /// AutoGain's upper bound on applied gain.
+    MaxGain,
+    /// Limiter ceiling (1.0 = 0 dBFS).
+    Ceiling,
+    /// FilePlayer playback rate (1.0 = normal speed).
+    PlaybackRate,
+}
+
 /// A single node in the graph: one of the supported processor types.
 #[derive(Clone, Debug, PartialEq)]
 pub enum GraphNode {
@@ -40,6 +112,204 @@ pub enum GraphNode {
     Overdrive(Overdrive),
     Biquad(BiquadFilter),
     Record(RecordNode),
+    Metronome(Metronome),
+    Noise(Noise),
+    PingPong(PingPongDelay),
+    Chorus(Chorus),
+    SampleHold(SampleHold),
+    Slew(Slew),
+    Meter(MeterTap),
+    Scope(ScopeTap),
+    Balance(Balance),
+    AutoGain(AutoGain),
+    Limiter(Limiter),
+    Quadrature(QuadratureOsc),
+    FilePlayer(FilePlayer),
+    AutoPan(AutoPanner),
+    MonoSum(MonoSum),
+    DeInterleave(DeInterleave),
+    Interleave(Interleave),
+    /// Impulse-response convolution; needs the `convolution` feature.
+    #[cfg(feature = "convolution")]
+    Convolve(crate::convolver::Convolver),
+    /// Any other [`Processor`]; lets new node types be added without extending this enum.
+    /// Compares equal only to itself, never to a clone (and a zero-sized processor not even to
+    /// itself), so [`AudioGraph::diff`] between a graph and its copy lists every `Dynamic` node
+    /// as changed.
+    Dynamic(Box<dyn DynProcessor>),
+}
+
+impl GraphNode {
+    /// Number of inputs the node reads: 0 for sources, one per gain for a mixer (at least one,
+    /// so a mixer is never a source), 2 for Interleave (left, right), whatever a `Dynamic`
+    /// processor reports from [`Processor::num_inputs`], and 1 for everything else.
+    pub fn num_inputs(&self) -> usize {
+        match self {
+            GraphNode::Sine(_)
+            | GraphNode::Input(_)
+            | GraphNode::Metronome(_)
+            | GraphNode::Noise(_)
+            | GraphNode::Quadrature(_)
+            | GraphNode::FilePlayer(_) => 0,
+            GraphNode::Mixer(m) => m.gains.len().max(1),
+            GraphNode::Interleave(_) => 2,
+            GraphNode::Dynamic(p) => p.num_inputs(),
+            _ => 1,
+        }
+    }
+
+    /// Variant name, e.g. `"Sine"` or `"Mixer"`, for listings such as [`AudioGraph::describe`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GraphNode::Sine(_) => "Sine",
+            GraphNode::Gain(_) => "Gain",
+            GraphNode::Mixer(_) => "Mixer",
+            GraphNode::Input(_) => "Input",
+            GraphNode::Delay(_) => "Delay",
+            GraphNode::Echo(_) => "Echo",
+            GraphNode::Tremolo(_) => "Tremolo",
+            GraphNode::Overdrive(_) => "Overdrive",
+            GraphNode::Biquad(_) => "Biquad",
+            GraphNode::Record(_) => "Record",
+            GraphNode::Metronome(_) => "Metronome",
+            GraphNode::Noise(_) => "Noise",
+            GraphNode::PingPong(_) => "PingPong",
+            GraphNode::Chorus(_) => "Chorus",
+            GraphNode::SampleHold(_) => "SampleHold",
+            GraphNode::Slew(_) => "Slew",
+            GraphNode::Meter(_) => "Meter",
+            GraphNode::Scope(_) => "Scope",
+            GraphNode::Balance(_) => "Balance",
+            GraphNode::AutoGain(_) => "AutoGain",
+            GraphNode::Limiter(_) => "Limiter",
+            GraphNode::Quadrature(_) => "Quadrature",
+            GraphNode::FilePlayer(_) => "FilePlayer",
+            GraphNode::AutoPan(_) => "AutoPan",
+            GraphNode::MonoSum(_) => "MonoSum",
+            GraphNode::DeInterleave(_) => "DeInterleave",
+            GraphNode::Interleave(_) => "Interleave",
+            #[cfg(feature = "convolution")]
+            GraphNode::Convolve(_) => "Convolve",
+            GraphNode::Dynamic(_) => "Dynamic",
+        }
+    }
+
+    /// True for nodes that generate signal and ignore their inputs (`num_inputs() == 0`).
+    pub fn is_source(&self) -> bool {
+        self.num_inputs() == 0
+    }
+
+    /// True for nodes that send audio out of the graph (to a recording, a meter or a scope).
+    /// They also pass their input through, so they can sit anywhere in a chain.
+    pub fn is_sink(&self) -> bool {
+        matches!(
+            self,
+            GraphNode::Record(_) | GraphNode::Meter(_) | GraphNode::Scope(_)
+        )
+    }
+
+    /// Current value of every parameter this node exposes, in a fixed order per node type.
+    /// Nodes without tweakable parameters (taps, inputs, filters, `Dynamic`) return none.
+    pub fn params(&self) -> Vec<(ParamId, f32)> {
+        match self {
+            GraphNode::Sine(s) => vec![(ParamId::Frequency, s.frequency_hz)],
+            GraphNode::Quadrature(q) => vec![(ParamId::Frequency, q.frequency_hz)],
+            GraphNode::Gain(g) => vec![(ParamId::Gain, g.gain)],
+            GraphNode::MonoSum(m) => vec![(ParamId::Gain, m.gain)],
+            GraphNode::Noise(n) => vec![(ParamId::Gain, n.amplitude)],
+            GraphNode::Balance(b) => vec![
+                (ParamId::LeftGain, b.left_gain),
+                (ParamId::RightGain, b.right_gain),
+            ],
+            GraphNode::Mixer(m) => m
+                .gains
+                .iter()
+                .enumerate()
+                .map(|(i, &g)| (ParamId::InputGain(i), g))
+                .collect(),
+            GraphNode::Delay(d) => vec![(ParamId::DelayMs, d.delay_ms)],
+            GraphNode::Echo(e) => vec![
+                (ParamId::DelayMs, e.delay_ms()),
+                (ParamId::Dry, e.dry),
+                (ParamId::Wet, e.wet),
+                (ParamId::Feedback, e.feedback),
+            ],
+            GraphNode::PingPong(p) => vec![
+                (ParamId::DelayMs, p.time_ms()),
+                (ParamId::Feedback, p.feedback),
+                (ParamId::Mix, p.mix),
+            ],
+            GraphNode::Chorus(c) => vec![
+                (ParamId::Rate, c.rate_hz),
+                (ParamId::Depth, c.depth_ms),
+                (ParamId::Mix, c.mix),
+            ],
+            GraphNode::Tremolo(t) => vec![(ParamId::Rate, t.rate_hz), (ParamId::Depth, t.depth)],
+            GraphNode::AutoPan(a) => vec![(ParamId::Rate, a.rate_hz), (ParamId::Depth, a.depth)],
+            GraphNode::SampleHold(s) => vec![(ParamId::Rate, s.rate_hz)],
+            GraphNode::Overdrive(o) => vec![(ParamId::Drive, o.drive)],
+            GraphNode::Metronome(m) => vec![(ParamId::Bpm, m.bpm)],
+            GraphNode::Slew(s) => vec![
+                (ParamId::RiseRate, s.rise_rate),
+                (ParamId::FallRate, s.fall_rate),
+            ],
+            GraphNode::AutoGain(a) => vec![
+                (ParamId::TargetRms, a.target_rms),
+                (ParamId::AttackMs, a.attack_ms),
+                (ParamId::ReleaseMs, a.release_ms),
+                (ParamId::MaxGain, a.max_gain),
+            ],
+            GraphNode::Limiter(l) => vec![(ParamId::Ceiling, l.ceiling)],
+            GraphNode::FilePlayer(f) => vec![(ParamId::PlaybackRate, f.playback_rate as f32)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets parameter `id` to `value`, going through the node's own setter where it has one
+    /// (so delay times are clamped to capacity). Returns false if the node has no such parameter.
+    pub fn set_param(&mut self, id: ParamId, value: f32) -> bool {
+        match (self, id) {
+            (GraphNode::Sine(s), ParamId::Frequency) => s.set_frequency(value),
+            (GraphNode::Quadrature(q), ParamId::Frequency) => q.frequency_hz = value,
+            (GraphNode::Gain(g), ParamId::Gain) => g.gain = value,
+            (GraphNode::MonoSum(m), ParamId::Gain) => m.gain = value,
+            (GraphNode::Noise(n), ParamId::Gain) => n.amplitude = value,
+            (GraphNode::Balance(b), ParamId::LeftGain) => b.left_gain = value,
+            (GraphNode::Balance(b), ParamId::RightGain) => b.right_gain = value,
+            (GraphNode::Mixer(m), ParamId::InputGain(i)) => match m.gains.get_mut(i) {
+                Some(g) => *g = value,
+                None => return false,
+            },
+            (GraphNode::Delay(d), ParamId::DelayMs) => d.set_delay_ms(value),
+            (GraphNode::Echo(e), ParamId::DelayMs) => e.set_delay_ms(value),
+            (GraphNode::Echo(e), ParamId::Dry) => e.dry = value,
+            (GraphNode::Echo(e), ParamId::Wet) => e.wet = value,
+            (GraphNode::Echo(e), ParamId::Feedback) => e.feedback = value,
+            (GraphNode::PingPong(p), ParamId::DelayMs) => p.set_time_ms(value),
+            (GraphNode::PingPong(p), ParamId::Feedback) => p.feedback = value,
+            (GraphNode::PingPong(p), ParamId::Mix) => p.mix = value,
+            (GraphNode::Chorus(c), ParamId::Rate) => c.rate_hz = value,
+            (GraphNode::Chorus(c), ParamId::Depth) => c.depth_ms = value,
+            (GraphNode::Chorus(c), ParamId::Mix) => c.mix = value,
+            (GraphNode::Tremolo(t), ParamId::Rate) => t.rate_hz = value,
+            (GraphNode::Tremolo(t), ParamId::Depth) => t.depth = value,
+            (GraphNode::AutoPan(a), ParamId::Rate) => a.rate_hz = value,
+            (GraphNode::AutoPan(a), ParamId::Depth) => a.depth = value,
+            (GraphNode::SampleHold(s), ParamId::Rate) => s.rate_hz = value,
+            (GraphNode::Overdrive(o), ParamId::Drive) => o.drive = value,
+            (GraphNode::Metronome(m), ParamId::Bpm) => m.bpm = value,
+            (GraphNode::Slew(s), ParamId::RiseRate) => s.rise_rate = value,
+            (GraphNode::Slew(s), ParamId::FallRate) => s.fall_rate = value,
+            (GraphNode::AutoGain(a), ParamId::TargetRms) => a.target_rms = value,
+            (GraphNode::AutoGain(a), ParamId::AttackMs) => a.attack_ms = value,
+            (GraphNode::AutoGain(a), ParamId::ReleaseMs) => a.release_ms = value,
+            (GraphNode::AutoGain(a), ParamId::MaxGain) => a.max_gain = value,
+            (GraphNode::Limiter(l), ParamId::Ceiling) => l.ceiling = value,
+            (GraphNode::FilePlayer(f), ParamId::PlaybackRate) => f.playback_rate = value as f64,
+            _ => return false,
+        }
+        true
+    }
 }
 
 impl Processor for GraphNode {
@@ -55,24 +325,139 @@ impl Processor for GraphNode {
             GraphNode::Overdrive(o) => o.process(inputs, output),
             GraphNode::Biquad(b) => b.process(inputs, output),
             GraphNode::Record(r) => r.process(inputs, output),
+            GraphNode::Metronome(m) => m.process(inputs, output),
+            GraphNode::Noise(n) => n.process(inputs, output),
+            GraphNode::PingPong(p) => p.process(inputs, output),
+            GraphNode::Chorus(c) => c.process(inputs, output),
+            GraphNode::SampleHold(s) => s.process(inputs, output),
+            GraphNode::Slew(s) => s.process(inputs, output),
+            GraphNode::Meter(m) => m.process(inputs, output),
+            GraphNode::Scope(s) => s.process(inputs, output),
+            GraphNode::Balance(b) => b.process(inputs, output),
+            GraphNode::AutoGain(a) => a.process(inputs, output),
+            GraphNode::Limiter(l) => l.process(inputs, output),
+            GraphNode::Quadrature(q) => q.process(inputs, output),
+            GraphNode::FilePlayer(f) => f.process(inputs, output),
+            GraphNode::AutoPan(a) => a.process(inputs, output),
+            GraphNode::MonoSum(m) => m.process(inputs, output),
+            GraphNode::DeInterleave(d) => d.process(inputs, output),
+            GraphNode::Interleave(i) => i.process(inputs, output),
+            #[cfg(feature = "convolution")]
+            GraphNode::Convolve(c) => c.process(inputs, output),
+            GraphNode::Dynamic(p) => p.process(inputs, output),
+        }
+    }
+
+    fn tail_samples(&self) -> usize {
+        match self {
+            GraphNode::Delay(d) => d.tail_samples(),
+            GraphNode::Echo(e) => e.tail_samples(),
+            GraphNode::PingPong(p) => p.tail_samples(),
+            GraphNode::Chorus(c) => c.tail_samples(),
+            #[cfg(feature = "convolution")]
+            GraphNode::Convolve(c) => c.tail_samples(),
+            GraphNode::Dynamic(p) => p.tail_samples(),
+            _ => 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            GraphNode::Delay(d) => d.reset(),
+            GraphNode::Echo(e) => e.reset(),
+            GraphNode::Overdrive(o) => o.reset(),
+            GraphNode::Biquad(b) => b.reset(),
+            GraphNode::Metronome(m) => m.reset(),
+            GraphNode::PingPong(p) => p.reset(),
+            GraphNode::Chorus(c) => c.reset(),
+            GraphNode::SampleHold(s) => s.reset(),
+            GraphNode::Slew(s) => s.reset(),
+            GraphNode::AutoGain(a) => a.reset(),
+            GraphNode::Limiter(l) => l.reset(),
+            #[cfg(feature = "convolution")]
+            GraphNode::Convolve(c) => c.reset(),
+            GraphNode::Dynamic(p) => p.reset(),
+            _ => (),
         }
     }
 }
 
+/// Most nodes an [`AudioGraph`] may hold. Far beyond any hand-built patch, but keeps a runaway
+/// generator from exhausting memory or building a graph no callback could render in time.
+pub const MAX_NODES: usize = 4096;
+
+/// Most inputs one node may read. [`CompiledGraph::process`] gathers a node's inputs in a fixed
+/// array of this size, so rendering never allocates.
+pub const MAX_INPUTS: usize = 64;
+
 /// Errors from graph operations (e.g. cycle detected, invalid meter config).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GraphError {
-    /// The graph contains a cycle; topological sort is impossible.
-    Cycle,
+    /// The graph contains a cycle; topological sort is impossible. Holds the nodes Kahn's
+    /// algorithm could not schedule: the cycle members plus anything downstream of them.
+    Cycle(Vec<NodeId>),
     /// Meter tap indices or buffer length is invalid.
     InvalidMeterTaps,
+    /// An edge points at a node id that does not exist.
+    DanglingEdge { from: NodeId, to: NodeId },
+    /// A source node (e.g. a sine) has incoming edges; its inputs would be ignored.
+    SourceHasInputs(NodeId),
+    /// A node that processes its inputs (effect, mixer, ...) has none.
+    MissingInputs(NodeId),
+    /// The node's output never reaches the graph output (last node in topological order).
+    Unreachable(NodeId),
+    /// A mixer's gain count doesn't match its number of inputs: `expected` inputs are
+    /// connected, but it has `got` gains.
+    MixerArity { expected: usize, got: usize },
+    /// Adding the node(s) would take the graph past [`MAX_NODES`] (`max`).
+    TooManyNodes { max: usize },
+    /// The node has more incoming edges than [`MAX_INPUTS`] (`max`).
+    TooManyInputs { node: NodeId, max: usize },
+    /// A graph written for `got` interleaved channels can't be combined with one written for
+    /// `expected` (see [`AudioGraph::merge`]).
+    ChannelMismatch { expected: usize, got: usize },
 }
 
 impl std::fmt::Display for GraphError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            GraphError::Cycle => write!(f, "graph contains a cycle"),
+            GraphError::Cycle(nodes) => {
+                let ids: Vec<String> = nodes.iter().map(|id| id.as_usize().to_string()).collect();
+                write!(f, "cycle among nodes [{}]", ids.join(", "))
+            }
             GraphError::InvalidMeterTaps => write!(f, "invalid meter tap configuration"),
+            GraphError::DanglingEdge { from, to } => write!(
+                f,
+                "edge {} -> {} points to a missing node",
+                from.as_usize(),
+                to.as_usize()
+            ),
+            GraphError::SourceHasInputs(id) => {
+                write!(f, "source node {} has incoming edges", id.as_usize())
+            }
+            GraphError::MissingInputs(id) => write!(f, "node {} has no inputs", id.as_usize()),
+            GraphError::Unreachable(id) => {
+                write!(f, "node {} does not reach the output", id.as_usize())
+            }
+            GraphError::MixerArity { expected, got } => write!(
+                f,
+                "mixer has {} gains but {} connected inputs",
+                got, expected
+            ),
+            GraphError::TooManyNodes { max } => {
+                write!(f, "graph already has the maximum of {} nodes", max)
+            }
+            GraphError::TooManyInputs { node, max } => write!(
+                f,
+                "node {} has more than the maximum of {} inputs",
+                node.as_usize(),
+                max
+            ),
+            GraphError::ChannelMismatch { expected, got } => write!(
+                f,
+                "graph is written for {} channels, expected {}",
+                got, expected
+            ),
         }
     }
 }
@@ -81,11 +466,22 @@ impl std::error::Error for GraphError {}
 
 /// Audio graph: adjacency list + node storage. Lives only on the control thread.
 /// Nodes are stored in a Vec; NodeId is the index. Edges go from node A to node B (A feeds B).
+/// Cloning snapshots the whole graph (node state included), e.g. before an experimental edit.
+/// A graph with `Dynamic` nodes never equals its clone (see [`GraphNode::Dynamic`]).
+#[derive(Clone, Debug, PartialEq)]
 pub struct AudioGraph {
     /// nodes[id.as_usize()] is the node for that id.
     nodes: Vec<GraphNode>,
     /// adjacency[id.as_usize()] is the list of node ids that this node's output feeds into.
     adjacency: Vec<Vec<NodeId>>,
+    /// edge_gains[id.as_usize()][k] is the trim on the edge to adjacency[id.as_usize()][k].
+    edge_gains: Vec<Vec<f32>>,
+    /// stable_ids[id.as_usize()] is that node's stable identity.
+    stable_ids: Vec<StableId>,
+    /// Next `StableId` to hand out; only ever increases.
+    next_stable_id: u64,
+    /// Interleaved channel count the graph's nodes are written for; see [`set_channels`](Self::set_channels).
+    channels: usize,
 }
 
 impl Default for AudioGraph {
@@ -100,19 +496,148 @@ impl AudioGraph {
         Self {
             nodes: Vec::new(),
             adjacency: Vec::new(),
+            edge_gains: Vec::new(),
+            stable_ids: Vec::new(),
+            next_stable_id: 0,
+            channels: 1,
         }
     }
 
+    /// Declares the interleaved channel count the graph renders (1 = mono, the default; 2 for
+    /// graphs of stereo nodes such as PingPong or AutoPan). Carried into the compiled graph so
+    /// the engine can refuse to play it into a buffer of a different width.
+    pub fn set_channels(&mut self, channels: usize) {
+        self.channels = channels.max(1);
+    }
+
+    /// Interleaved channel count the graph renders.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
     /// Adds a node and returns its id. The node is not connected to anything yet.
+    /// Panics if the graph already holds [`MAX_NODES`] nodes; use
+    /// [`try_add_node`](Self::try_add_node) when the node count comes from outside input.
     pub fn add_node(&mut self, node: GraphNode) -> NodeId {
+        match self.try_add_node(node) {
+            Ok(id) => id,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like [`add_node`](Self::add_node), but returns `GraphError::TooManyNodes` instead of
+    /// panicking when the graph is full.
+    pub fn try_add_node(&mut self, node: GraphNode) -> Result<NodeId, GraphError> {
+        if self.nodes.len() >= MAX_NODES {
+            return Err(GraphError::TooManyNodes { max: MAX_NODES });
+        }
         self.nodes.push(node);
         self.adjacency.push(Vec::new());
-        NodeId::new(self.nodes.len() - 1)
+        self.edge_gains.push(Vec::new());
+        let stable = self.next_stable_id();
+        self.stable_ids.push(stable);
+        Ok(NodeId::new(self.nodes.len() - 1))
     }
 
-    /// Adds an edge from `from` to `to` (output of `from` feeds into `to`). Panics if either id is out of range.
+    fn next_stable_id(&mut self) -> StableId {
+        let id = StableId(self.next_stable_id);
+        self.next_stable_id += 1;
+        id
+    }
+
+    /// Stable identity of the node at `id`; use it to find the node in a compiled graph.
+    /// Panics if `id` is out of range.
+    pub fn stable_id(&self, id: NodeId) -> StableId {
+        self.stable_ids[id.as_usize()]
+    }
+
+    /// Adds an edge from `from` to `to` (output of `from` feeds into `to`). Panics if `from` is out of range.
+    /// Adding an edge that already exists is a no-op, so in-degree and compiled inputs always agree.
     pub fn add_edge(&mut self, from: NodeId, to: NodeId) {
-        self.adjacency[from.as_usize()].push(to);
+        let succs = &mut self.adjacency[from.as_usize()];
+        if !succs.contains(&to) {
+            succs.push(to);
+            self.edge_gains[from.as_usize()].push(1.0);
+        }
+    }
+
+    /// Adds an edge like [`add_edge`](Self::add_edge) whose signal is scaled by `gain` before
+    /// `to` reads it, a trim without a separate Gain node. On an existing edge, sets its gain.
+    pub fn add_edge_with_gain(&mut self, from: NodeId, to: NodeId, gain: f32) {
+        self.add_edge(from, to);
+        let k = self.adjacency[from.as_usize()]
+            .iter()
+            .position(|&s| s == to)
+            .expect("edge was just added");
+        self.edge_gains[from.as_usize()][k] = gain;
+    }
+
+    /// Trim on the edge from `from` to `to` (1.0 unless set with
+    /// [`add_edge_with_gain`](Self::add_edge_with_gain)), or `None` if there is no such edge.
+    pub fn edge_gain(&self, from: NodeId, to: NodeId) -> Option<f32> {
+        let k = self
+            .adjacency
+            .get(from.as_usize())?
+            .iter()
+            .position(|&s| s == to)?;
+        Some(self.edge_gains[from.as_usize()][k])
+    }
+
+    /// Swaps the node at `id` for `node`, keeping all of its edges, and returns the old node.
+    /// Fails (leaving the graph unchanged) if `node` can't take `id`'s current inputs: a source
+    /// with incoming edges, a processing node without any, or a mixer whose gain count differs
+    /// from the number of inputs. Panics if `id` is out of range.
+    pub fn replace_node(&mut self, id: NodeId, node: GraphNode) -> Result<GraphNode, GraphError> {
+        let inputs = self.in_degree(id);
+        if node.is_source() && inputs > 0 {
+            return Err(GraphError::SourceHasInputs(id));
+        }
+        if !node.is_source() && inputs == 0 {
+            return Err(GraphError::MissingInputs(id));
+        }
+        if let GraphNode::Mixer(mixer) = &node {
+            if mixer.gains.len() != inputs {
+                return Err(GraphError::MixerArity {
+                    expected: inputs,
+                    got: mixer.gains.len(),
+                });
+            }
+        }
+        Ok(std::mem::replace(&mut self.nodes[id.as_usize()], node))
+    }
+
+    /// Appends all of `other`'s nodes and edges to this graph and returns their new ids, in
+    /// `other`'s id order (`other`'s node `i` becomes `returned[i]`). Merged nodes get new
+    /// [`StableId`]s from this graph. Nothing is connected between
+    /// the two parts; add edges from the returned ids afterwards. Returns
+    /// `GraphError::TooManyNodes` if the result would hold more than [`MAX_NODES`] nodes, or
+    /// `GraphError::ChannelMismatch` if the graphs are written for different
+    /// [`channels`](Self::channels) counts; this graph is left unchanged in both cases.
+    pub fn merge(&mut self, other: AudioGraph) -> Result<Vec<NodeId>, GraphError> {
+        if self.nodes.len() + other.nodes.len() > MAX_NODES {
+            return Err(GraphError::TooManyNodes { max: MAX_NODES });
+        }
+        if self.channels != other.channels {
+            return Err(GraphError::ChannelMismatch {
+                expected: self.channels,
+                got: other.channels,
+            });
+        }
+        let offset = self.nodes.len();
+        for _ in 0..other.nodes.len() {
+            let stable = self.next_stable_id();
+            self.stable_ids.push(stable);
+        }
+        self.nodes.extend(other.nodes);
+        self.adjacency
+            .extend(other.adjacency.into_iter().map(|succs| {
+                succs
+                    .into_iter()
+                    .map(|id| NodeId::new(id.as_usize() + offset))
+                    .collect()
+            }));
+        self.edge_gains.extend(other.edge_gains);
+        Ok((offset..self.nodes.len()).map(NodeId::new).collect())
     }
 
     /// Returns the number of nodes.
@@ -120,13 +645,106 @@ impl AudioGraph {
         self.nodes.len()
     }
 
+    /// Returns the node at `id`. Panics if `id` is out of range.
+    pub fn node(&self, id: NodeId) -> &GraphNode {
+        &self.nodes[id.as_usize()]
+    }
+
     /// Returns the successors of the given node (nodes this node's output feeds into).
     pub fn successors(&self, id: NodeId) -> &[NodeId] {
         &self.adjacency[id.as_usize()]
     }
 
+    /// Returns the predecessors of the given node (nodes whose output feeds into it), in id order.
+    /// Scans every edge (O(E)); fine for control-thread queries, not for hot loops.
+    pub fn predecessors(&self, id: NodeId) -> Vec<NodeId> {
+        self.adjacency
+            .iter()
+            .enumerate()
+            .filter(|(_, succs)| succs.contains(&id))
+            .map(|(i, _)| NodeId::new(i))
+            .collect()
+    }
+
+    /// Readable outline of the patch, for debugging: a tree per output (a node that feeds
+    /// nothing), each line `[id] Kind Param=value ...` with the nodes feeding it indented below,
+    /// then every edge. A node that feeds several others is expanded under the first and shown
+    /// as `[id] Kind (above)` elsewhere; trimmed edges show `(edge gain g)`.
+    pub fn describe(&self) -> String {
+        if self.nodes.is_empty() {
+            return "(empty graph)\n".to_string();
+        }
+        let n = self.nodes.len();
+        // 0 = not yet shown, 1 = being expanded (so seeing it again is a cycle), 2 = shown.
+        let mut state = vec![0u8; n];
+        let mut out = String::new();
+        let outputs = (0..n).filter(|&i| self.adjacency[i].is_empty());
+        // Nodes caught in a cycle never reach an output; give them trees of their own.
+        let roots: Vec<usize> = outputs.chain(0..n).collect();
+        for root in roots {
+            if state[root] == 0 {
+                self.describe_node(NodeId::new(root), 0, None, &mut state, &mut out);
+            }
+        }
+        let edges: Vec<String> = self
+            .adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(from, succs)| succs.iter().map(move |to| (from, to.as_usize())))
+            .map(|(from, to)| format!("{} -> {}", from, to))
+            .collect();
+        out.push_str(&format!("edges: {}\n", edges.join(", ")));
+        out
+    }
+
+    fn describe_node(
+        &self,
+        id: NodeId,
+        depth: usize,
+        trim: Option<f32>,
+        state: &mut [u8],
+        out: &mut String,
+    ) {
+        let i = id.as_usize();
+        let node = &self.nodes[i];
+        out.push_str(&format!("{}[{}] {}", "  ".repeat(depth), i, node.kind()));
+        match state[i] {
+            1 => out.push_str(" (cycle)"),
+            2 => out.push_str(" (above)"),
+            _ => {
+                for (param, value) in node.params() {
+                    out.push_str(&format!(" {:?}={}", param, value));
+                }
+            }
+        }
+        if let Some(gain) = trim {
+            out.push_str(&format!(" (edge gain {})", gain));
+        }
+        out.push('\n');
+        if state[i] != 0 {
+            return;
+        }
+        state[i] = 1;
+        for pred in self.predecessors(id) {
+            let gain = self.edge_gain(pred, id).filter(|&g| g != 1.0);
+            self.describe_node(pred, depth + 1, gain, state, out);
+        }
+        state[i] = 2;
+    }
+
+    /// Returns the number of edges pointing into the given node. Scans every edge (O(E)).
+    pub fn in_degree(&self, id: NodeId) -> usize {
+        self.adjacency
+            .iter()
+            .map(|succs| succs.iter().filter(|&&s| s == id).count())
+            .sum()
+    }
+
     /// Returns nodes in topological order (Kahn's algorithm). Nodes with no incoming edges first.
-    /// Returns `Err(GraphError::Cycle)` if the graph contains a cycle.
+    /// Deterministic: among nodes that are ready at the same time, the lowest `NodeId` goes first,
+    /// so the same graph always yields the same order regardless of edge insertion order.
+    /// Returns `Err(GraphError::Cycle(nodes))` if the graph contains a cycle, listing the nodes
+    /// whose in-degree never reached zero.
     pub fn topological_sort(&self) -> Result<Vec<NodeId>, GraphError> {
         let n = self.nodes.len();
         if n == 0 {
@@ -142,31 +760,141 @@ impl AudioGraph {
                 }
             }
         }
-        let mut queue: VecDeque<NodeId> = (0..n)
+        // Min-heap of ready nodes: ties break by NodeId.
+        let mut ready: BinaryHeap<Reverse<NodeId>> = (0..n)
             .filter(|&i| in_degree[i] == 0)
-            .map(NodeId::new)
+            .map(|i| Reverse(NodeId::new(i)))
             .collect();
         let mut order = Vec::with_capacity(n);
-        while let Some(id) = queue.pop_front() {
+        while let Some(Reverse(id)) = ready.pop() {
             order.push(id);
             for &succ in self.successors(id) {
                 let i = succ.as_usize();
                 if i < n {
                     in_degree[i] -= 1;
                     if in_degree[i] == 0 {
-                        queue.push_back(succ);
+                        ready.push(Reverse(succ));
                     }
                 }
             }
         }
         if order.len() != n {
-            return Err(GraphError::Cycle); // cycle: some nodes never got in_degree 0
+            // cycle: some nodes never got in_degree 0
+            let stuck = (0..n)
+                .filter(|&i| in_degree[i] > 0)
+                .map(NodeId::new)
+                .collect();
+            return Err(GraphError::Cycle(stuck));
         }
         Ok(order)
     }
 
+    /// Compares this graph (old) with `other` (new), matching nodes by id. Lets an editor tell a
+    /// parameter tweak (only `changed_nodes`) from a structural edit that needs a full rebuild.
+    /// Nodes compare by value, except `Dynamic` nodes, which only match themselves: diffing a
+    /// graph against its clone lists them all in `changed_nodes`.
+    pub fn diff(&self, other: &AudioGraph) -> GraphDiff {
+        let common = self.nodes.len().min(other.nodes.len());
+        let ids = |range: std::ops::Range<usize>| range.map(NodeId::new).collect::<Vec<_>>();
+        let edges = |g: &AudioGraph| -> Vec<(NodeId, NodeId)> {
+            g.adjacency
+                .iter()
+                .enumerate()
+                .flat_map(|(from, succ)| succ.iter().map(move |&to| (NodeId::new(from), to)))
+                .collect()
+        };
+        let (old_edges, new_edges) = (edges(self), edges(other));
+        let changed_edges = old_edges
+            .iter()
+            .filter(|&&(from, to)| {
+                new_edges.contains(&(from, to))
+                    && self.edge_gain(from, to) != other.edge_gain(from, to)
+            })
+            .copied()
+            .collect();
+        GraphDiff {
+            added_nodes: ids(common..other.nodes.len()),
+            removed_nodes: ids(common..self.nodes.len()),
+            changed_nodes: (0..common)
+                .filter(|&i| self.nodes[i] != other.nodes[i])
+                .map(NodeId::new)
+                .collect(),
+            added_edges: new_edges
+                .iter()
+                .filter(|e| !old_edges.contains(e))
+                .copied()
+                .collect(),
+            removed_edges: old_edges
+                .iter()
+                .filter(|e| !new_edges.contains(e))
+                .copied()
+                .collect(),
+            changed_edges,
+            channels_changed: self.channels != other.channels,
+        }
+    }
+
+    /// Checks the patch for common mistakes and reports every problem found, not just the first:
+    /// dangling edges, cycles, sources with inputs, processing nodes without inputs, and nodes
+    /// that never reach the output. The reachability check is skipped when there is a cycle.
+    pub fn validate(&self) -> Result<(), Vec<GraphError>> {
+        let n = self.nodes.len();
+        let mut problems = Vec::new();
+        let mut reverse: Vec<Vec<NodeId>> = vec![Vec::new(); n];
+        for (from, succs) in self.adjacency.iter().enumerate() {
+            for &to in succs {
+                if to.as_usize() < n {
+                    reverse[to.as_usize()].push(NodeId::new(from));
+                } else {
+                    problems.push(GraphError::DanglingEdge {
+                        from: NodeId::new(from),
+                        to,
+                    });
+                }
+            }
+        }
+        let order = self.topological_sort();
+        if let Err(cycle) = &order {
+            problems.push(cycle.clone());
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            let has_inputs = !reverse[i].is_empty();
+            if node.is_source() && has_inputs {
+                problems.push(GraphError::SourceHasInputs(NodeId::new(i)));
+            } else if !node.is_source() && !has_inputs {
+                problems.push(GraphError::MissingInputs(NodeId::new(i)));
+            }
+        }
+        if let Some(&output) = order.as_ref().ok().and_then(|o| o.last()) {
+            let mut reached = vec![false; n];
+            reached[output.as_usize()] = true;
+            let mut stack = vec![output];
+            while let Some(id) = stack.pop() {
+                for &pred in &reverse[id.as_usize()] {
+                    if !reached[pred.as_usize()] {
+                        reached[pred.as_usize()] = true;
+                        stack.push(pred);
+                    }
+                }
+            }
+            problems.extend(
+                (0..n)
+                    .filter(|&i| !reached[i])
+                    .map(|i| GraphError::Unreachable(NodeId::new(i))),
+            );
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
     /// Builds a CompiledGraph: topo-sorted nodes, one scratch buffer per node, and input indices per node.
     ///
+    /// Scratch buffers hold `frame_count` frames; for varying block sizes compile once with the
+    /// largest (see [`compile_with_capacity`](Self::compile_with_capacity)).
+    ///
     /// # Example
     ///
     /// Build a simple chain (sine → gain), compile it, and process a few blocks:
@@ -190,6 +918,14 @@ impl AudioGraph {
         self.compile_with_meter(frame_count, None)
     }
 
+    /// [`compile`](Self::compile) for graphs run at varying block sizes, named for intent:
+    /// `max_frames` is the largest block the graph will see. Any block up to `max_frames` is
+    /// processed in full; a longer one gets its first `max_frames` samples rendered and the rest
+    /// zero-filled (see [`CompiledGraph::process`]).
+    pub fn compile_with_capacity(&self, max_frames: usize) -> Result<CompiledGraph, GraphError> {
+        self.compile(max_frames)
+    }
+
     /// Like [`compile`](Self::compile), but optionally wires meter taps: after each process call,
     /// the peak level of each specified scratch buffer (by index in topo order) is written to the
     /// shared [`MeterBuffer`]. Use for live level meters in a UI. `tap_indices` must have the same
@@ -201,6 +937,23 @@ impl AudioGraph {
     ) -> Result<CompiledGraph, GraphError> {
         let order = self.topological_sort()?;
         let n = order.len();
+        for &id in &order {
+            if self.in_degree(id) > MAX_INPUTS {
+                return Err(GraphError::TooManyInputs {
+                    node: id,
+                    max: MAX_INPUTS,
+                });
+            }
+            if let GraphNode::Mixer(mixer) = &self.nodes[id.as_usize()] {
+                let expected = self.in_degree(id);
+                if mixer.gains.len() != expected {
+                    return Err(GraphError::MixerArity {
+                        expected,
+                        got: mixer.gains.len(),
+                    });
+                }
+            }
+        }
         if let Some((ref tap_indices, ref buf)) = meter {
             if tap_indices.len() != buf.len() {
                 return Err(GraphError::InvalidMeterTaps);
@@ -211,48 +964,194 @@ impl AudioGraph {
                 }
             }
         }
-        let nodes: Vec<GraphNode> = order
+        let nodes: Box<[GraphNode]> = order
             .iter()
             .map(|&id| self.nodes[id.as_usize()].clone())
             .collect();
-        let scratch_buffers: Vec<AudioBuffer> =
-            (0..n).map(|_| AudioBuffer::new(frame_count)).collect();
-        let input_buf_indices: Vec<Vec<usize>> = (0..n)
+        let input_buf_indices: Box<[Vec<(usize, f32)>]> = (0..n)
             .map(|i| {
                 (0..n)
-                    .filter(|&j| self.adjacency[order[j].as_usize()].contains(&order[i]))
+                    .filter_map(|j| Some((j, self.edge_gain(order[j], order[i])?)))
                     .collect()
             })
             .collect();
+        // Trimmed inputs are scaled into temporaries after the node slots; nodes run one at a
+        // time, so they only need as many as the node with the most trimmed inputs.
+        let temps = input_buf_indices
+            .iter()
+            .map(|inputs| inputs.iter().filter(|&&(_, gain)| gain != 1.0).count())
+            .max()
+            .unwrap_or(0);
+        let scratch = ScratchPool::new(n + temps, frame_count);
         let (tap_indices, meter_buffer) = meter
-            .map(|(taps, buf)| (Some(taps), Some(buf)))
+            .map(|(taps, buf)| (Some(taps.into_boxed_slice()), Some(buf)))
             .unwrap_or((None, None));
         Ok(CompiledGraph {
             nodes,
-            scratch_buffers,
+            scratch,
             input_buf_indices,
             tap_indices,
             meter_buffer,
+            channels: self.channels,
+            stable_ids: order
+                .iter()
+                .map(|&id| self.stable_ids[id.as_usize()])
+                .collect(),
+            solo: None,
         })
     }
 }
 
-/// Immutable execution plan: nodes in topo order, one scratch buffer per node, and per-node input indices.
+/// Fluent construction for the common shapes: linear chains, and chains that several chains
+/// mix into. Each call adds a node fed by the current tail (the last node added), so
+///
+/// ```
+/// use capstan::graph::{GraphBuilder, GraphNode};
+/// use capstan::nodes::{GainProcessor, SineGenerator};
+///
+/// let sine = |hz| GraphBuilder::new().source(GraphNode::Sine(SineGenerator::new(hz, 48_000)));
+/// let compiled = GraphBuilder::new()
+///     .mix(vec![sine(220.0), sine(330.0)])
+///     .then(GraphNode::Gain(GainProcessor::new(0.5)))
+///     .compile(64)
+///     .unwrap();
+/// assert_eq!(compiled.node_count(), 4);
+/// ```
+///
+/// builds the same graph as four `add_node` and three `add_edge` calls. For anything else
+/// (trimmed edges, fan-out, taps off the chain), take the graph with
+/// [`into_graph`](Self::into_graph) and carry on by hand.
+#[derive(Clone, Debug, Default)]
+pub struct GraphBuilder {
+    graph: AudioGraph,
+    tail: Option<NodeId>,
+}
+
+impl GraphBuilder {
+    /// Starts an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `node` unconnected and makes it the tail. Use for the first node of a chain.
+    pub fn source(mut self, node: GraphNode) -> Self {
+        self.tail = Some(self.graph.add_node(node));
+        self
+    }
+
+    /// Adds `node` fed by the tail, and makes it the tail. With no tail yet, adds it unconnected
+    /// (an error at [`compile`](Self::compile) unless it is a source).
+    pub fn then(mut self, node: GraphNode) -> Self {
+        let id = self.graph.add_node(node);
+        if let Some(tail) = self.tail {
+            self.graph.add_edge(tail, id);
+        }
+        self.tail = Some(id);
+        self
+    }
+
+    /// Adds an equal-gain [`Mixer`] fed by this builder's tail (if any) and the tail of each of
+    /// `chains`, and makes it the tail. Each input gets gain `1 / inputs`. The chains' nodes are
+    /// appended in order, after this builder's. Panics if the graph would outgrow [`MAX_NODES`].
+    pub fn mix(mut self, chains: Vec<GraphBuilder>) -> Self {
+        let mut inputs: Vec<NodeId> = self.tail.into_iter().collect();
+        for chain in chains {
+            let ids = match self.graph.merge(chain.graph) {
+                Ok(ids) => ids,
+                Err(err) => panic!("{}", err),
+            };
+            inputs.extend(chain.tail.map(|tail| ids[tail.as_usize()]));
+        }
+        let level = 1.0 / inputs.len().max(1) as f32;
+        let mixer = self
+            .graph
+            .add_node(GraphNode::Mixer(Mixer::new(vec![level; inputs.len()])));
+        for input in inputs {
+            self.graph.add_edge(input, mixer);
+        }
+        self.tail = Some(mixer);
+        self
+    }
+
+    /// The graph built so far, unchecked.
+    pub fn into_graph(self) -> AudioGraph {
+        self.graph
+    }
+
+    /// Validates the graph (see [`AudioGraph::validate`]) and compiles it for `frame_count`.
+    /// Returns the first problem found, e.g. [`GraphError::MissingInputs`] for a chain that
+    /// starts with an effect, or for a [`mix`](Self::mix) with nothing to mix.
+    pub fn compile(self, frame_count: usize) -> Result<CompiledGraph, GraphError> {
+        if let Err(mut problems) = self.graph.validate() {
+            return Err(problems.swap_remove(0));
+        }
+        self.graph.compile(frame_count)
+    }
+}
+
+/// Differences between two graphs, from [`AudioGraph::diff`]. Node ids refer to the new graph
+/// for additions and changes, and to the old graph for removals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    /// Same id in both graphs, different node (type or parameters).
+    pub changed_nodes: Vec<NodeId>,
+    pub added_edges: Vec<(NodeId, NodeId)>,
+    pub removed_edges: Vec<(NodeId, NodeId)>,
+    /// In both graphs, with a different edge gain.
+    pub changed_edges: Vec<(NodeId, NodeId)>,
+    /// The graphs are written for different channel counts (see [`AudioGraph::set_channels`]).
+    pub channels_changed: bool,
+}
+
+impl GraphDiff {
+    /// True when the graphs are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+            && !self.channels_changed
+    }
+
+    /// True when only node parameters changed: same nodes, same edges, same channel count.
+    pub fn is_param_only(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && !self.channels_changed
+    }
+}
+
+/// Immutable execution plan: nodes in topo order, one scratch slice per node (all in one [`ScratchPool`]), and per-node input indices.
 /// Optionally holds meter taps: scratch buffer indices whose peak level is written to [`MeterBuffer`] each callback.
 #[derive(Clone)]
 pub struct CompiledGraph {
-    nodes: Vec<GraphNode>,
-    scratch_buffers: Vec<AudioBuffer>,
-    /// input_buf_indices[i] = buffer indices (0..i) that are inputs to node i.
-    input_buf_indices: Vec<Vec<usize>>,
-    tap_indices: Option<Vec<usize>>,
+    /// Boxed slices here and below keep the struct, and so every Command and Event that can
+    /// carry it, small.
+    nodes: Box<[GraphNode]>,
+    /// Slot i is node i's output; slots from `nodes.len()` on hold trimmed inputs.
+    scratch: ScratchPool,
+    /// input_buf_indices[i] = scratch slots (0..i) that are inputs to node i, with their edge gains.
+    input_buf_indices: Box<[Vec<(usize, f32)>]>,
+    tap_indices: Option<Box<[usize]>>,
     meter_buffer: Option<Arc<MeterBuffer>>,
+    channels: usize,
+    /// stable_ids[i] is the identity of nodes[i].
+    stable_ids: Box<[StableId]>,
+    /// Position of the node whose output replaces the last node's, if one is soloed.
+    solo: Option<usize>,
 }
 
 impl std::fmt::Debug for CompiledGraph {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CompiledGraph")
             .field("node_count", &self.nodes.len())
+            .field("channels", &self.channels)
             .field("tap_indices", &self.tap_indices)
             .finish_non_exhaustive()
     }
@@ -261,6 +1160,7 @@ impl std::fmt::Debug for CompiledGraph {
 impl PartialEq for CompiledGraph {
     fn eq(&self, other: &Self) -> bool {
         self.nodes.len() == other.nodes.len()
+            && self.channels == other.channels
             && self.tap_indices == other.tap_indices
             && match (&self.meter_buffer, &other.meter_buffer) {
                 (None, None) => true,
@@ -271,28 +1171,151 @@ impl PartialEq for CompiledGraph {
 }
 
 impl CompiledGraph {
+    /// Number of nodes in the execution plan.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Longest [`tail_samples`](Processor::tail_samples) of any node: how long to keep rendering
+    /// after the input stops so delay and reverb tails ring out.
+    pub fn tail_samples(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(Processor::tail_samples)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Position in the execution plan of the node with identity `id`, if it is in this graph.
+    pub fn position(&self, id: StableId) -> Option<usize> {
+        self.stable_ids.iter().position(|&s| s == id)
+    }
+
+    /// The node with identity `id`, for adjusting parameters before the graph is swapped in.
+    pub fn node_mut(&mut self, id: StableId) -> Option<&mut GraphNode> {
+        let i = self.position(id)?;
+        Some(&mut self.nodes[i])
+    }
+
+    /// Sets parameter `id` to `value` on every node that has it (see [`GraphNode::set_param`])
+    /// and returns how many did. Doesn't allocate, so the audio thread may call it.
+    pub fn set_param_all(&mut self, id: ParamId, value: f32) -> usize {
+        self.nodes
+            .iter_mut()
+            .map(|node| node.set_param(id, value))
+            .filter(|&set| set)
+            .count()
+    }
+
+    /// Solo the node with identity `id`: [`process`](Self::process) outputs its buffer instead of
+    /// the last node's, so everything downstream of it is bypassed (the whole graph still runs).
+    /// `None` restores normal routing. Returns false, leaving the routing alone, if `id` is not
+    /// in this graph.
+    pub fn set_solo(&mut self, id: Option<StableId>) -> bool {
+        match id {
+            None => self.solo = None,
+            Some(id) => match self.position(id) {
+                Some(i) => self.solo = Some(i),
+                None => return false,
+            },
+        }
+        true
+    }
+
+    /// Identity of the soloed node, if any (see [`set_solo`](Self::set_solo)).
+    pub fn solo(&self) -> Option<StableId> {
+        self.solo.map(|i| self.stable_ids[i])
+    }
+
+    /// Clears every node's signal memory (see [`Processor::reset`]): echoes, filter ringing and
+    /// envelopes stop at once. Parameters are kept. Real-time safe.
+    pub fn reset(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.reset();
+        }
+    }
+
+    /// Every node's current [`params`](GraphNode::params), keyed by identity, in execution order.
+    /// Nodes without parameters are left out. Reflects runtime tweaks, so this is what to persist
+    /// for "save current state"; see [`restore_params`](Self::restore_params).
+    pub fn snapshot_params(&self) -> Vec<(StableId, Vec<(ParamId, f32)>)> {
+        self.stable_ids
+            .iter()
+            .zip(&self.nodes)
+            .map(|(&id, node)| (id, node.params()))
+            .filter(|(_, params)| !params.is_empty())
+            .collect()
+    }
+
+    /// Applies a [`snapshot_params`](Self::snapshot_params) result, typically to a fresh compile
+    /// of the same `AudioGraph`. Nodes that are not in this graph and parameters a node does not
+    /// have are skipped.
+    pub fn restore_params(&mut self, snapshot: &[(StableId, Vec<(ParamId, f32)>)]) {
+        for (id, params) in snapshot {
+            if let Some(node) = self.node_mut(*id) {
+                for &(param, value) in params {
+                    node.set_param(param, value);
+                }
+            }
+        }
+    }
+
+    /// Interleaved channel count the graph was compiled for (see [`AudioGraph::set_channels`]).
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Block size the graph was compiled for (frames per scratch buffer); 0 for an empty graph.
+    pub fn frame_count(&self) -> usize {
+        if self.nodes.is_empty() {
+            0
+        } else {
+            self.scratch.frame_count()
+        }
+    }
+
     /// Runs the graph: each node reads from its input buffers and writes to its scratch; last node's buffer is copied to output.
     /// Only processes `output.len()` frames per call so generator phase and timing stay in sync with the device.
+    /// At most [`frame_count`](Self::frame_count) frames are rendered; any excess in `output` is zero-filled.
     pub fn process(&mut self, output: &mut [f32]) {
         let node_count = self.nodes.len();
         if node_count == 0 {
             return;
         }
-        let out_len = output.len().min(self.scratch_buffers[0].len());
+        let out_len = output.len().min(self.scratch.frame_count());
         if out_len == 0 {
             return;
         }
         for i in 0..node_count {
-            let (head, tail) = self.scratch_buffers.split_at_mut(i);
-            let out_buf = &mut tail[0];
-            let input_slices: Vec<&[f32]> = self.input_buf_indices[i]
+            let frame_count = self.scratch.frame_count();
+            let (head, out_buf, after) = self.scratch.split_at_slot_mut(i);
+            let input = |j: usize| &head[j * frame_count..j * frame_count + out_len];
+            // Temporaries start at slot node_count; `after` starts at slot i + 1.
+            let temp_offset = |k: usize| (node_count - i - 1 + k) * frame_count;
+            let trimmed = self.input_buf_indices[i]
                 .iter()
-                .map(|&j| &head[j].as_slice()[..out_len])
-                .collect();
-            self.nodes[i].process(&input_slices, &mut out_buf.as_mut_slice()[..out_len]);
+                .filter(|&&(_, gain)| gain != 1.0);
+            for (k, &(j, gain)) in trimmed.enumerate() {
+                let start = temp_offset(k);
+                scale_into(input(j), &mut after[start..start + out_len], gain);
+            }
+            let after = &*after;
+            let inputs = &self.input_buf_indices[i];
+            let mut input_slices: [&[f32]; MAX_INPUTS] = [&[]; MAX_INPUTS];
+            let mut k = 0;
+            for (slice, &(j, gain)) in input_slices.iter_mut().zip(inputs) {
+                *slice = if gain == 1.0 {
+                    input(j)
+                } else {
+                    let start = temp_offset(k);
+                    k += 1;
+                    &after[start..start + out_len]
+                };
+            }
+            self.nodes[i].process(&input_slices[..inputs.len()], &mut out_buf[..out_len]);
         }
-        output[..out_len]
-            .copy_from_slice(&self.scratch_buffers[node_count - 1].as_slice()[..out_len]);
+        let out_slot = self.solo.unwrap_or(node_count - 1);
+        output[..out_len].copy_from_slice(&self.scratch.slot(out_slot)[..out_len]);
         if output.len() > out_len {
             output[out_len..].fill(0.0);
         }
@@ -301,9 +1324,8 @@ impl CompiledGraph {
             (&self.tap_indices, &self.meter_buffer)
         {
             for (slot, &scratch_idx) in tap_indices.iter().enumerate() {
-                if scratch_idx < self.scratch_buffers.len() {
-                    let buf = &self.scratch_buffers[scratch_idx];
-                    let slice = &buf.as_slice()[..out_len];
+                if scratch_idx < node_count {
+                    let slice = &self.scratch.slot(scratch_idx)[..out_len];
                     let peak = slice.iter().map(|&s| s.abs()).fold(0.0f32, |a, b| a.max(b));
                     meter_buffer.write_peak(slot, peak);
                 }
@@ -314,8 +1336,464 @@ impl CompiledGraph {
 
 #[cfg(test)]
 mod tests {
-    use super::{AudioGraph, GraphNode, NodeId};
-    use crate::nodes::{GainProcessor, SineGenerator};
+    use super::{
+        AudioGraph, CompiledGraph, GraphBuilder, GraphError, GraphNode, NodeId, MAX_INPUTS,
+        MAX_NODES,
+    };
+    use crate::nodes::{GainProcessor, Mixer, SineGenerator};
+    use crate::processor::Processor;
+
+    #[test]
+    fn test_try_add_node_stops_at_max_nodes() {
+        let mut g = AudioGraph::new();
+        for _ in 0..MAX_NODES {
+            g.try_add_node(GraphNode::Gain(GainProcessor::new(1.0)))
+                .expect("below the cap");
+        }
+        assert_eq!(g.node_count(), MAX_NODES);
+        assert_eq!(
+            g.try_add_node(GraphNode::Gain(GainProcessor::new(1.0))),
+            Err(GraphError::TooManyNodes { max: MAX_NODES })
+        );
+        assert_eq!(g.node_count(), MAX_NODES);
+    }
+
+    #[test]
+    fn test_compile_rejects_more_than_max_inputs() {
+        let fan_in = |inputs: usize| {
+            let mut g = AudioGraph::new();
+            let sources: Vec<NodeId> = (0..inputs)
+                .map(|_| g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000))))
+                .collect();
+            let mix = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0; inputs])));
+            for s in sources {
+                g.add_edge(s, mix);
+            }
+            (g, mix)
+        };
+        let (g, _) = fan_in(MAX_INPUTS);
+        assert!(g.compile(64).is_ok());
+        let (g, mix) = fan_in(MAX_INPUTS + 1);
+        assert_eq!(
+            g.compile(64).unwrap_err(),
+            GraphError::TooManyInputs {
+                node: mix,
+                max: MAX_INPUTS
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_past_max_nodes_is_an_error() {
+        let mut full = AudioGraph::new();
+        for _ in 0..MAX_NODES {
+            full.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        }
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        assert_eq!(
+            g.merge(full),
+            Err(GraphError::TooManyNodes { max: MAX_NODES })
+        );
+        assert_eq!(g.node_count(), 1, "graph unchanged");
+    }
+
+    #[test]
+    fn test_merge_with_other_channel_count_is_an_error() {
+        let mut stereo = AudioGraph::new();
+        stereo.set_channels(2);
+        stereo.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        assert_eq!(
+            g.merge(stereo),
+            Err(GraphError::ChannelMismatch {
+                expected: 1,
+                got: 2
+            })
+        );
+        assert_eq!(g.node_count(), 1, "graph unchanged");
+    }
+
+    #[test]
+    fn test_graph_error_display_messages() {
+        let cases = [
+            (
+                GraphError::Cycle(vec![NodeId::new(1), NodeId::new(2)]),
+                "cycle among nodes [1, 2]",
+            ),
+            (GraphError::InvalidMeterTaps, "invalid meter tap"),
+            (
+                GraphError::DanglingEdge {
+                    from: NodeId::new(0),
+                    to: NodeId::new(9),
+                },
+                "edge 0 -> 9 points to a missing node",
+            ),
+            (
+                GraphError::SourceHasInputs(NodeId::new(3)),
+                "source node 3 has incoming edges",
+            ),
+            (
+                GraphError::MissingInputs(NodeId::new(4)),
+                "node 4 has no inputs",
+            ),
+            (
+                GraphError::Unreachable(NodeId::new(5)),
+                "node 5 does not reach the output",
+            ),
+            (
+                GraphError::MixerArity {
+                    expected: 2,
+                    got: 3,
+                },
+                "mixer has 3 gains but 2 connected inputs",
+            ),
+            (
+                GraphError::TooManyNodes { max: 4096 },
+                "maximum of 4096 nodes",
+            ),
+            (
+                GraphError::TooManyInputs {
+                    node: NodeId::new(2),
+                    max: 64,
+                },
+                "node 2 has more than the maximum of 64 inputs",
+            ),
+            (
+                GraphError::ChannelMismatch {
+                    expected: 1,
+                    got: 2,
+                },
+                "graph is written for 2 channels, expected 1",
+            ),
+        ];
+        for (err, text) in cases {
+            assert!(err.to_string().contains(text), "{:?} -> {}", err, err);
+        }
+
+        // Works with `?` into a boxed error.
+        fn compile_cycle() -> Result<CompiledGraph, Box<dyn std::error::Error>> {
+            let mut g = AudioGraph::new();
+            let a = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+            let b = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+            g.add_edge(a, b);
+            g.add_edge(b, a);
+            Ok(g.compile(64)?)
+        }
+        let err = compile_cycle().unwrap_err();
+        assert!(err.to_string().starts_with("cycle among nodes"));
+    }
+
+    #[test]
+    fn test_stable_id_survives_reordering_recompile() {
+        let mut g = AudioGraph::new();
+        let mixer = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0])));
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        g.add_edge(sine, mixer);
+        let stable = g.stable_id(mixer);
+        assert_ne!(stable, g.stable_id(sine));
+
+        let mut first = g.compile(64).unwrap();
+        assert_eq!(first.position(stable), Some(1));
+        assert!(matches!(first.node_mut(stable), Some(GraphNode::Mixer(_))));
+
+        // A second input makes the mixer wait for one more source: it moves in the plan.
+        let other = g.add_node(GraphNode::Sine(SineGenerator::new(220.0, 48_000)));
+        g.add_edge(other, mixer);
+        g.replace_node(mixer, GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])))
+            .unwrap();
+        assert_eq!(g.stable_id(mixer), stable);
+
+        let mut second = g.compile(64).unwrap();
+        assert_eq!(second.position(stable), Some(2));
+        match second.node_mut(stable) {
+            Some(GraphNode::Mixer(m)) => assert_eq!(m.gains, vec![0.5, 0.5]),
+            other => panic!("expected the mixer, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_replace_node_keeps_edges() {
+        use crate::nodes::{Noise, NoiseKind};
+
+        let mut g = AudioGraph::new();
+        let osc = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+        g.add_edge(osc, gain);
+        let mut before = vec![0.0f32; 64];
+        g.compile(64).unwrap().process(&mut before);
+
+        let old = g
+            .replace_node(osc, GraphNode::Noise(Noise::new(NoiseKind::White)))
+            .unwrap();
+        assert!(matches!(old, GraphNode::Sine(_)));
+        assert_eq!(g.successors(osc), &[gain]);
+        let mut after = vec![0.0f32; 64];
+        g.compile(64).unwrap().process(&mut after);
+        assert_ne!(after, before, "new source feeds the same chain");
+        assert!(after.iter().all(|s| s.abs() <= 0.5));
+
+        // A source can't take the gain's input, nor a 2-input mixer a single input.
+        let sine = GraphNode::Sine(SineGenerator::new(440.0, 48_000));
+        assert_eq!(
+            g.replace_node(gain, sine),
+            Err(GraphError::SourceHasInputs(gain))
+        );
+        let mixer = GraphNode::Mixer(Mixer::new(vec![1.0, 1.0]));
+        assert_eq!(
+            g.replace_node(gain, mixer),
+            Err(GraphError::MixerArity {
+                expected: 1,
+                got: 2
+            })
+        );
+        assert!(matches!(g.nodes[gain.as_usize()], GraphNode::Gain(_)));
+    }
+
+    #[test]
+    fn test_merge_subgraph_and_wire_into_mixer() {
+        let mut main = AudioGraph::new();
+        let other_sine = main.add_node(GraphNode::Sine(SineGenerator::new(220.0, 48_000)));
+        let mixer = main.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        main.add_edge(other_sine, mixer);
+
+        let mut voice = AudioGraph::new();
+        let sine = voice.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = voice.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+        voice.add_edge(sine, gain);
+
+        let ids = main.merge(voice).unwrap();
+        assert_eq!(ids, vec![NodeId::new(2), NodeId::new(3)]);
+        assert_eq!(main.successors(ids[0]), &[ids[1]]);
+        main.add_edge(ids[1], mixer);
+
+        let mut compiled = main.compile(64).expect("merged graph compiles");
+        assert_eq!(compiled.node_count(), 4);
+        let mut out = vec![0.0f32; 64];
+        compiled.process(&mut out);
+        assert!(out.iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_compile_rejects_mixer_gain_count_mismatch() {
+        let mut g = AudioGraph::new();
+        let m = g.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        for _ in 0..3 {
+            let s = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+            g.add_edge(s, m);
+        }
+        assert_eq!(
+            g.compile(64).err(),
+            Some(GraphError::MixerArity {
+                expected: 3,
+                got: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_mixer_try_new_rejects_empty_gains() {
+        assert!(Mixer::try_new(Vec::new()).is_err());
+        assert_eq!(
+            Mixer::try_new(vec![1.0, 0.5]).unwrap().gains,
+            vec![1.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn test_compile_with_capacity_handles_smaller_and_larger_blocks() {
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut compiled = g.compile_with_capacity(1024).unwrap();
+        assert_eq!(compiled.frame_count(), 1024);
+
+        let mut reference = SineGenerator::new(440.0, 48_000);
+        for len in [256, 512] {
+            let mut expected = vec![0.0f32; len];
+            reference.process(&[], &mut expected);
+            let mut out = vec![0.0f32; len];
+            compiled.process(&mut out);
+            assert_eq!(out, expected, "{len}-frame block");
+        }
+
+        let mut expected = vec![0.0f32; 1024];
+        reference.process(&[], &mut expected);
+        let mut out = vec![1.0f32; 2048];
+        compiled.process(&mut out);
+        assert_eq!(&out[..1024], &expected[..]);
+        assert!(
+            out[1024..].iter().all(|&s| s == 0.0),
+            "excess is zero-filled"
+        );
+    }
+
+    fn sine_gain_graph(gain: f32) -> AudioGraph {
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let amp = g.add_node(GraphNode::Gain(GainProcessor::new(gain)));
+        g.add_edge(sine, amp);
+        g
+    }
+
+    #[test]
+    fn test_builder_chain_matches_manual_graph() {
+        let built = GraphBuilder::new()
+            .source(GraphNode::Sine(SineGenerator::new(440.0, 48_000)))
+            .then(GraphNode::Gain(GainProcessor::new(0.5)));
+        assert_eq!(built.clone().into_graph(), sine_gain_graph(0.5));
+        assert_eq!(
+            built.compile(64).unwrap(),
+            sine_gain_graph(0.5).compile(64).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_builder_mix_matches_manual_graph() {
+        let mut manual = AudioGraph::new();
+        let a = manual.add_node(GraphNode::Sine(SineGenerator::new(220.0, 48_000)));
+        let b = manual.add_node(GraphNode::Sine(SineGenerator::new(330.0, 48_000)));
+        let mix = manual.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        let gain = manual.add_node(GraphNode::Gain(GainProcessor::new(0.8)));
+        manual.add_edge(a, mix);
+        manual.add_edge(b, mix);
+        manual.add_edge(mix, gain);
+
+        let sine = |hz| GraphBuilder::new().source(GraphNode::Sine(SineGenerator::new(hz, 48_000)));
+        let built = GraphBuilder::new()
+            .mix(vec![sine(220.0), sine(330.0)])
+            .then(GraphNode::Gain(GainProcessor::new(0.8)));
+        assert_eq!(built.clone().into_graph(), manual);
+        assert_eq!(built.compile(64).unwrap(), manual.compile(64).unwrap());
+
+        // Mixing onto an existing chain counts its tail as the first input.
+        let onto = sine(220.0).mix(vec![sine(330.0)]);
+        assert_eq!(onto.into_graph().predecessors(mix), vec![a, b]);
+    }
+
+    #[test]
+    fn test_builder_reports_invalid_chains() {
+        let gain = || GraphNode::Gain(GainProcessor::new(1.0));
+        let sine = || GraphNode::Sine(SineGenerator::new(440.0, 48_000));
+        assert_eq!(
+            GraphBuilder::new().then(gain()).compile(64).err(),
+            Some(GraphError::MissingInputs(NodeId::new(0)))
+        );
+        assert_eq!(
+            GraphBuilder::new()
+                .source(sine())
+                .then(sine())
+                .compile(64)
+                .err(),
+            Some(GraphError::SourceHasInputs(NodeId::new(1)))
+        );
+        assert_eq!(
+            GraphBuilder::new().mix(Vec::new()).compile(64).err(),
+            Some(GraphError::MissingInputs(NodeId::new(0)))
+        );
+    }
+
+    #[test]
+    fn test_describe_lists_nodes_as_a_tree_and_edges() {
+        let sine = |hz| GraphBuilder::new().source(GraphNode::Sine(SineGenerator::new(hz, 48_000)));
+        let g = GraphBuilder::new()
+            .mix(vec![sine(220.0), sine(330.0)])
+            .then(GraphNode::Gain(GainProcessor::new(0.8)))
+            .into_graph();
+        assert_eq!(
+            g.describe(),
+            "[3] Gain Gain=0.8\n\
+             \x20 [2] Mixer InputGain(0)=0.5 InputGain(1)=0.5\n\
+             \x20   [0] Sine Frequency=220\n\
+             \x20   [1] Sine Frequency=330\n\
+             edges: 0 -> 2, 1 -> 2, 2 -> 3\n"
+        );
+
+        // Fan-out is expanded once; trims and cycles are marked.
+        let mut g = AudioGraph::new();
+        let src = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let a = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        let b = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        g.add_edge(src, a);
+        g.add_edge_with_gain(src, b, 0.5);
+        let text = g.describe();
+        assert!(text.contains("[0] Sine Frequency=440\n"), "{text}");
+        assert!(text.contains("[0] Sine (above) (edge gain 0.5)"), "{text}");
+        g.add_edge(b, a);
+        g.add_edge(a, b);
+        assert!(g.describe().contains("(cycle)"));
+        assert_eq!(AudioGraph::new().describe(), "(empty graph)\n");
+    }
+
+    #[test]
+    fn test_diff_identical_graphs_is_empty() {
+        let diff = sine_gain_graph(0.5).diff(&sine_gain_graph(0.5));
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_changed_gain() {
+        let diff = sine_gain_graph(0.5).diff(&sine_gain_graph(0.8));
+        assert_eq!(diff.changed_nodes, vec![NodeId::new(1)]);
+        assert!(diff.is_param_only());
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_changed_channel_count() {
+        let old = sine_gain_graph(0.5);
+        let mut new = old.clone();
+        new.set_channels(2);
+        let diff = old.diff(&new);
+        assert!(diff.channels_changed);
+        assert!(!diff.is_empty());
+        assert!(!diff.is_param_only());
+    }
+
+    #[test]
+    fn test_diff_reports_added_node_and_edge() {
+        let old = sine_gain_graph(0.5);
+        let mut new = old.clone();
+        let out = new.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        new.add_edge(NodeId::new(1), out);
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_nodes, vec![out]);
+        assert_eq!(diff.added_edges, vec![(NodeId::new(1), out)]);
+        assert!(diff.removed_nodes.is_empty() && diff.removed_edges.is_empty());
+        assert!(diff.changed_nodes.is_empty());
+
+        let back = new.diff(&old);
+        assert_eq!(back.removed_nodes, vec![out]);
+        assert_eq!(back.removed_edges, vec![(NodeId::new(1), out)]);
+    }
+
+    #[test]
+    fn test_default_equals_new() {
+        assert_eq!(AudioGraph::default(), AudioGraph::new());
+    }
+
+    #[test]
+    fn test_cloned_graph_compiles_equivalently() {
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+        g.add_edge(sine, gain);
+        let mut branch = g.clone();
+        assert_eq!(branch, g);
+
+        let mut a = g.compile(64).unwrap();
+        let mut b = branch.compile(64).unwrap();
+        assert_eq!(a, b);
+        let mut out_a = vec![0.0f32; 64];
+        let mut out_b = vec![0.0f32; 64];
+        a.process(&mut out_a);
+        b.process(&mut out_b);
+        assert_eq!(out_a, out_b);
+
+        branch.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(branch.node_count(), 3);
+    }
 
     #[test]
     fn test_node_id_roundtrip() {
@@ -375,6 +1853,294 @@ mod tests {
         assert!(g.topological_sort().is_err());
     }
 
+    #[test]
+    fn test_predecessors_and_in_degree_of_mixer() {
+        let mut g = AudioGraph::new();
+        let a = g.add_node(GraphNode::Sine(SineGenerator::new(220.0, 48_000)));
+        let b = g.add_node(GraphNode::Sine(SineGenerator::new(330.0, 48_000)));
+        let mixer = g.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        g.add_edge(a, mixer);
+        g.add_edge(b, mixer);
+        assert_eq!(g.predecessors(mixer), vec![a, b]);
+        assert_eq!(g.in_degree(mixer), 2);
+        assert!(g.predecessors(a).is_empty());
+        assert_eq!(g.in_degree(a), 0);
+    }
+
+    /// A node type the library knows nothing about: adds a constant offset to its input.
+    #[derive(Clone)]
+    struct Offset(f32);
+
+    impl Processor for Offset {
+        fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+            for (i, sample) in output.iter_mut().enumerate() {
+                *sample = inputs.first().map_or(0.0, |inp| inp[i]) + self.0;
+            }
+        }
+    }
+
+    #[test]
+    fn test_dynamic_node_processes_in_graph() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let o = g.add_node(GraphNode::Dynamic(Box::new(Offset(0.25))));
+        g.add_edge(s, o);
+        assert_eq!(g.validate(), Ok(()));
+
+        let mut reference = SineGenerator::new(440.0, 48_000);
+        let mut expected = vec![0.0f32; 64];
+        reference.process(&[], &mut expected);
+        let mut compiled = g.compile(64).unwrap();
+        let mut out = vec![0.0f32; 64];
+        compiled.process(&mut out);
+        for (got, want) in out.iter().zip(&expected) {
+            assert!((got - (want + 0.25)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_source_takes_no_inputs() {
+        #[derive(Clone)]
+        struct Dc(f32);
+        impl Processor for Dc {
+            fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+                output.fill(self.0);
+            }
+            fn num_inputs(&self) -> usize {
+                0
+            }
+        }
+
+        let dc = || GraphNode::Dynamic(Box::new(Dc(0.25)));
+        assert!(dc().is_source());
+        assert_eq!(GraphNode::Dynamic(Box::new(Offset(0.25))).num_inputs(), 1);
+
+        let mut g = AudioGraph::new();
+        let osc = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.5)));
+        g.add_edge(osc, gain);
+        assert!(matches!(
+            g.replace_node(gain, dc()),
+            Err(GraphError::SourceHasInputs(id)) if id == gain
+        ));
+        g.replace_node(osc, dc()).unwrap();
+        assert_eq!(g.validate(), Ok(()));
+        let mut out = vec![0.0f32; 64];
+        g.compile(64).unwrap().process(&mut out);
+        assert!(out.iter().all(|&s| (s - 0.125).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_distinct_zero_sized_dynamic_nodes_differ() {
+        #[derive(Clone)]
+        struct Invert;
+        impl Processor for Invert {
+            fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+                for (i, sample) in output.iter_mut().enumerate() {
+                    *sample = -inputs.first().map_or(0.0, |inp| inp[i]);
+                }
+            }
+        }
+        #[derive(Clone)]
+        struct Mute;
+        impl Processor for Mute {
+            fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+                output.fill(0.0);
+            }
+        }
+
+        let graph = |node: GraphNode| {
+            let mut g = AudioGraph::new();
+            let s = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+            let d = g.add_node(node);
+            g.add_edge(s, d);
+            g
+        };
+        let inverted = graph(GraphNode::Dynamic(Box::new(Invert)));
+        let muted = graph(GraphNode::Dynamic(Box::new(Mute)));
+        assert_ne!(inverted, muted);
+        assert_eq!(inverted.diff(&muted).changed_nodes, vec![NodeId::new(1)]);
+    }
+
+    #[test]
+    fn test_duplicate_edge_is_ignored() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let m = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0])));
+        g.add_edge(s, m);
+        g.add_edge(s, m);
+        assert_eq!(g.successors(s), &[m]);
+        assert_eq!(g.in_degree(m), 1);
+        assert_eq!(g.topological_sort().unwrap(), vec![s, m]);
+
+        // One logical connection: the mixer sees the sine once, at unity gain.
+        let mut reference = SineGenerator::new(440.0, 48_000);
+        let mut expected = vec![0.0f32; 64];
+        reference.process(&[], &mut expected);
+        let mut compiled = g.compile(64).unwrap();
+        let mut out = vec![0.0f32; 64];
+        compiled.process(&mut out);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_topological_sort_breaks_ties_by_node_id() {
+        // Diamond 0 -> {1, 2} -> 3, with 0's edges added high id first. A FIFO queue would
+        // yield [0, 2, 1, 3]; the NodeId tie-break must give [0, 1, 2, 3] every time.
+        let mut g = AudioGraph::new();
+        let ids: Vec<NodeId> = (0..4)
+            .map(|_| g.add_node(GraphNode::Gain(GainProcessor::new(1.0))))
+            .collect();
+        g.add_edge(ids[0], ids[2]);
+        g.add_edge(ids[0], ids[1]);
+        g.add_edge(ids[2], ids[3]);
+        g.add_edge(ids[1], ids[3]);
+        for _ in 0..3 {
+            assert_eq!(g.topological_sort().unwrap(), ids);
+        }
+    }
+
+    fn sine() -> GraphNode {
+        GraphNode::Sine(SineGenerator::new(440.0, 48_000))
+    }
+
+    fn gain() -> GraphNode {
+        GraphNode::Gain(GainProcessor::new(1.0))
+    }
+
+    #[test]
+    fn test_validate_accepts_simple_chain() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(sine());
+        let m = g.add_node(gain());
+        g.add_edge(s, m);
+        assert_eq!(g.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_dangling_edge() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(sine());
+        g.add_edge(s, NodeId::new(7));
+        assert_eq!(
+            g.validate(),
+            Err(vec![GraphError::DanglingEdge {
+                from: s,
+                to: NodeId::new(7)
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_cycle() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(sine());
+        let a = g.add_node(gain());
+        let b = g.add_node(gain());
+        g.add_edge(s, a);
+        g.add_edge(a, b);
+        g.add_edge(b, a);
+        assert_eq!(g.validate(), Err(vec![GraphError::Cycle(vec![a, b])]));
+    }
+
+    #[test]
+    fn test_validate_reports_fed_source() {
+        let mut g = AudioGraph::new();
+        let a = g.add_node(sine());
+        let b = g.add_node(sine());
+        g.add_edge(a, b);
+        assert_eq!(g.validate(), Err(vec![GraphError::SourceHasInputs(b)]));
+    }
+
+    #[test]
+    fn test_node_role_classification() {
+        use crate::nodes::Interleave;
+        assert!(sine().is_source());
+        assert_eq!(sine().num_inputs(), 0);
+        let gain = GraphNode::Gain(GainProcessor::new(1.0));
+        assert!(!gain.is_source());
+        assert_eq!(gain.num_inputs(), 1);
+        assert!(!gain.is_sink());
+
+        let mixer = GraphNode::Mixer(Mixer::new(vec![0.5, 0.5, 1.0]));
+        assert_eq!(mixer.num_inputs(), 3);
+        assert!(!mixer.is_source() && !mixer.is_sink());
+        assert!(!GraphNode::Mixer(Mixer::new(Vec::new())).is_source());
+        assert_eq!(GraphNode::Interleave(Interleave).num_inputs(), 2);
+
+        let record = GraphNode::Record(crate::nodes::RecordNode::new(std::sync::Arc::new(
+            crate::record::RecordBuffer::with_capacity(16),
+        )));
+        assert!(record.is_sink() && !record.is_source());
+    }
+
+    #[test]
+    fn test_validate_reports_mixer_without_inputs() {
+        let mut g = AudioGraph::new();
+        let m = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0])));
+        assert_eq!(g.validate(), Err(vec![GraphError::MissingInputs(m)]));
+    }
+
+    #[test]
+    fn test_validate_reports_nodes_not_reaching_output() {
+        let mut g = AudioGraph::new();
+        let s1 = g.add_node(sine());
+        let g1 = g.add_node(gain());
+        let s2 = g.add_node(sine());
+        let g2 = g.add_node(gain());
+        g.add_edge(s1, g1);
+        g.add_edge(s2, g2);
+        // g2 is last in topological order, so it is the output; s1 -> g1 is orphaned.
+        assert_eq!(
+            g.validate(),
+            Err(vec![
+                GraphError::Unreachable(s1),
+                GraphError::Unreachable(g1)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems_at_once() {
+        let mut g = AudioGraph::new();
+        let s = g.add_node(sine());
+        let fed = g.add_node(sine());
+        let lonely = g.add_node(gain());
+        let out = g.add_node(gain());
+        g.add_edge(s, fed);
+        g.add_edge(fed, out);
+        g.add_edge(out, NodeId::new(9));
+        let problems = g.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            vec![
+                GraphError::DanglingEdge {
+                    from: out,
+                    to: NodeId::new(9)
+                },
+                GraphError::SourceHasInputs(fed),
+                GraphError::MissingInputs(lonely),
+                GraphError::Unreachable(lonely),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cycle_error_names_cycle_nodes_only() {
+        let mut g = AudioGraph::new();
+        let free = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let a = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        let b = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        let c = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        g.add_edge(a, b);
+        g.add_edge(b, c);
+        g.add_edge(c, a);
+        let err = g.topological_sort().unwrap_err();
+        assert_eq!(err, GraphError::Cycle(vec![a, b, c]));
+        assert!(!matches!(&err, GraphError::Cycle(nodes) if nodes.contains(&free)));
+        assert_eq!(err.to_string(), "cycle among nodes [1, 2, 3]");
+    }
+
     #[test]
     fn test_compiled_graph_process() {
         let mut g = AudioGraph::new();
@@ -410,6 +2176,304 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scratch_pool_matches_per_node_buffers() {
+        // Sine -> gain and a second sine mixed in, with a tap; rendered through the pool and
+        // through the previous one-AudioBuffer-per-node layout, from identical starting state.
+        let mut g = AudioGraph::new();
+        let s0 = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let s1 = g.add_node(GraphNode::Sine(SineGenerator::new(660.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.7)));
+        let mix = g.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        g.add_edge(s0, gain);
+        g.add_edge(gain, mix);
+        g.add_edge(s1, mix);
+        let mut compiled = g.compile(64).unwrap();
+
+        let backing = compiled.scratch.as_slice();
+        assert_eq!(
+            backing.len(),
+            4 * 64,
+            "one allocation holds every node's scratch"
+        );
+        let range = backing.as_ptr_range();
+        for i in 0..4 {
+            let slot = compiled.scratch.slot(i);
+            assert_eq!(slot.len(), 64);
+            assert_eq!(slot.as_ptr(), backing[i * 64..].as_ptr());
+            assert!(range.contains(&slot.as_ptr()));
+        }
+
+        let mut nodes = compiled.nodes.clone();
+        let mut buffers: Vec<crate::audio_buffer::AudioBuffer> = (0..4)
+            .map(|_| crate::audio_buffer::AudioBuffer::new(64))
+            .collect();
+        for block in [64, 40, 64] {
+            let mut pooled = vec![0.0f32; block];
+            compiled.process(&mut pooled);
+            for (i, node) in nodes.iter_mut().enumerate() {
+                let (head, tail) = buffers.split_at_mut(i);
+                let inputs: Vec<&[f32]> = compiled.input_buf_indices[i]
+                    .iter()
+                    .map(|&(j, _)| &head[j].as_slice()[..block])
+                    .collect();
+                node.process(&inputs, &mut tail[0].as_mut_slice()[..block]);
+            }
+            assert_eq!(pooled[..], buffers[3].as_slice()[..block]);
+        }
+    }
+
+    #[test]
+    fn test_edge_gain_trims_input() {
+        let build = |trim: Option<f32>| {
+            let mut g = AudioGraph::new();
+            let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+            let gain = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+            match trim {
+                Some(t) => g.add_edge_with_gain(sine, gain, t),
+                None => g.add_edge(sine, gain),
+            }
+            g
+        };
+        let render = |g: &AudioGraph| {
+            let mut out = vec![0.0f32; 64];
+            g.compile(64).unwrap().process(&mut out);
+            out
+        };
+        let (plain, trimmed) = (build(None), build(Some(0.5)));
+        assert_eq!(trimmed.edge_gain(NodeId::new(0), NodeId::new(1)), Some(0.5));
+        let expected: Vec<f32> = render(&plain).iter().map(|s| s * 0.5).collect();
+        assert_eq!(render(&trimmed), expected);
+        assert_eq!(
+            plain.diff(&trimmed).changed_edges,
+            [(NodeId::new(0), NodeId::new(1))]
+        );
+        assert!(plain.diff(&trimmed).is_param_only());
+
+        // Two trimmed inputs into one mixer each get their own temporary.
+        let mut g = AudioGraph::new();
+        let a = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let b = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mix = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0, 1.0])));
+        g.add_edge_with_gain(a, mix, 0.25);
+        g.add_edge_with_gain(b, mix, 0.5);
+        let sine_only = render(&plain);
+        let mixed = render(&g);
+        for (m, s) in mixed.iter().zip(&sine_only) {
+            assert!((m - 0.75 * s).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_tail_samples_covers_feedback_delay() {
+        use crate::nodes::Echo;
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut echo = Echo::new(500.0, 48_000);
+        echo.set_delay_ms(250.0);
+        echo.feedback = 0.5;
+        let echo = g.add_node(GraphNode::Echo(echo));
+        g.add_edge(sine, echo);
+        let compiled = g.compile(64).unwrap();
+        let delay = 12_000;
+        assert!(compiled.tail_samples() >= delay);
+        // 0.5^10 < -60 dB: ten repeats after the first.
+        assert_eq!(compiled.tail_samples(), delay * 11);
+
+        let mut dry = AudioGraph::new();
+        dry.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        assert_eq!(dry.compile(64).unwrap().tail_samples(), 0);
+    }
+
+    #[test]
+    fn test_snapshot_params_reflects_runtime_tweaks() {
+        use super::ParamId;
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        g.add_edge(sine, gain);
+        let (sine, gain) = (g.stable_id(sine), g.stable_id(gain));
+
+        let mut running = g.compile(64).unwrap();
+        match running.node_mut(gain) {
+            Some(GraphNode::Gain(g)) => g.gain = 0.25,
+            other => panic!("expected the gain, got {:?}", other),
+        }
+        let snapshot = running.snapshot_params();
+        assert_eq!(
+            snapshot,
+            vec![
+                (sine, vec![(ParamId::Frequency, 440.0)]),
+                (gain, vec![(ParamId::Gain, 0.25)]),
+            ]
+        );
+
+        // A fresh compile starts from the original values until the snapshot is restored.
+        let mut fresh = g.compile(64).unwrap();
+        assert_eq!(fresh.snapshot_params()[1].1, vec![(ParamId::Gain, 1.0)]);
+        fresh.restore_params(&snapshot);
+        assert_eq!(fresh.snapshot_params(), snapshot);
+        let (mut a, mut b) = (vec![0.0f32; 64], vec![0.0f32; 64]);
+        running.process(&mut a);
+        fresh.process(&mut b);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_solo_outputs_node_and_bypasses_downstream() {
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let other = g.add_node(GraphNode::Sine(SineGenerator::new(660.0, 48_000)));
+        let mixer = g.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 0.5])));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(0.1)));
+        g.add_edge(sine, mixer);
+        g.add_edge(other, mixer);
+        g.add_edge(mixer, gain);
+        let sine = g.stable_id(sine);
+        let mut compiled = g.compile(64).unwrap();
+        let mut reference = SineGenerator::new(440.0, 48_000);
+
+        assert!(compiled.set_solo(Some(sine)));
+        assert_eq!(compiled.solo(), Some(sine));
+        let mut out = vec![0.0f32; 64];
+        let mut want = vec![0.0f32; 64];
+        compiled.process(&mut out);
+        reference.process(&[], &mut want);
+        assert_eq!(out, want, "soloed sine skips the mixer and the 0.1 gain");
+
+        assert!(compiled.set_solo(None));
+        compiled.process(&mut out);
+        reference.process(&[], &mut want);
+        assert!(out.iter().zip(&want).any(|(o, w)| (o - w).abs() > 1e-3));
+        assert!(out.iter().all(|s| s.abs() <= 0.1 + 1e-6));
+
+        // An id from another graph is refused and leaves routing alone.
+        let mut small = AudioGraph::new();
+        small.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut small = small.compile(64).unwrap();
+        assert!(!small.set_solo(Some(g.stable_id(gain))));
+        assert_eq!(small.solo(), None);
+    }
+
+    #[test]
+    fn test_set_param_rejects_params_the_node_lacks() {
+        use super::ParamId;
+        let mut mixer = GraphNode::Mixer(Mixer::new(vec![1.0, 1.0]));
+        assert!(mixer.set_param(ParamId::InputGain(1), 0.5));
+        assert!(!mixer.set_param(ParamId::InputGain(2), 0.5));
+        assert!(!mixer.set_param(ParamId::Frequency, 220.0));
+        assert_eq!(
+            mixer.params(),
+            vec![(ParamId::InputGain(0), 1.0), (ParamId::InputGain(1), 0.5)]
+        );
+    }
+
+    /// One of every built-in node type, set up so each one does something audible.
+    fn one_of_each_node() -> Vec<GraphNode> {
+        use crate::input_buffer::InputSampleBuffer;
+        use crate::nodes::{
+            AutoGain, AutoPanner, Balance, BiquadFilter, Chorus, DeInterleave, DelayLine, Echo,
+            FilePlayer, InputNode, Interleave, Limiter, MeterTap, Metronome, MonoSum, Noise,
+            NoiseKind, Overdrive, PingPongDelay, QuadratureOsc, RecordNode, SampleHold, ScopeTap,
+            Slew, Tremolo,
+        };
+        use crate::record::RecordBuffer;
+        use crate::ring_buffer::ScopeRing;
+        use std::sync::atomic::AtomicU32;
+        use std::sync::Arc;
+
+        let sr = 48_000;
+        let mut delay = DelayLine::new(10.0, sr);
+        delay.set_delay_ms(0.5);
+        let mut echo = Echo::new(10.0, sr);
+        echo.set_delay_ms(0.5);
+        let mut ping_pong = PingPongDelay::new(10.0, sr);
+        ping_pong.set_time_ms(0.5);
+        #[allow(unused_mut)]
+        let mut nodes = vec![
+            GraphNode::Sine(SineGenerator::new(440.0, sr)),
+            GraphNode::Gain(GainProcessor::new(0.5)),
+            GraphNode::Mixer(Mixer::new(vec![0.5, 0.25])),
+            GraphNode::Input(InputNode::new(Arc::new(InputSampleBuffer::new(64)))),
+            GraphNode::Delay(delay),
+            GraphNode::Echo(echo),
+            GraphNode::Tremolo(Tremolo::new(5.0, sr)),
+            GraphNode::Overdrive(Overdrive::new(3.0)),
+            GraphNode::Biquad(BiquadFilter::lowpass(sr, 1000.0, 0.7)),
+            GraphNode::Record(RecordNode::new(Arc::new(RecordBuffer::new()))),
+            GraphNode::Metronome(Metronome::new(120.0, sr)),
+            GraphNode::Noise(Noise::with_seed(NoiseKind::Pink, 7)),
+            GraphNode::PingPong(ping_pong),
+            GraphNode::Chorus(Chorus::new(sr)),
+            GraphNode::SampleHold(SampleHold::new(1000.0, sr)),
+            GraphNode::Slew(Slew::new(100.0, 100.0, sr)),
+            GraphNode::Meter(MeterTap::new(
+                Arc::new(AtomicU32::new(0)),
+                Arc::new(AtomicU32::new(0)),
+            )),
+            GraphNode::Scope(ScopeTap::new(Arc::new(ScopeRing::new(256)))),
+            GraphNode::Balance(Balance::new(0.25, 0.75)),
+            GraphNode::AutoGain(AutoGain::new(0.1, sr)),
+            GraphNode::Limiter(Limiter::new(0.2, 50.0, sr)),
+            GraphNode::Quadrature(QuadratureOsc::new(440.0, sr)),
+            GraphNode::FilePlayer(FilePlayer::new(Arc::new(vec![0.5, -0.5, 0.25]), 1)),
+            GraphNode::AutoPan(AutoPanner::new(2.0, sr)),
+            GraphNode::MonoSum(MonoSum::new()),
+            GraphNode::DeInterleave(DeInterleave::new(1)),
+            GraphNode::Interleave(Interleave::new()),
+        ];
+        #[cfg(feature = "convolution")]
+        nodes.push(GraphNode::Convolve(crate::convolver::Convolver::new(
+            &[0.5, 0.25, 0.125],
+            64,
+        )));
+        nodes
+    }
+
+    #[test]
+    fn test_every_node_overwrites_output_from_inputs_only() {
+        let signal: Vec<f32> = (0..64).map(|i| (i as f32 * 0.3).sin() * 0.5).collect();
+        for node in one_of_each_node() {
+            let inputs = vec![&signal[..]; node.num_inputs()];
+            // Same node state, different stale output: the result must not change.
+            let run = |inputs: &[&[f32]], stale: f32| {
+                let mut node = node.clone();
+                let mut out = vec![stale; 64];
+                node.process(inputs, &mut out);
+                out
+            };
+            let out = run(&inputs, 0.0);
+            assert_eq!(out, run(&inputs, 123.0), "{node:?} read its output buffer");
+            assert!(out.iter().all(|s| s.is_finite()), "{node:?}");
+            if node.is_source() {
+                assert_eq!(run(&[&signal], 0.0), out, "{node:?}: sources ignore inputs");
+            } else {
+                assert!(out.iter().any(|&s| s != 0.0), "{node:?} ignored its input");
+                assert!(
+                    run(&[], 123.0).iter().all(|&s| s == 0.0),
+                    "{node:?}: no input is silence"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reset_clears_node_memory() {
+        let signal: Vec<f32> = (0..64).map(|i| (i as f32 * 0.3).sin() * 0.5).collect();
+        let silence = vec![0.0f32; 64];
+        for node in one_of_each_node().into_iter().filter(|n| !n.is_source()) {
+            let mut used = node.clone();
+            let mut out = vec![0.0f32; 64];
+            used.process(&vec![&signal[..]; node.num_inputs()], &mut out);
+            used.reset();
+            used.process(&vec![&silence[..]; node.num_inputs()], &mut out);
+            assert!(
+                out.iter().all(|&s| s == 0.0),
+                "{node:?} kept signal after reset"
+            );
+        }
+    }
+
     #[test]
     fn test_compiled_graph_with_input() {
         use crate::input_buffer::{InputSampleBuffer, SampleSource};
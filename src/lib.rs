@@ -10,9 +10,15 @@
 //! a duplex setup (input + output, same device and config) and small buffers; CPAL supports
 //! that via separate input/output streams or platform-specific duplex where available.
 
+pub mod analysis;
 pub mod audio_buffer;
+pub mod automation;
 pub mod command;
+#[cfg(feature = "convolution")]
+pub mod convolver;
+pub mod denormal;
 pub mod device;
+pub mod dither;
 pub mod engine;
 pub mod event;
 pub mod file_feeder;
@@ -20,20 +26,32 @@ pub mod graph;
 pub mod input_buffer;
 pub mod meter;
 pub mod nodes;
+pub mod notes;
+#[cfg(feature = "serde")]
+pub mod patch;
+pub mod presets;
 pub mod processor;
 pub mod record;
+pub mod render;
 pub mod ring_buffer;
+pub mod rng;
 
 /// Re-export for advanced use (custom streams, device enumeration). Most apps should use [`run_audio`].
 pub use cpal;
 
+use std::time::{Duration, Instant};
+
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{BufferSize, SampleFormat, StreamConfig, SupportedBufferSize};
+use cpal::{
+    BufferSize, SampleFormat, StreamConfig, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange,
+};
 
 use crate::command::CommandReceiver;
-use crate::engine::Engine;
+use crate::engine::{Engine, SampleCounter, StopSignal};
 use crate::event::EventSender;
 use crate::input_buffer::InputSampleBuffer;
+use crate::rng::SmallRng;
 
 /// Errors from [`run_audio`]: device discovery, stream config, or stream build/play failure.
 ///
@@ -44,12 +62,16 @@ pub enum RunAudioError {
     NoOutputDevice,
     /// Could not get the default stream config for the output device.
     NoOutputConfig(cpal::DefaultStreamConfigError),
-    /// The device does not support F32 output; only F32 is supported.
+    /// The device has no F32, I16 or U16 output config (the default's format is given).
     UnsupportedSampleFormat(cpal::SampleFormat),
     /// Failed to build the output stream (e.g. config not supported).
     BuildOutputStream(cpal::BuildStreamError),
     /// Failed to start the output stream (e.g. device not available).
     PlayStream(cpal::PlayStreamError),
+    /// Failed to pause a running stream.
+    PauseStream(cpal::PauseStreamError),
+    /// [`AudioOptions::out_channels`] names a channel (0-based) the device does not have.
+    OutputChannelOutOfRange { channel: usize, channels: u16 },
 }
 
 impl std::fmt::Display for RunAudioError {
@@ -60,7 +82,7 @@ impl std::fmt::Display for RunAudioError {
             RunAudioError::UnsupportedSampleFormat(fmt) => {
                 write!(
                     f,
-                    "run_audio only supports F32 output; device has {:?}",
+                    "run_audio only supports F32, I16 and U16 output; device has {:?}",
                     fmt
                 )
             }
@@ -68,6 +90,13 @@ impl std::fmt::Display for RunAudioError {
                 write!(f, "failed to build output stream: {}", e)
             }
             RunAudioError::PlayStream(e) => write!(f, "failed to start output stream: {}", e),
+            RunAudioError::PauseStream(e) => write!(f, "failed to pause stream: {}", e),
+            RunAudioError::OutputChannelOutOfRange { channel, channels } => write!(
+                f,
+                "output channel {} not available: device has {} channels",
+                channel + 1,
+                channels
+            ),
         }
     }
 }
@@ -78,11 +107,23 @@ impl std::error::Error for RunAudioError {
             RunAudioError::NoOutputConfig(e) => Some(e),
             RunAudioError::BuildOutputStream(e) => Some(e),
             RunAudioError::PlayStream(e) => Some(e),
+            RunAudioError::PauseStream(e) => Some(e),
             _ => None,
         }
     }
 }
 
+/// How often the output callback sends `Event::CpuLoad`.
+const CPU_LOAD_REPORTS_PER_SEC: u32 = 4;
+/// One-pole smoothing factor applied to each callback's load.
+const CPU_LOAD_SMOOTHING: f32 = 0.1;
+
+/// How often a blocking [`run_audio`] checks whether the engine has finished a `Command::Stop`.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Smallest mono scratch allocated for the output callback, in frames.
+const MIN_MONO_SCRATCH_FRAMES: usize = 4096;
+
 /// Preferred buffer size in frames for low-latency (≈2.7 ms at 48 kHz). Host may use a larger minimum.
 const LOW_LATENCY_BUFFER_FRAMES: u32 = 128;
 
@@ -106,17 +147,96 @@ pub fn interleave_mono_to_stereo(mono: &[f32], data: &mut [f32], channels: u16)
     }
 }
 
+/// Like [`interleave_mono_to_stereo`], but writes the mono signal only to the device channels
+/// (0-based) listed in `map` and zeroes the others; indices at or past `channels` are skipped.
+/// An empty map means every channel. If `data` is shorter than `mono.len() * channels`, it is
+/// filled with silence rather than left holding stale samples.
+pub fn place_mono_on_channels(mono: &[f32], data: &mut [f32], channels: u16, map: &[usize]) {
+    let ch = channels as usize;
+    if data.len() < mono.len() * ch {
+        data.fill(0.0);
+        return;
+    }
+    if map.is_empty() {
+        interleave_mono_to_stereo(mono, data, channels);
+        return;
+    }
+    for (frame, &s) in data.chunks_exact_mut(ch).zip(mono) {
+        frame.fill(0.0);
+        for &c in map {
+            if let Some(out) = frame.get_mut(c) {
+                *out = s;
+            }
+        }
+    }
+}
+
 /// Returns the sample rate (Hz) that [`run_audio`] will use for the default output device,
 /// or `None` if no device/config is available. Use this when starting file feeders or
 /// building graphs so playback matches the actual output rate.
 pub fn default_output_sample_rate() -> Option<u32> {
     let host = cpal::default_host();
     let device = host.default_output_device()?;
-    let supported = device.default_output_config().ok()?;
+    let supported = output_config(&device).ok()?;
     let config = stream_config_with_low_latency(&supported);
     Some(config.sample_rate)
 }
 
+/// Sample rate [`find_f32_config`] aims for when a device offers several.
+const PREFERRED_SAMPLE_RATE: u32 = 48_000;
+
+/// Finds an F32 output config on `device`, for devices whose default config uses another sample
+/// format. Picks the supported rate closest to 48 kHz; `None` if the device has no F32 output.
+pub fn find_f32_config(device: &cpal::Device) -> Option<SupportedStreamConfig> {
+    closest_config(device.supported_output_configs().ok()?, SampleFormat::F32)
+}
+
+/// The selection behind [`find_f32_config`], over any list of supported ranges and any format.
+fn closest_config(
+    ranges: impl IntoIterator<Item = SupportedStreamConfigRange>,
+    format: SampleFormat,
+) -> Option<SupportedStreamConfig> {
+    ranges
+        .into_iter()
+        .filter(|range| range.sample_format() == format)
+        .filter_map(|range| {
+            let rate = PREFERRED_SAMPLE_RATE
+                .max(range.min_sample_rate())
+                .min(range.max_sample_rate());
+            range.try_with_sample_rate(rate)
+        })
+        .min_by_key(|config| config.sample_rate().abs_diff(PREFERRED_SAMPLE_RATE))
+}
+
+/// Output sample formats [`run_audio`] can play, in order of preference. Integer formats are
+/// converted from the engine's f32 with TPDF dither (see [`dither`]).
+const OUTPUT_FORMATS: [SampleFormat; 3] = [SampleFormat::F32, SampleFormat::I16, SampleFormat::U16];
+
+/// The device's default output config if its format is playable, otherwise the best config it
+/// supports in the first of [`OUTPUT_FORMATS`] it offers.
+fn output_config(device: &cpal::Device) -> Result<SupportedStreamConfig, RunAudioError> {
+    let default = device
+        .default_output_config()
+        .map_err(RunAudioError::NoOutputConfig)?;
+    if OUTPUT_FORMATS.contains(&default.sample_format()) {
+        return Ok(default);
+    }
+    let ranges: Vec<_> = device
+        .supported_output_configs()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    preferred_config(ranges).ok_or(RunAudioError::UnsupportedSampleFormat(
+        default.sample_format(),
+    ))
+}
+
+/// The closest-to-48-kHz config in the first of [`OUTPUT_FORMATS`] that `ranges` offers.
+fn preferred_config(ranges: Vec<SupportedStreamConfigRange>) -> Option<SupportedStreamConfig> {
+    OUTPUT_FORMATS
+        .iter()
+        .find_map(|&format| closest_config(ranges.iter().cloned(), format))
+}
+
 /// Builds a `StreamConfig` from the device default and sets a low-latency buffer size when the
 /// device reports a range. Uses `LOW_LATENCY_BUFFER_FRAMES` if it lies within the supported
 /// range, otherwise the device minimum. If the device reports `Unknown`, requests the fixed
@@ -137,7 +257,8 @@ pub fn stream_config_with_low_latency(supported: &cpal::SupportedStreamConfig) -
 }
 
 /// Runs the audio engine with the default output device (and optionally the default input device).
-/// Blocks until `shutdown` receives a message, then returns `Ok(())`. All CPAL setup and the
+/// Blocks until `shutdown` receives a message (or is dropped), or until a `Command::Stop` has
+/// faded out, then returns `Ok(())`. All CPAL setup and the
 /// audio callback are handled inside the crate; the control thread only needs to pass the
 /// command/event channels and a shutdown receiver.
 ///
@@ -146,7 +267,7 @@ pub fn stream_config_with_low_latency(supported: &cpal::SupportedStreamConfig) -
 /// Returns an error if:
 /// - No default output device is available
 /// - The default output config cannot be retrieved
-/// - The device does not support F32 output (only F32 is supported)
+/// - The device supports none of F32, I16 or U16 output
 /// - The output stream fails to build or start
 ///
 /// The caller should handle [`RunAudioError`] and decide whether to try another device, show a
@@ -190,85 +311,823 @@ pub fn run_audio(
     shutdown: std::sync::mpsc::Receiver<()>,
     input_buffer: Option<std::sync::Arc<InputSampleBuffer>>,
 ) -> Result<(), RunAudioError> {
+    run_audio_with_options(
+        cmd_rx,
+        evt_tx,
+        shutdown,
+        input_buffer,
+        &AudioOptions::default(),
+    )
+}
+
+/// The control [`StreamHandle`] needs over an open stream. Implemented for `cpal::Stream`;
+/// tests stand in a fake so the handle's lifetime can be checked without an audio device.
+trait StreamControl {
+    fn play_stream(&self) -> Result<(), cpal::PlayStreamError>;
+    fn pause_stream(&self) -> Result<(), cpal::PauseStreamError>;
+}
+
+impl StreamControl for cpal::Stream {
+    fn play_stream(&self) -> Result<(), cpal::PlayStreamError> {
+        self.play()
+    }
+
+    fn pause_stream(&self) -> Result<(), cpal::PauseStreamError> {
+        self.pause()
+    }
+}
+
+/// Running audio streams started by [`start_audio`]. Audio plays for as long as the handle is
+/// alive; dropping it (or calling [`stop`](Self::stop)) stops the streams. Keep it on the
+/// thread that created it: on some platforms the underlying `cpal::Stream` is not `Send`.
+pub struct StreamHandle {
+    output: Box<dyn StreamControl>,
+    input: Option<Box<dyn StreamControl>>,
+    samples: SampleCounter,
+    stopped: StopSignal,
+}
+
+impl StreamHandle {
+    /// Pauses the output (and input, if open). The engine keeps its state.
+    pub fn pause(&self) -> Result<(), RunAudioError> {
+        if let Some(ref input) = self.input {
+            input.pause_stream().map_err(RunAudioError::PauseStream)?;
+        }
+        self.output
+            .pause_stream()
+            .map_err(RunAudioError::PauseStream)
+    }
+
+    /// Resumes streams paused with [`pause`](Self::pause).
+    pub fn play(&self) -> Result<(), RunAudioError> {
+        if let Some(ref input) = self.input {
+            input.play_stream().map_err(RunAudioError::PlayStream)?;
+        }
+        self.output.play_stream().map_err(RunAudioError::PlayStream)
+    }
+
+    /// Stops and closes the streams. Same as dropping the handle.
+    pub fn stop(self) {}
+
+    /// Lock-free count of samples the engine has rendered (see [`Engine::sample_counter`]).
+    pub fn sample_counter(&self) -> SampleCounter {
+        self.samples.clone()
+    }
+
+    /// True once the engine has finished a `Command::Stop`; the streams then only play
+    /// silence, so drop the handle.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.is_set()
+    }
+}
+
+/// Engine settings for [`start_audio_with_options`] / [`run_audio_with_options`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioOptions {
+    /// Start in safe mode: master gain capped at -12 dBFS with the limiter forced on (see
+    /// [`Engine::set_safe_mode`]). For experimenting on headphones.
+    pub safe_mode: bool,
+    /// Device channels (0-based) to play on; the rest are silent. Empty (the default) plays on
+    /// every channel. E.g. `vec![2, 3]` for monitors on outputs 3-4 of an interface.
+    pub out_channels: Vec<usize>,
+    /// Crossfade graph swaps over this many milliseconds instead of switching instantly (see
+    /// [`Engine::set_swap_crossfade_ms`]). 0 (the default) is instant.
+    pub swap_crossfade_ms: f32,
+    /// Run every callback in the CPU's flush-to-zero mode (see
+    /// [`Engine::set_flush_denormals`]).
+    pub flush_denormals: bool,
+    /// Largest block, in frames, the engine renders per call; bigger device blocks are rendered
+    /// in pieces of this size. The scratch for it is allocated once when the stream starts. 0
+    /// (the default) uses the stream's fixed buffer size, but at least 4096 frames.
+    pub max_block: usize,
+}
+
+/// [`run_audio`] with explicit [`AudioOptions`].
+pub fn run_audio_with_options(
+    cmd_rx: CommandReceiver,
+    evt_tx: EventSender,
+    shutdown: std::sync::mpsc::Receiver<()>,
+    input_buffer: Option<std::sync::Arc<InputSampleBuffer>>,
+    options: &AudioOptions,
+) -> Result<(), RunAudioError> {
+    let stream = start_audio_with_options(cmd_rx, evt_tx, input_buffer, options)?;
+    while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+        shutdown.recv_timeout(STOP_POLL_INTERVAL)
+    {
+        if stream.is_stopped() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Non-blocking [`run_audio`]: opens the default output (and, if `input_buffer` is `Some`, the
+/// default input), starts playback and returns a [`StreamHandle`]. The caller decides how long
+/// audio runs by keeping the handle; errors are the same as for `run_audio`.
+pub fn start_audio(
+    cmd_rx: CommandReceiver,
+    evt_tx: EventSender,
+    input_buffer: Option<std::sync::Arc<InputSampleBuffer>>,
+) -> Result<StreamHandle, RunAudioError> {
+    start_audio_with_options(cmd_rx, evt_tx, input_buffer, &AudioOptions::default())
+}
+
+/// [`start_audio`] with explicit [`AudioOptions`].
+///
+/// Whatever the device's channel count, the engine renders one mono signal and copies it to
+/// every output channel (or those in `out_channels`). Graphs must therefore be mono: one built
+/// with `AudioGraph::set_channels(2)` is refused with `Event::ChannelMismatch` and plays silence.
+pub fn start_audio_with_options(
+    cmd_rx: CommandReceiver,
+    evt_tx: EventSender,
+    input_buffer: Option<std::sync::Arc<InputSampleBuffer>>,
+    options: &AudioOptions,
+) -> Result<StreamHandle, RunAudioError> {
     let host = cpal::default_host();
     let device = host
         .default_output_device()
         .ok_or(RunAudioError::NoOutputDevice)?;
-    let supported_config = device
-        .default_output_config()
-        .map_err(RunAudioError::NoOutputConfig)?;
-    let sample_format = supported_config.sample_format();
-    if sample_format != SampleFormat::F32 {
-        return Err(RunAudioError::UnsupportedSampleFormat(sample_format));
-    }
+    let supported_config = output_config(&device)?;
     let config = stream_config_with_low_latency(&supported_config);
     let sample_rate = config.sample_rate;
     let _ = evt_tx.try_send(crate::event::Event::StreamStarted(sample_rate));
 
-    let mut engine = Engine::new(sample_rate, 440.0, 0.5);
+    let mut engine = Engine::new(sample_rate, 440.0, 1.0);
+    // Mono render, spread over the device channels by `place_mono_on_channels`.
+    engine.set_channels(1);
+    engine.set_safe_mode(options.safe_mode);
+    engine.set_swap_crossfade_ms(options.swap_crossfade_ms);
+    engine.set_flush_denormals(options.flush_denormals);
+    let samples = engine.sample_counter();
+    let stopped = engine.stop_signal();
     let channels = config.channels;
-    let mut mono_buf = vec![0.0f32; 4096];
-
-    if let Some(ref buf) = input_buffer {
-        if let Some(input_device) = host.default_input_device() {
-            if let Ok(supported_input) = input_device.default_input_config() {
-                if supported_input.sample_format() == SampleFormat::F32 {
-                    let input_config = stream_config_with_low_latency(&supported_input);
-                    let in_ch = input_config.channels;
-                    let buf_clone = std::sync::Arc::clone(buf);
-                    let err_fn =
-                        move |err: cpal::StreamError| eprintln!("input stream error: {}", err);
-                    if let Ok(input_stream) = input_device.build_input_stream(
-                        &input_config,
-                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                            buf_clone.write_block(data, in_ch);
-                        },
-                        err_fn,
-                        None,
-                    ) {
-                        let _ = input_stream.play();
-                        let _input_stream = input_stream;
-                        let err_fn_out =
-                            move |err: cpal::StreamError| eprintln!("output stream error: {}", err);
-                        let out_stream = device
-                            .build_output_stream(
-                                &config,
-                                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                                    let frames = data.len() / channels as usize;
-                                    let mono = mono_buf[..frames].as_mut();
-                                    engine.process_audio(&cmd_rx, &evt_tx, mono);
-                                    interleave_mono_to_stereo(mono, data, channels);
-                                },
-                                err_fn_out,
-                                None,
-                            )
-                            .map_err(RunAudioError::BuildOutputStream)?;
-                        out_stream.play().map_err(RunAudioError::PlayStream)?;
-                        let _ = shutdown.recv();
-                        return Ok(());
-                    }
-                    // Input stream build failed; fall through to output-only
-                }
-            }
-        }
+    if let Some(&channel) = options
+        .out_channels
+        .iter()
+        .find(|&&c| c >= channels as usize)
+    {
+        return Err(RunAudioError::OutputChannelOutOfRange { channel, channels });
     }
+    let format = supported_config.sample_format();
+    let mono_frames = mono_scratch_frames(&config, options.max_block);
+    let mut callback = OutputCallback {
+        engine,
+        cmd_rx,
+        evt_tx,
+        mono_buf: vec![0.0; mono_frames],
+        // Only integer devices render through it.
+        float_buf: if format == SampleFormat::F32 {
+            Vec::new()
+        } else {
+            vec![0.0; mono_frames * channels.max(1) as usize]
+        },
+        rng: SmallRng::default(),
+        channels,
+        out_map: options.out_channels.clone(),
+        cpu_meter: CpuLoadMeter::new(sample_rate),
+    };
+
+    // Input is best effort: if it can't be opened, playback continues output-only.
+    let input = input_buffer
+        .and_then(|buf| open_default_input(&host, buf))
+        .map(|stream| Box::new(stream) as Box<dyn StreamControl>);
 
     let err_fn = move |err: cpal::StreamError| eprintln!("output stream error: {}", err);
-    let stream = device
-        .build_output_stream(
+    let output = match format {
+        SampleFormat::I16 => device.build_output_stream(
+            &config,
+            move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                callback.render_dithered(data, dither::to_i16)
+            },
+            err_fn,
+            None,
+        ),
+        SampleFormat::U16 => device.build_output_stream(
+            &config,
+            move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
+                callback.render_dithered(data, dither::to_u16)
+            },
+            err_fn,
+            None,
+        ),
+        _ => device.build_output_stream(
             &config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let frames = data.len() / channels as usize;
-                let mono = mono_buf[..frames].as_mut();
-                engine.process_audio(&cmd_rx, &evt_tx, mono);
-                interleave_mono_to_stereo(mono, data, channels);
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| callback.render(data),
+            err_fn,
+            None,
+        ),
+    }
+    .map_err(RunAudioError::BuildOutputStream)?;
+    output.play().map_err(RunAudioError::PlayStream)?;
+    Ok(StreamHandle {
+        output: Box::new(output),
+        input,
+        samples,
+        stopped,
+    })
+}
+
+/// Fraction of a block's real-time budget spent rendering it: `elapsed / budget`, where the
+/// budget is how long the block lasts when played (`frames / sample_rate`).
+fn load_fraction(elapsed: Duration, budget: Duration) -> f32 {
+    if budget.is_zero() {
+        return 0.0;
+    }
+    elapsed.as_secs_f32() / budget.as_secs_f32()
+}
+
+/// Smooths per-callback load and decides when to report it (`CPU_LOAD_REPORTS_PER_SEC`).
+/// Lives in the output callback; no allocation.
+struct CpuLoadMeter {
+    sample_rate: u32,
+    smoothed: f32,
+    frames_since_report: usize,
+}
+
+impl CpuLoadMeter {
+    fn new(sample_rate: u32) -> Self {
+        CpuLoadMeter {
+            sample_rate,
+            smoothed: 0.0,
+            frames_since_report: 0,
+        }
+    }
+
+    /// Records one callback of `frames` that took `elapsed` to render. Returns the smoothed
+    /// load when a report is due.
+    fn update(&mut self, elapsed: Duration, frames: usize) -> Option<f32> {
+        let budget = Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64);
+        let load = load_fraction(elapsed, budget);
+        self.smoothed += CPU_LOAD_SMOOTHING * (load - self.smoothed);
+        self.frames_since_report += frames;
+        let report_frames = (self.sample_rate / CPU_LOAD_REPORTS_PER_SEC) as usize;
+        if self.frames_since_report >= report_frames {
+            self.frames_since_report = 0;
+            Some(self.smoothed)
+        } else {
+            None
+        }
+    }
+}
+
+/// What the output callback owns: the engine and its channels, the scratch it renders into, and
+/// the CPU load meter. Nothing here allocates once the stream is running.
+struct OutputCallback {
+    engine: Engine,
+    cmd_rx: CommandReceiver,
+    evt_tx: EventSender,
+    mono_buf: Vec<f32>,
+    /// Interleaved f32 block for integer devices, converted after rendering; empty for F32.
+    float_buf: Vec<f32>,
+    /// Dither noise for integer devices.
+    rng: SmallRng,
+    channels: u16,
+    out_map: Vec<usize>,
+    cpu_meter: CpuLoadMeter,
+}
+
+impl OutputCallback {
+    /// Renders an F32 device block (see [`render_interleaved`]) and sends `Event::CpuLoad` when
+    /// a report is due.
+    fn render(&mut self, data: &mut [f32]) {
+        let started = Instant::now();
+        render_interleaved(
+            &mut self.engine,
+            &self.cmd_rx,
+            &self.evt_tx,
+            &mut self.mono_buf,
+            data,
+            self.channels,
+            &self.out_map,
+        );
+        let frames = data.len() / self.channels.max(1) as usize;
+        if let Some(load) = self.cpu_meter.update(started.elapsed(), frames) {
+            let _ = self.evt_tx.try_send(crate::event::Event::CpuLoad(load));
+        }
+    }
+
+    /// Renders an integer device block: each `float_buf`-sized chunk is rendered as f32, then
+    /// quantized with TPDF dither by `convert` (e.g. [`dither::to_i16`]).
+    fn render_dithered<T>(&mut self, data: &mut [T], convert: fn(f32, &mut SmallRng) -> T) {
+        let mut float_buf = std::mem::take(&mut self.float_buf);
+        for out in data.chunks_mut(float_buf.len().max(1)) {
+            let rendered = &mut float_buf[..out.len()];
+            self.render(rendered);
+            for (sample, &s) in out.iter_mut().zip(rendered.iter()) {
+                *sample = convert(s, &mut self.rng);
+            }
+        }
+        self.float_buf = float_buf;
+    }
+}
+
+/// Frames of mono scratch for the output callback: `max_block` if set (see
+/// [`AudioOptions::max_block`]), otherwise the requested fixed buffer size, but at least
+/// `MIN_MONO_SCRATCH_FRAMES` since hosts may still hand over larger blocks.
+fn mono_scratch_frames(config: &StreamConfig, max_block: usize) -> usize {
+    if max_block > 0 {
+        return max_block;
+    }
+    match config.buffer_size {
+        BufferSize::Fixed(frames) => (frames as usize).max(MIN_MONO_SCRATCH_FRAMES),
+        BufferSize::Default => MIN_MONO_SCRATCH_FRAMES,
+    }
+}
+
+/// Body of the output callback: renders `data` (interleaved, `channels` wide, mono placed on
+/// `out_map` as in [`place_mono_on_channels`]) through the engine, one `mono_buf`-sized chunk at
+/// a time, so a device block of any size is filled completely without allocating. A panic
+/// inside the engine must not unwind into cpal: it is caught, the block is silenced, the graph
+/// is disabled until the next `SwapGraph` ([`Engine::disable_graph`]) and
+/// `Event::ProcessorPanicked` is sent once.
+fn render_interleaved(
+    engine: &mut Engine,
+    cmd_rx: &CommandReceiver,
+    evt_tx: &EventSender,
+    mono_buf: &mut [f32],
+    data: &mut [f32],
+    channels: u16,
+    out_map: &[usize],
+) {
+    let ch = (channels as usize).max(1);
+    let chunk_frames = mono_buf.len().max(1);
+    let rendered = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        for out in data.chunks_mut(chunk_frames * ch) {
+            let mono = &mut mono_buf[..out.len() / ch];
+            engine.process_audio(cmd_rx, evt_tx, mono);
+            place_mono_on_channels(mono, out, channels, out_map);
+        }
+    }));
+    if rendered.is_err() {
+        data.fill(0.0);
+        if engine.disable_graph() {
+            let _ = evt_tx.try_send(crate::event::Event::ProcessorPanicked);
+        }
+    }
+}
+
+/// Opens the default input device (F32 only) feeding `buffer`, and starts it.
+fn open_default_input(
+    host: &cpal::Host,
+    buffer: std::sync::Arc<InputSampleBuffer>,
+) -> Option<cpal::Stream> {
+    let input_device = host.default_input_device()?;
+    let supported_input = input_device.default_input_config().ok()?;
+    if supported_input.sample_format() != SampleFormat::F32 {
+        return None;
+    }
+    let input_config = stream_config_with_low_latency(&supported_input);
+    let in_ch = input_config.channels;
+    let err_fn = move |err: cpal::StreamError| eprintln!("input stream error: {}", err);
+    let stream = input_device
+        .build_input_stream(
+            &input_config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                buffer.write_block(data, in_ch);
             },
             err_fn,
             None,
         )
-        .map_err(RunAudioError::BuildOutputStream)?;
+        .ok()?;
+    let _ = stream.play();
+    Some(stream)
+}
 
-    stream.play().map_err(RunAudioError::PlayStream)?;
-    let _ = shutdown.recv();
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{
+        closest_config, load_fraction, mono_scratch_frames, place_mono_on_channels,
+        preferred_config, render_interleaved, start_audio, CpuLoadMeter, OutputCallback,
+        StreamControl, StreamHandle, MIN_MONO_SCRATCH_FRAMES,
+    };
+    use crate::command::{command_channel, Command};
+    use crate::engine::Engine;
+    use crate::event::event_channel;
+    use cpal::{
+        BufferSize, SampleFormat, StreamConfig, SupportedBufferSize, SupportedStreamConfigRange,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_closest_f32_config_prefers_rate_nearest_48k() {
+        let range = |min, max, format| {
+            SupportedStreamConfigRange::new(2, min, max, SupportedBufferSize::Unknown, format)
+        };
+        let pick = |ranges: Vec<SupportedStreamConfigRange>| {
+            closest_config(ranges, SampleFormat::F32).map(|c| (c.sample_rate(), c.sample_format()))
+        };
+        // The I16 config hits 48k exactly but is skipped; 44.1k beats 88.2k.
+        let configs = vec![
+            range(48_000, 48_000, SampleFormat::I16),
+            range(88_200, 96_000, SampleFormat::F32),
+            range(8_000, 44_100, SampleFormat::F32),
+        ];
+        assert_eq!(pick(configs), Some((44_100, SampleFormat::F32)));
+        // A range that spans 48k is used at 48k.
+        let configs = vec![
+            range(44_100, 44_100, SampleFormat::F32),
+            range(22_050, 192_000, SampleFormat::F32),
+        ];
+        assert_eq!(pick(configs), Some((48_000, SampleFormat::F32)));
+        assert_eq!(pick(vec![range(8_000, 192_000, SampleFormat::I32)]), None);
+        assert_eq!(pick(Vec::new()), None);
+    }
+
+    #[test]
+    fn test_preferred_config_falls_back_to_integer_formats() {
+        let range = |format| {
+            SupportedStreamConfigRange::new(2, 48_000, 48_000, SupportedBufferSize::Unknown, format)
+        };
+        let pick = |formats: &[SampleFormat]| {
+            preferred_config(formats.iter().map(|&f| range(f)).collect()).map(|c| c.sample_format())
+        };
+        assert_eq!(
+            pick(&[SampleFormat::U16, SampleFormat::I16, SampleFormat::F32]),
+            Some(SampleFormat::F32)
+        );
+        assert_eq!(
+            pick(&[SampleFormat::U16, SampleFormat::I16]),
+            Some(SampleFormat::I16)
+        );
+        assert_eq!(pick(&[SampleFormat::U16]), Some(SampleFormat::U16));
+        assert_eq!(pick(&[SampleFormat::I32]), None);
+    }
+
+    #[test]
+    fn test_integer_output_is_dithered_from_the_engine_render() {
+        use crate::dither;
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::input_buffer::FilePlaybackBuffer;
+        use crate::nodes::InputNode;
+        use crate::rng::SmallRng;
+        use std::sync::Arc;
+
+        // A DC level a third of the way between two 16-bit steps.
+        let level = 1000.33 / 32767.0;
+        let callback = || {
+            let (_cmd_tx, cmd_rx) = command_channel(8);
+            let (evt_tx, _evt_rx) = event_channel(64);
+            let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![level; 1 << 16])));
+            let mut g = AudioGraph::new();
+            g.add_node(GraphNode::Input(InputNode::new(source)));
+            let mut engine = Engine::new(48_000, 440.0, 1.0);
+            engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+            // Get past the soft start.
+            engine.tick(&mut [0.0f32; 1024]);
+            OutputCallback {
+                engine,
+                cmd_rx,
+                evt_tx,
+                mono_buf: vec![0.0; 512],
+                float_buf: vec![0.0; 1024],
+                rng: SmallRng::default(),
+                channels: 2,
+                out_map: Vec::new(),
+                cpu_meter: CpuLoadMeter::new(48_000),
+            }
+        };
+
+        // Larger than the float scratch, so it is rendered in chunks.
+        let mut data = vec![0i16; 16_384];
+        callback().render_dithered(&mut data, dither::to_i16);
+        assert!(
+            data.iter().all(|&s| (999..=1002).contains(&s)),
+            "within 1 LSB"
+        );
+        assert!(
+            data.contains(&1000) && data.contains(&1001),
+            "dithered, not rounded"
+        );
+        let mean = data.iter().map(|&s| s as f64).sum::<f64>() / data.len() as f64;
+        assert!((mean - 1000.33).abs() < 0.05, "mean {mean} keeps the level");
+
+        let mut data = vec![0u16; 16_384];
+        callback().render_dithered(&mut data, dither::to_u16);
+        let mean = data.iter().map(|&s| s as f64).sum::<f64>() / data.len() as f64;
+        assert!((mean - (32768.0 + 1000.33)).abs() < 0.05, "mean {mean}");
+    }
+
+    #[test]
+    fn test_place_mono_on_channels_uses_mapped_channels_only() {
+        let mono = [0.5, -0.25, 1.0];
+        let mut data = [9.0f32; 12];
+        place_mono_on_channels(&mono, &mut data, 4, &[2, 3]);
+        assert_eq!(
+            data,
+            [0.0, 0.0, 0.5, 0.5, 0.0, 0.0, -0.25, -0.25, 0.0, 0.0, 1.0, 1.0]
+        );
+
+        // Empty map: every channel, as before.
+        place_mono_on_channels(&mono, &mut data, 4, &[]);
+        assert!(data
+            .chunks(4)
+            .zip(mono)
+            .all(|(f, s)| f.iter().all(|&x| x == s)));
+
+        // Too short for three 4-channel frames: silence, not stale samples.
+        let mut short = [9.0f32; 8];
+        place_mono_on_channels(&mono, &mut short, 4, &[2, 3]);
+        assert_eq!(short, [0.0; 8]);
+    }
+
+    #[test]
+    fn test_load_fraction() {
+        let budget = Duration::from_millis(10);
+        assert_eq!(load_fraction(Duration::from_millis(5), budget), 0.5);
+        assert_eq!(load_fraction(Duration::from_millis(20), budget), 2.0);
+        assert_eq!(load_fraction(Duration::from_millis(1), Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn test_cpu_load_meter_smooths_and_reports_periodically() {
+        // 480 frames at 48 kHz = 10 ms budget; a report is due every 12_000 frames (25 calls).
+        let mut meter = CpuLoadMeter::new(48_000);
+        let mut reports = Vec::new();
+        for _ in 0..100 {
+            if let Some(load) = meter.update(Duration::from_millis(5), 480) {
+                reports.push(load);
+            }
+        }
+        assert_eq!(reports.len(), 4);
+        assert!(
+            reports.windows(2).all(|w| w[1] >= w[0]),
+            "rises toward the load"
+        );
+        assert!(
+            (reports[3] - 0.5).abs() < 0.01,
+            "settles at 0.5, got {}",
+            reports[3]
+        );
+    }
+
+    #[test]
+    fn test_render_interleaved_survives_node_panic() {
+        use crate::event::Event;
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::processor::Processor;
+
+        #[derive(Clone)]
+        struct Explodes;
+        impl Processor for Explodes {
+            fn process(&mut self, _inputs: &[&[f32]], _output: &mut [f32]) {
+                panic!("node bug");
+            }
+            fn num_inputs(&self) -> usize {
+                0
+            }
+        }
+
+        let (_cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Dynamic(Box::new(Explodes)));
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(64).unwrap()), &evt_tx);
+
+        let mut mono_buf = vec![0.0f32; 64];
+        let mut data = vec![1.0f32; 128];
+        render_interleaved(
+            &mut engine,
+            &cmd_rx,
+            &evt_tx,
+            &mut mono_buf,
+            &mut data,
+            2,
+            &[],
+        );
+        assert!(data.iter().all(|&s| s == 0.0), "panicked block is silent");
+        assert_eq!(evt_rx.try_recv(), Some(Event::ProcessorPanicked));
+
+        // The broken graph is not run again: later callbacks are silent and quiet.
+        for _ in 0..3 {
+            data.fill(1.0);
+            render_interleaved(
+                &mut engine,
+                &cmd_rx,
+                &evt_tx,
+                &mut mono_buf,
+                &mut data,
+                2,
+                &[],
+            );
+            assert!(data.iter().all(|&s| s == 0.0));
+        }
+        assert_eq!(evt_rx.try_recv(), None);
+    }
+
+    #[test]
+    fn test_render_interleaved_fills_blocks_larger_than_scratch() {
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (_cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _evt_rx) = event_channel(8);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut reference = g.compile(512).unwrap();
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(512).unwrap()), &evt_tx);
+
+        // Get past the soft start so the output is the plain sine.
+        let mut warm = vec![0.0f32; 512];
+        engine.process_audio(&cmd_rx, &evt_tx, &mut warm);
+        reference.process(&mut warm);
+
+        let mut expected = vec![0.0f32; 8192];
+        for chunk in expected.chunks_mut(512) {
+            reference.process(chunk);
+        }
+        let mut mono_buf = vec![0.0f32; 4096];
+        let mut data = vec![f32::NAN; 8192 * 2];
+        render_interleaved(
+            &mut engine,
+            &cmd_rx,
+            &evt_tx,
+            &mut mono_buf,
+            &mut data,
+            2,
+            &[],
+        );
+        let left: Vec<f32> = data.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = data.iter().skip(1).step_by(2).copied().collect();
+        assert_eq!(left, expected, "whole block rendered, phase-continuous");
+        assert_eq!(right, expected);
+        assert!(left[4096..].iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_mono_scratch_frames_honours_max_block() {
+        let config = |buffer_size| StreamConfig {
+            channels: 2,
+            sample_rate: 48_000,
+            buffer_size,
+        };
+        let fixed = config(BufferSize::Fixed(128));
+        assert_eq!(mono_scratch_frames(&fixed, 0), MIN_MONO_SCRATCH_FRAMES);
+        assert_eq!(
+            mono_scratch_frames(&config(BufferSize::Fixed(8192)), 0),
+            8192
+        );
+        assert_eq!(
+            mono_scratch_frames(&config(BufferSize::Default), 0),
+            MIN_MONO_SCRATCH_FRAMES
+        );
+        assert_eq!(mono_scratch_frames(&fixed, 16_384), 16_384);
+        assert_eq!(mono_scratch_frames(&fixed, 256), 256);
+    }
+
+    #[test]
+    fn test_render_interleaved_spreads_mono_and_refuses_stereo_graphs() {
+        use crate::event::Event;
+        use crate::graph::{AudioGraph, GraphNode};
+        use crate::nodes::SineGenerator;
+
+        let (_cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, evt_rx) = event_channel(8);
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut engine = Engine::new(48_000, 440.0, 1.0);
+        engine.apply_command(Command::SwapGraph(g.compile(64).unwrap()), &evt_tx);
+        let mut mono_buf = vec![0.0f32; 64];
+        let mut data = vec![0.0f32; 128];
+        for _ in 0..10 {
+            render_interleaved(
+                &mut engine,
+                &cmd_rx,
+                &evt_tx,
+                &mut mono_buf,
+                &mut data,
+                2,
+                &[],
+            );
+        }
+        assert!(data.iter().any(|&s| s != 0.0));
+        assert!(data.chunks(2).all(|f| f[0] == f[1]), "same mono on L and R");
+
+        // A stereo graph is not spread over a stereo device: it is muted and reported.
+        g.set_channels(2);
+        engine.apply_command(Command::SwapGraph(g.compile(64).unwrap()), &evt_tx);
+        render_interleaved(
+            &mut engine,
+            &cmd_rx,
+            &evt_tx,
+            &mut mono_buf,
+            &mut data,
+            2,
+            &[],
+        );
+        assert!(data.iter().all(|&s| s == 0.0));
+        let events: Vec<Event> = std::iter::from_fn(|| evt_rx.try_recv()).collect();
+        assert!(
+            events.contains(&Event::ChannelMismatch {
+                expected: 1,
+                got: 2
+            }),
+            "{:?}",
+            events
+        );
+    }
+
+    /// Stand-in for a device stream: renders `engine` on its own thread, as a device callback
+    /// would, until dropped. `pause` and `play` gate the rendering.
+    struct FakeStream {
+        playing: Arc<AtomicBool>,
+        alive: Arc<AtomicBool>,
+        thread: Option<std::thread::JoinHandle<()>>,
+    }
+
+    impl FakeStream {
+        fn start(mut engine: Engine) -> Self {
+            let playing = Arc::new(AtomicBool::new(true));
+            let alive = Arc::new(AtomicBool::new(true));
+            let thread = {
+                let (playing, alive) = (Arc::clone(&playing), Arc::clone(&alive));
+                std::thread::spawn(move || {
+                    let mut buf = vec![0.0f32; 64];
+                    while alive.load(Ordering::Acquire) {
+                        if playing.load(Ordering::Acquire) {
+                            engine.tick(&mut buf);
+                        }
+                        std::thread::sleep(Duration::from_millis(1));
+                    }
+                })
+            };
+            FakeStream {
+                playing,
+                alive,
+                thread: Some(thread),
+            }
+        }
+    }
+
+    impl StreamControl for FakeStream {
+        fn play_stream(&self) -> Result<(), cpal::PlayStreamError> {
+            self.playing.store(true, Ordering::Release);
+            Ok(())
+        }
+
+        fn pause_stream(&self) -> Result<(), cpal::PauseStreamError> {
+            self.playing.store(false, Ordering::Release);
+            Ok(())
+        }
+    }
+
+    impl Drop for FakeStream {
+        fn drop(&mut self) {
+            self.alive.store(false, Ordering::Release);
+            if let Some(thread) = self.thread.take() {
+                thread.join().unwrap();
+            }
+        }
+    }
+
+    fn wait_for(what: &str, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting for {what}");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_dropping_stream_handle_stops_audio() {
+        let engine = Engine::new(48_000, 440.0, 1.0);
+        let samples = engine.sample_counter();
+        let handle = StreamHandle {
+            samples: engine.sample_counter(),
+            stopped: engine.stop_signal(),
+            output: Box::new(FakeStream::start(engine)),
+            input: None,
+        };
+        wait_for("the stream to render", || samples.get() > 0);
+        handle.pause().unwrap();
+        handle.play().unwrap();
+        let resumed = samples.get();
+        wait_for("the stream to resume", || samples.get() > resumed);
+        assert!(!handle.is_stopped());
+
+        handle.stop();
+        // The stream (and its render thread) is gone, so the count no longer moves.
+        let after = samples.get();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(samples.get(), after);
+    }
+
+    #[test]
+    #[ignore = "needs an audio output device"]
+    fn test_dropping_device_stream_handle_stops_audio() {
+        let (_cmd_tx, cmd_rx) = command_channel(8);
+        let (evt_tx, _evt_rx) = event_channel(8);
+        let handle = start_audio(cmd_rx, evt_tx, None).expect("output device");
+        let samples = handle.sample_counter();
+        wait_for("the device to pull audio", || samples.get() > 0);
+        handle.pause().unwrap();
+        handle.play().unwrap();
+        handle.stop();
+        let after = samples.get();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(samples.get(), after);
+    }
 }
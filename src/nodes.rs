@@ -1,20 +1,75 @@
 //! Audio nodes: sources (e.g. SineGenerator, InputNode) and processors (e.g. GainProcessor).
 
+use crate::graph::MAX_INPUTS;
 use crate::input_buffer::SampleSource;
 use crate::processor::Processor;
 use crate::record::RecordBuffer;
+use crate::ring_buffer::ScopeRing;
+use crate::rng::SmallRng;
 use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
+/// Magnitude below which recursive state is snapped to zero. Well above the f32 denormal range so
+/// decaying feedback never reaches it (denormal arithmetic can be 10–100x slower on some CPUs).
+const DENORMAL_THRESHOLD: f32 = 1e-15;
+
+/// Flushes tiny values to exactly 0.0. Apply to recursive state (filter memory, feedback) each sample.
+#[inline]
+pub(crate) fn flush_denormals(x: f32) -> f32 {
+    if x.abs() < DENORMAL_THRESHOLD {
+        0.0
+    } else {
+        x
+    }
+}
+
+/// How [`SineGenerator`] evaluates the sine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SineApprox {
+    /// `f32::sin`.
+    #[default]
+    Exact,
+    /// Odd 7th-order polynomial after folding the phase into a quarter period: max error about
+    /// 2e-4, several times cheaper. For dense additive patches.
+    Polynomial,
+}
+
+/// Sine of `2π * phase` for `phase` in [0, 1) by polynomial: fold into [-π/2, π/2], where the
+/// Taylor series to x⁷ is accurate and never exceeds 1.
+#[inline]
+fn fast_sin_cycles(phase: f32) -> f32 {
+    let t = if phase < 0.25 {
+        phase
+    } else if phase < 0.75 {
+        0.5 - phase
+    } else {
+        phase - 1.0
+    };
+    let x = 2.0 * PI * t;
+    let x2 = x * x;
+    x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0)))
+}
+
 /// Generates a sine wave at the given frequency. Phase is carried across process() calls for continuity.
-#[derive(Clone, Debug, PartialEq)]
+/// Two sine generators are equal when their settings and phase are; the cached increment is
+/// ignored.
+#[derive(Clone, Debug)]
 pub struct SineGenerator {
-    /// Frequency in Hz (e.g. 440.0).
+    /// Frequency in Hz (e.g. 440.0). Prefer [`set_frequency`](Self::set_frequency); a direct
+    /// write takes effect at the next block.
     pub frequency_hz: f32,
-    /// Sample rate in Hz (e.g. 48_000). Must match the stream.
+    /// Sample rate in Hz (e.g. 48_000). Must match the stream. Prefer
+    /// [`set_sample_rate`](Self::set_sample_rate); a direct write takes effect at the next block.
     pub sample_rate: u32,
-    /// Phase in [0.0, 1.0). Advance by frequency_hz / sample_rate per sample; wrap at 1.0.
+    /// Phase advance per sample, `frequency_hz / sample_rate`, and the pair it was computed
+    /// from. The setters keep it in step; `process` recomputes it if the fields were written.
+    phase_inc: f32,
+    phase_inc_for: (f32, u32),
+    /// Phase in [0.0, 1.0). Advance by `phase_inc` per sample; wrap at 1.0.
     pub phase: f32,
+    /// Exact `sin` (default) or a faster approximation.
+    pub approx: SineApprox,
 }
 
 impl SineGenerator {
@@ -23,22 +78,109 @@ impl SineGenerator {
         Self {
             frequency_hz,
             sample_rate,
+            phase_inc: frequency_hz / sample_rate as f32,
+            phase_inc_for: (frequency_hz, sample_rate),
             phase: 0.0,
+            approx: SineApprox::Exact,
         }
     }
+
+    /// Phase advance per sample (cycles), as cached for rendering. The setters refresh it at
+    /// once; after a direct write to `frequency_hz` or `sample_rate` it still holds the old
+    /// value until the next `process` refreshes it.
+    pub fn phase_increment(&self) -> f32 {
+        self.phase_inc
+    }
+
+    /// Changes the pitch; the phase carries on, so there is no click.
+    pub fn set_frequency(&mut self, frequency_hz: f32) {
+        self.frequency_hz = frequency_hz;
+        self.update_phase_inc();
+    }
+
+    /// Changes the sample rate (e.g. after the stream is reopened), keeping the pitch.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.update_phase_inc();
+    }
+
+    fn update_phase_inc(&mut self) {
+        self.phase_inc = self.frequency_hz / self.sample_rate as f32;
+        self.phase_inc_for = (self.frequency_hz, self.sample_rate);
+    }
+}
+
+impl PartialEq for SineGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        self.frequency_hz == other.frequency_hz
+            && self.sample_rate == other.sample_rate
+            && self.phase == other.phase
+            && self.approx == other.approx
+    }
 }
 
 impl Processor for SineGenerator {
     fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
-        for sample in output.iter_mut() {
-            *sample = f32::sin(2.0 * PI * self.phase);
+        if self.phase_inc_for != (self.frequency_hz, self.sample_rate) {
+            self.update_phase_inc();
+        }
+        match self.approx {
+            SineApprox::Exact => {
+                for sample in output.iter_mut() {
+                    *sample = f32::sin(2.0 * PI * self.phase);
+                    self.phase += self.phase_inc;
+                    self.phase %= 1.0;
+                }
+            }
+            SineApprox::Polynomial => {
+                for sample in output.iter_mut() {
+                    *sample = fast_sin_cycles(self.phase);
+                    self.phase += self.phase_inc;
+                    self.phase %= 1.0;
+                }
+            }
+        }
+    }
+}
+
+/// Quadrature oscillator on interleaved stereo (L, R, L, R, ...): left is `sin(2π phase)`, right
+/// is `cos(2π phase)` of the same phase, i.e. 90° ahead. One frame advances the phase once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuadratureOsc {
+    /// Frequency in Hz.
+    pub frequency_hz: f32,
+    /// Sample rate in Hz. Must match the stream.
+    pub sample_rate: u32,
+    /// Phase in [0.0, 1.0), advanced once per stereo frame.
+    pub phase: f32,
+}
+
+impl QuadratureOsc {
+    /// Creates a quadrature oscillator. Phase starts at 0.0.
+    pub fn new(frequency_hz: f32, sample_rate: u32) -> Self {
+        Self {
+            frequency_hz,
+            sample_rate,
+            phase: 0.0,
+        }
+    }
+}
+
+impl Processor for QuadratureOsc {
+    fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+        let n = output.len() / 2 * 2;
+        for frame in output[..n].chunks_exact_mut(2) {
+            let angle = 2.0 * PI * self.phase;
+            frame[0] = angle.sin();
+            frame[1] = angle.cos();
             self.phase += self.frequency_hz / self.sample_rate as f32;
             self.phase %= 1.0;
         }
+        output[n..].fill(0.0);
     }
 }
 
-/// Multiplies each sample by a gain factor. In-place: reads and writes the same buffer.
+/// Multiplies each sample of its input by a gain factor. Without an input it outputs silence.
 #[derive(Clone, Debug, PartialEq)]
 pub struct GainProcessor {
     /// Linear gain (1.0 = unity, 0.0 = silence).
@@ -54,49 +196,226 @@ impl GainProcessor {
 
 impl Processor for GainProcessor {
     fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
-        if let Some(inp) = inputs.first() {
-            let n = output.len().min(inp.len());
-            for i in 0..n {
-                output[i] = inp[i] * self.gain;
-            }
-            for s in output[n..].iter_mut() {
-                *s = 0.0;
-            }
+        // Unity and silence need no multiplies: copy and zero-fill.
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let n = output.len().min(inp.len());
+        if self.gain == 1.0 {
+            output[..n].copy_from_slice(&inp[..n]);
+        } else if self.gain == 0.0 {
+            output[..n].fill(0.0);
         } else {
-            for sample in output.iter_mut() {
-                *sample *= self.gain;
-            }
+            scale_into(&inp[..n], &mut output[..n], self.gain);
         }
+        output[n..].fill(0.0);
     }
 }
 
-/// Mixes N inputs with per-input linear gain. output[i] = sum over j of inputs[j][i] * gains[j].
+/// `output[i] = input[i] * gain` over equal-length slices.
+fn scale_into_scalar(input: &[f32], output: &mut [f32], gain: f32) {
+    for (o, &i) in output.iter_mut().zip(input) {
+        *o = i * gain;
+    }
+}
+
+#[cfg(not(feature = "simd"))]
+pub(crate) fn scale_into(input: &[f32], output: &mut [f32], gain: f32) {
+    scale_into_scalar(input, output, gain);
+}
+
+/// Eight lanes at a time, scalar for the remainder. Lane multiplies are plain IEEE products, so
+/// the result is bit-identical to the scalar path.
+#[cfg(feature = "simd")]
+pub(crate) fn scale_into(input: &[f32], output: &mut [f32], gain: f32) {
+    use wide::f32x8;
+    let g = f32x8::splat(gain);
+    let mut inputs = input.chunks_exact(8);
+    let mut outputs = output.chunks_exact_mut(8);
+    for (o, i) in (&mut outputs).zip(&mut inputs) {
+        let lanes: [f32; 8] = i.try_into().expect("chunk of 8");
+        o.copy_from_slice(&(f32x8::from(lanes) * g).to_array());
+    }
+    scale_into_scalar(inputs.remainder(), outputs.into_remainder(), gain);
+}
+
+/// Per-channel gain for interleaved stereo (L, R, L, R, ...): even samples get `left_gain`,
+/// odd samples `right_gain`. With `channels == 1` the buffer is mono and `left_gain` applies
+/// to every sample. Like [`GainProcessor`], outputs silence when there is no input.
 #[derive(Clone, Debug, PartialEq)]
+pub struct Balance {
+    /// Linear gain for the left channel (and for mono buffers).
+    pub left_gain: f32,
+    /// Linear gain for the right channel.
+    pub right_gain: f32,
+    /// Interleaved channel count: 1 (mono) or 2 (stereo).
+    pub channels: usize,
+}
+
+impl Balance {
+    /// Creates a stereo balance stage with the given left/right gains.
+    pub fn new(left_gain: f32, right_gain: f32) -> Self {
+        Self {
+            left_gain,
+            right_gain,
+            channels: 2,
+        }
+    }
+
+    fn gain_at(&self, i: usize) -> f32 {
+        if self.channels >= 2 && i % 2 == 1 {
+            self.right_gain
+        } else {
+            self.left_gain
+        }
+    }
+}
+
+impl Processor for Balance {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let n = output.len().min(inp.len());
+        for i in 0..n {
+            output[i] = inp[i] * self.gain_at(i);
+        }
+        output[n..].fill(0.0);
+    }
+}
+
+/// Mixes N inputs with per-input linear gain. output[i] = sum over j of inputs[j][i] * gains[j].
+/// Two mixers are equal when their settings are; the measured input peaks are ignored.
+#[derive(Clone, Debug)]
 pub struct Mixer {
     /// Per-input linear gain; length must match number of inputs when process() is called.
     pub gains: Vec<f32>,
+    /// How weighted inputs are combined. Defaults to [`MixMode::Sum`].
+    pub mode: MixMode,
+    /// Peak of each raw input (before its gain) over the last block. One slot per possible
+    /// input, so `gains` can change length without `process` allocating.
+    input_peaks: [f32; MAX_INPUTS],
+}
+
+impl PartialEq for Mixer {
+    fn eq(&self, other: &Self) -> bool {
+        self.gains == other.gains && self.mode == other.mode
+    }
+}
+
+/// How a [`Mixer`] combines its weighted inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MixMode {
+    /// Plain sum; many inputs at unity can exceed full scale.
+    #[default]
+    Sum,
+    /// Sum divided by the number of active (connected, nonzero-gain) inputs, so N unity inputs
+    /// stay at the level of one.
+    Average,
 }
 
 impl Mixer {
     /// Creates a mixer with the given per-input gains.
     pub fn new(gains: Vec<f32>) -> Self {
-        Self { gains }
+        Self {
+            gains,
+            mode: MixMode::Sum,
+            input_peaks: [0.0; MAX_INPUTS],
+        }
+    }
+
+    /// Peak level of each input over the last processed block, measured before the input's gain,
+    /// so a hot source shows up even if it is turned down. Indexed like `gains` (one entry per
+    /// gain, up to [`MAX_INPUTS`]); an input without a gain is still measured.
+    pub fn input_peaks(&self) -> &[f32] {
+        &self.input_peaks[..self.gains.len().min(MAX_INPUTS)]
+    }
+
+    /// Like [`new`](Self::new), but rejects an empty gain list (a mixer that can never pass signal).
+    /// The count is checked against the connected inputs at `AudioGraph::compile`.
+    pub fn try_new(gains: Vec<f32>) -> Result<Self, &'static str> {
+        if gains.is_empty() {
+            return Err("mixer needs at least one gain");
+        }
+        Ok(Self::new(gains))
     }
 
     /// Creates a stereo mixer with unity gain on both inputs.
     pub fn stereo() -> Self {
         Self::new(vec![1.0, 1.0])
     }
-}
 
-impl Processor for Mixer {
-    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+    /// Any number of inputs, any lengths: missing samples and gains count as zero.
+    fn mix_general(&self, inputs: &[&[f32]], scale: f32, output: &mut [f32]) {
         for (i, sample) in output.iter_mut().enumerate() {
             *sample = 0.0;
             for (j, inp) in inputs.iter().enumerate() {
                 let g = self.gains.get(j).copied().unwrap_or(0.0);
                 *sample += inp.get(i).copied().unwrap_or(0.0) * g;
             }
+            *sample *= scale;
+        }
+    }
+}
+
+/// Two-input fast path for [`Mixer`]: both inputs cover `output`, so no per-sample bounds checks.
+fn mix_two(a: &[f32], b: &[f32], g0: f32, g1: f32, scale: f32, output: &mut [f32]) {
+    let n = output.len();
+    for ((out, &x), &y) in output.iter_mut().zip(&a[..n]).zip(&b[..n]) {
+        *out = (x * g0 + y * g1) * scale;
+    }
+}
+
+impl Processor for Mixer {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let scale = match self.mode {
+            MixMode::Sum => 1.0,
+            MixMode::Average => {
+                let active = (0..inputs.len())
+                    .filter(|&j| self.gains.get(j).is_some_and(|&g| g != 0.0))
+                    .count();
+                1.0 / active.max(1) as f32
+            }
+        };
+        match (inputs, self.gains.as_slice()) {
+            (&[a, b], &[g0, g1, ..]) if a.len() >= output.len() && b.len() >= output.len() => {
+                mix_two(a, b, g0, g1, scale, output)
+            }
+            _ => self.mix_general(inputs, scale, output),
+        }
+        let n = output.len();
+        for (peak, inp) in self.input_peaks.iter_mut().zip(inputs) {
+            let len = n.min(inp.len());
+            *peak = inp[..len].iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        }
+        // Gains past the connected inputs meter silence.
+        self.input_peaks[inputs.len().min(MAX_INPUTS)..].fill(0.0);
+    }
+}
+
+/// Musical note length for tempo-synced delay times.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteDivision {
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    DottedQuarter,
+    DottedEighth,
+    QuarterTriplet,
+    EighthTriplet,
+}
+
+impl NoteDivision {
+    /// Length in quarter-note beats.
+    pub fn beats(self) -> f32 {
+        match self {
+            NoteDivision::Whole => 4.0,
+            NoteDivision::Half => 2.0,
+            NoteDivision::Quarter => 1.0,
+            NoteDivision::Eighth => 0.5,
+            NoteDivision::Sixteenth => 0.25,
+            NoteDivision::DottedQuarter => 1.5,
+            NoteDivision::DottedEighth => 0.75,
+            NoteDivision::QuarterTriplet => 2.0 / 3.0,
+            NoteDivision::EighthTriplet => 1.0 / 3.0,
         }
     }
 }
@@ -135,13 +454,43 @@ impl DelayLine {
         );
     }
 
+    /// Sets delay time to a musical note length at `bpm`, at the line's own sample rate
+    /// (clamped to capacity).
+    pub fn set_delay_note(&mut self, division: NoteDivision, bpm: f32) {
+        let rate = self.sample_rate as f32;
+        let samples = (division.beats() * 60.0 / bpm.max(1.0) * rate).round();
+        let samples = samples.min(self.buffer.len() as f32);
+        self.set_delay_ms(1000.0 * samples / rate);
+    }
+
     fn delay_samples(&self) -> usize {
         let d = (self.delay_ms / 1000.0 * self.sample_rate as f32).round() as usize;
         d.min(self.buffer.len())
     }
 }
 
+/// Level at which a decaying feedback tail counts as gone (-60 dB).
+const TAIL_FLOOR: f32 = 0.001;
+/// Most repeats a feedback tail reports; feedback of 1 or more never decays.
+const MAX_TAIL_REPEATS: usize = 100;
+
+/// Repeats of a feedback delay before they fall below [`TAIL_FLOOR`].
+fn feedback_repeats(feedback: f32) -> usize {
+    let feedback = feedback.abs();
+    if feedback < TAIL_FLOOR {
+        0
+    } else if feedback >= 1.0 {
+        MAX_TAIL_REPEATS
+    } else {
+        ((TAIL_FLOOR.ln() / feedback.ln()).ceil() as usize).min(MAX_TAIL_REPEATS)
+    }
+}
+
 impl Processor for DelayLine {
+    fn tail_samples(&self) -> usize {
+        self.delay_samples()
+    }
+
     fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
         let inp = match inputs.first() {
             Some(s) => *s,
@@ -170,6 +519,11 @@ impl Processor for DelayLine {
         }
         output[n..].fill(0.0);
     }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
 }
 
 /// Echo: delay line with feedback and dry/wet mix. Repeats decay over time (echo).
@@ -210,6 +564,11 @@ impl Echo {
         self.delay_ms = delay_ms.clamp(0.0, max_ms);
     }
 
+    /// Delay time in milliseconds.
+    pub fn delay_ms(&self) -> f32 {
+        self.delay_ms
+    }
+
     fn delay_samples(&self) -> usize {
         let d = (self.delay_ms / 1000.0 * self.sample_rate as f32).round() as usize;
         d.min(self.buffer.len())
@@ -217,6 +576,11 @@ impl Echo {
 }
 
 impl Processor for Echo {
+    /// The delay, once for the first repeat and once more for each repeat until -60 dB.
+    fn tail_samples(&self) -> usize {
+        self.delay_samples() * (1 + feedback_repeats(self.feedback))
+    }
+
     fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
         let inp = match inputs.first() {
             Some(s) => *s,
@@ -241,11 +605,197 @@ impl Processor for Echo {
             let read_pos = (self.write_pos + cap - delay) % cap;
             let delayed = self.buffer[read_pos];
             output[i] = self.dry * inp[i] + self.wet * delayed;
-            self.buffer[self.write_pos] = inp[i] + self.feedback * delayed;
+            self.buffer[self.write_pos] = flush_denormals(inp[i] + self.feedback * delayed);
+            self.write_pos = (self.write_pos + 1) % cap;
+        }
+        output[n..].fill(0.0);
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_pos = 0;
+    }
+}
+
+/// Ping-pong delay on interleaved stereo (L, R, L, R, ...). Left repeats feed the right delay and
+/// right repeats feed the left, so echoes bounce between channels as they decay.
+/// Both delay lines are allocated in new(); no allocation in process().
+#[derive(Clone, Debug, PartialEq)]
+pub struct PingPongDelay {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    /// Write position in frames (shared by both lines).
+    write_pos: usize,
+    time_ms: f32,
+    sample_rate: u32,
+    /// Feedback (0..=1). Amount of each repeat sent across to the other channel.
+    pub feedback: f32,
+    /// Dry/wet mix (0 = dry only, 1 = wet only).
+    pub mix: f32,
+}
+
+impl PingPongDelay {
+    /// Creates a ping-pong delay with room for up to `max_time_ms` milliseconds per bounce.
+    pub fn new(max_time_ms: f32, sample_rate: u32) -> Self {
+        let max_frames = (max_time_ms / 1000.0 * sample_rate as f32).ceil().max(1.0) as usize;
+        PingPongDelay {
+            left: vec![0.0; max_frames],
+            right: vec![0.0; max_frames],
+            write_pos: 0,
+            time_ms: 0.0,
+            sample_rate,
+            feedback: 0.5,
+            mix: 0.35,
+        }
+    }
+
+    /// Sets the time between bounces in milliseconds (clamped to 0..max).
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        let max_ms = 1000.0 * self.left.len() as f32 / self.sample_rate as f32;
+        self.time_ms = time_ms.clamp(0.0, max_ms);
+    }
+
+    /// Time between bounces in milliseconds.
+    pub fn time_ms(&self) -> f32 {
+        self.time_ms
+    }
+
+    fn delay_frames(&self) -> usize {
+        let d = (self.time_ms / 1000.0 * self.sample_rate as f32).round() as usize;
+        d.clamp(1, self.left.len())
+    }
+}
+
+impl Processor for PingPongDelay {
+    /// As for [`Echo`], in interleaved samples (two per frame).
+    fn tail_samples(&self) -> usize {
+        2 * self.delay_frames() * (1 + feedback_repeats(self.feedback))
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = match inputs.first() {
+            Some(s) => *s,
+            None => {
+                output.fill(0.0);
+                return;
+            }
+        };
+        let cap = self.left.len();
+        let delay = self.delay_frames();
+        let n = output.len().min(inp.len()) / 2 * 2;
+        for i in (0..n).step_by(2) {
+            let read_pos = (self.write_pos + cap - delay) % cap;
+            let dl = self.left[read_pos];
+            let dr = self.right[read_pos];
+            output[i] = (1.0 - self.mix) * inp[i] + self.mix * dl;
+            output[i + 1] = (1.0 - self.mix) * inp[i + 1] + self.mix * dr;
+            self.left[self.write_pos] = flush_denormals(inp[i] + self.feedback * dr);
+            self.right[self.write_pos] = flush_denormals(inp[i + 1] + self.feedback * dl);
+            self.write_pos = (self.write_pos + 1) % cap;
+        }
+        output[n..].fill(0.0);
+    }
+
+    fn reset(&mut self) {
+        self.left.fill(0.0);
+        self.right.fill(0.0);
+        self.write_pos = 0;
+    }
+}
+
+/// Center delay of the chorus voice; the LFO sweeps +/- depth_ms around it.
+const CHORUS_BASE_MS: f32 = 15.0;
+/// Upper bound on `depth_ms`; sizes the delay buffers.
+const CHORUS_MAX_DEPTH_MS: f32 = 10.0;
+
+/// Stereo chorus on interleaved L/R: a short delay swept by a sine LFO, mixed with the dry signal.
+/// The right channel's LFO runs a quarter cycle ahead of the left for width.
+/// Delay buffers are allocated in new(); no allocation in process().
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chorus {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    write_pos: usize,
+    /// LFO phase in 0..1.
+    phase: f32,
+    sample_rate: u32,
+    /// LFO rate in Hz.
+    pub rate_hz: f32,
+    /// LFO sweep in milliseconds (clamped to 0..=10).
+    pub depth_ms: f32,
+    /// Dry/wet mix (0 = dry only, 1 = wet only).
+    pub mix: f32,
+}
+
+impl Chorus {
+    /// Creates a chorus with a gentle default sweep.
+    pub fn new(sample_rate: u32) -> Self {
+        let max_ms = CHORUS_BASE_MS + CHORUS_MAX_DEPTH_MS;
+        // +2 frames of headroom for linear interpolation at the longest delay.
+        let len = (max_ms / 1000.0 * sample_rate as f32).ceil() as usize + 2;
+        Chorus {
+            left: vec![0.0; len],
+            right: vec![0.0; len],
+            write_pos: 0,
+            phase: 0.0,
+            sample_rate,
+            rate_hz: 0.8,
+            depth_ms: 3.0,
+            mix: 0.5,
+        }
+    }
+
+    /// Reads `buf` `delay` frames (fractional) behind the write position.
+    #[inline]
+    fn read(buf: &[f32], write_pos: usize, delay: f32) -> f32 {
+        let cap = buf.len();
+        let whole = delay.floor() as usize;
+        let frac = delay - whole as f32;
+        let a = buf[(write_pos + cap - whole) % cap];
+        let b = buf[(write_pos + cap - whole - 1) % cap];
+        a + (b - a) * frac
+    }
+}
+
+impl Processor for Chorus {
+    /// The longest swept delay, in interleaved samples.
+    fn tail_samples(&self) -> usize {
+        2 * self.left.len()
+    }
+
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = match inputs.first() {
+            Some(s) => *s,
+            None => {
+                output.fill(0.0);
+                return;
+            }
+        };
+        let sr = self.sample_rate as f32;
+        let base = CHORUS_BASE_MS / 1000.0 * sr;
+        let depth = self.depth_ms.clamp(0.0, CHORUS_MAX_DEPTH_MS) / 1000.0 * sr;
+        let phase_inc = self.rate_hz / sr;
+        let cap = self.left.len();
+        let n = output.len().min(inp.len()) / 2 * 2;
+        for i in (0..n).step_by(2) {
+            self.left[self.write_pos] = inp[i];
+            self.right[self.write_pos] = inp[i + 1];
+            let lfo_l = f32::sin(2.0 * PI * self.phase);
+            let lfo_r = f32::sin(2.0 * PI * (self.phase + 0.25));
+            let wet_l = Self::read(&self.left, self.write_pos, base + depth * lfo_l);
+            let wet_r = Self::read(&self.right, self.write_pos, base + depth * lfo_r);
+            output[i] = (1.0 - self.mix) * inp[i] + self.mix * wet_l;
+            output[i + 1] = (1.0 - self.mix) * inp[i + 1] + self.mix * wet_r;
             self.write_pos = (self.write_pos + 1) % cap;
+            self.phase = (self.phase + phase_inc).fract();
         }
         output[n..].fill(0.0);
     }
+
+    fn reset(&mut self) {
+        self.left.fill(0.0);
+        self.right.fill(0.0);
+    }
 }
 
 /// Tremolo: periodic volume modulation (LFO). output = input * (1 - depth + depth * lfo).
@@ -293,20 +843,72 @@ impl Processor for Tremolo {
     }
 }
 
-/// Overdrive: soft clipping saturation. output = tanh(drive * input) for warmth.
+/// Auto-panner: sweeps a mono input across interleaved stereo (L, R, L, R, ...) with a sine LFO.
+/// Frame `f` of the output reads `inputs[0][f]`. Constant-power law: `L = cos θ`, `R = sin θ`
+/// with θ from 0 (hard left) to π/2 (hard right), so loudness stays even across the sweep.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Overdrive {
-    /// Drive amount (0 = clean passthrough, higher = more saturation). Typical 1–5.
-    pub drive: f32,
+pub struct AutoPanner {
+    phase: f32,
+    /// LFO rate in Hz.
+    pub rate_hz: f32,
+    sample_rate: u32,
+    /// Sweep width 0..=1. 0 = always centered, 1 = hard left to hard right.
+    pub depth: f32,
 }
 
-impl Overdrive {
-    pub fn new(drive: f32) -> Self {
-        Overdrive { drive }
+impl AutoPanner {
+    /// Creates an auto-panner at `rate_hz` with full depth. The sweep starts centered, moving right.
+    pub fn new(rate_hz: f32, sample_rate: u32) -> Self {
+        AutoPanner {
+            phase: 0.0,
+            rate_hz,
+            sample_rate,
+            depth: 1.0,
+        }
     }
 }
 
-impl Processor for Overdrive {
+impl Processor for AutoPanner {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let n = output.len() / 2 * 2;
+        let phase_inc = self.rate_hz / self.sample_rate as f32;
+        for (f, frame) in output[..n].chunks_exact_mut(2).enumerate() {
+            let x = inp.get(f).copied().unwrap_or(0.0);
+            let pan = self.depth.clamp(0.0, 1.0) * f32::sin(2.0 * PI * self.phase);
+            let theta = (pan + 1.0) * PI / 4.0;
+            frame[0] = x * theta.cos();
+            frame[1] = x * theta.sin();
+            self.phase += phase_inc;
+            self.phase %= 1.0;
+        }
+        output[n..].fill(0.0);
+    }
+}
+
+/// Stereo-to-mono downmix on interleaved stereo (L, R, L, R, ...): both output channels get
+/// `(L + R) * gain`, for checking a mix's mono compatibility. Out-of-phase content cancels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonoSum {
+    /// Scale applied to the channel sum. 0.5 (the default) keeps a centered signal at its level;
+    /// use `FRAC_1_SQRT_2` for an equal-power downmix.
+    pub gain: f32,
+}
+
+impl MonoSum {
+    /// Creates a downmix with gain 0.5.
+    pub fn new() -> Self {
+        MonoSum { gain: 0.5 }
+    }
+}
+
+impl Default for MonoSum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Processor for MonoSum {
     fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
         let inp = match inputs.first() {
             Some(s) => *s,
@@ -315,26 +917,221 @@ impl Processor for Overdrive {
                 return;
             }
         };
-        let n = output.len().min(inp.len());
-        if self.drive <= 0.0 {
-            output[..n].copy_from_slice(&inp[..n]);
-        } else {
-            let scale = 1.0 + self.drive * 8.0;
-            for i in 0..n {
-                output[i] = (inp[i] * scale).tanh();
-            }
+        let n = output.len().min(inp.len()) / 2 * 2;
+        for (out, frame) in output[..n]
+            .chunks_exact_mut(2)
+            .zip(inp[..n].chunks_exact(2))
+        {
+            let mono = (frame[0] + frame[1]) * self.gain;
+            out[0] = mono;
+            out[1] = mono;
         }
         output[n..].fill(0.0);
     }
 }
 
-/// Biquad filter (Direct Form I). Lowpass or highpass via Audio EQ Cookbook coefficients.
+/// Splits one channel out of interleaved stereo (L, R, L, R, ...): frame `f`'s `channel` sample
+/// is written to `output[f]`, the rest of the buffer is zeroed. Graph nodes have a single
+/// output, so use two of these (channel 0 and 1) to process left and right separately, then
+/// rejoin them with [`Interleave`].
 #[derive(Clone, Debug, PartialEq)]
-pub struct BiquadFilter {
-    b0: f32,
-    b1: f32,
-    b2: f32,
-    a1: f32,
+pub struct DeInterleave {
+    /// Channel to extract: 0 = left, 1 = right.
+    pub channel: usize,
+}
+
+impl DeInterleave {
+    pub fn new(channel: usize) -> Self {
+        DeInterleave { channel }
+    }
+}
+
+impl Processor for DeInterleave {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let frames = output.len().min(inp.len()) / 2;
+        let channel = self.channel.min(1);
+        for (f, out) in output[..frames].iter_mut().enumerate() {
+            *out = inp[2 * f + channel];
+        }
+        output[frames..].fill(0.0);
+    }
+}
+
+/// Joins two mono inputs (one sample per frame, as produced by [`DeInterleave`]) into
+/// interleaved stereo: the first input becomes left, the second right. A missing input is silent.
+/// In a graph, inputs arrive in execution order, so build the left chain first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interleave;
+
+impl Interleave {
+    pub fn new() -> Self {
+        Interleave
+    }
+}
+
+impl Processor for Interleave {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let left = inputs.first().copied().unwrap_or(&[]);
+        let right = inputs.get(1).copied().unwrap_or(&[]);
+        let n = output.len() / 2 * 2;
+        for (f, frame) in output[..n].chunks_exact_mut(2).enumerate() {
+            frame[0] = left.get(f).copied().unwrap_or(0.0);
+            frame[1] = right.get(f).copied().unwrap_or(0.0);
+        }
+        output[n..].fill(0.0);
+    }
+}
+
+/// FIR taps per polyphase branch for Overdrive oversampling. Kernel length = taps * factor.
+const OVERSAMPLE_TAPS_PER_PHASE: usize = 16;
+/// Highest supported oversampling factor.
+const MAX_OVERSAMPLE: usize = 4;
+
+/// Overdrive: soft clipping saturation. output = tanh(drive * input) for warmth.
+/// With [`set_oversample`](Overdrive::set_oversample) > 1 the shaper runs at a higher rate
+/// (polyphase FIR up, tanh, FIR down) so harmonics above Nyquist are filtered instead of
+/// aliasing back into the audible band. Filter state is allocated in set_oversample(); no
+/// allocation in process().
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overdrive {
+    /// Drive amount (0 = clean passthrough, higher = more saturation). Typical 1–5.
+    pub drive: f32,
+    oversample: usize,
+    /// Windowed-sinc lowpass at the oversampled rate (`taps * oversample` long; empty at 1x).
+    kernel: Vec<f32>,
+    /// Recent input samples (ring, newest at `in_pos`).
+    in_hist: Vec<f32>,
+    in_pos: usize,
+    /// Recent shaped samples at the oversampled rate (ring, newest at `shaped_pos`).
+    shaped_hist: Vec<f32>,
+    shaped_pos: usize,
+}
+
+impl Overdrive {
+    pub fn new(drive: f32) -> Self {
+        Overdrive {
+            drive,
+            oversample: 1,
+            kernel: Vec::new(),
+            in_hist: Vec::new(),
+            in_pos: 0,
+            shaped_hist: Vec::new(),
+            shaped_pos: 0,
+        }
+    }
+
+    /// Sets the oversampling factor: 1 (off), 2, or 4. Other values round down to the nearest
+    /// of these. Allocates and clears filter state: call from the control thread. Adds about
+    /// 16 samples of latency when > 1.
+    pub fn set_oversample(&mut self, factor: usize) {
+        self.oversample = match factor {
+            0 | 1 => 1,
+            2 | 3 => 2,
+            _ => MAX_OVERSAMPLE,
+        };
+        if self.oversample == 1 {
+            self.kernel = Vec::new();
+            self.in_hist = Vec::new();
+            self.shaped_hist = Vec::new();
+            return;
+        }
+        let len = OVERSAMPLE_TAPS_PER_PHASE * self.oversample;
+        self.kernel = vec![0.0; len];
+        self.in_hist = vec![0.0; OVERSAMPLE_TAPS_PER_PHASE];
+        self.shaped_hist = vec![0.0; len];
+        self.in_pos = 0;
+        self.shaped_pos = 0;
+        // Blackman-windowed sinc, cutoff just under the original Nyquist, unity DC gain.
+        let cutoff = 0.45 / self.oversample as f32;
+        let mid = (len - 1) as f32 / 2.0;
+        for (j, h) in self.kernel.iter_mut().enumerate() {
+            let t = j as f32 - mid;
+            let sinc = if t == 0.0 {
+                2.0 * cutoff
+            } else {
+                f32::sin(2.0 * PI * cutoff * t) / (PI * t)
+            };
+            let w = 2.0 * PI * j as f32 / (len - 1) as f32;
+            *h = sinc * (0.42 - 0.5 * f32::cos(w) + 0.08 * f32::cos(2.0 * w));
+        }
+        let sum: f32 = self.kernel.iter().sum();
+        for h in &mut self.kernel {
+            *h /= sum;
+        }
+    }
+
+    /// Current oversampling factor (1, 2, or 4).
+    pub fn oversample(&self) -> usize {
+        self.oversample
+    }
+
+    /// One input sample through upsample -> tanh -> downsample.
+    #[inline]
+    fn process_oversampled(&mut self, x: f32, scale: f32) -> f32 {
+        let factor = self.oversample;
+        let len = self.shaped_hist.len();
+        self.in_pos = (self.in_pos + 1) % OVERSAMPLE_TAPS_PER_PHASE;
+        self.in_hist[self.in_pos] = x;
+        for phase in 0..factor {
+            // Zero-stuffed upsampling: only every factor-th kernel tap meets a real sample.
+            let mut up = 0.0;
+            for i in 0..OVERSAMPLE_TAPS_PER_PHASE {
+                let idx = (self.in_pos + OVERSAMPLE_TAPS_PER_PHASE - i) % OVERSAMPLE_TAPS_PER_PHASE;
+                up += self.kernel[phase + i * factor] * self.in_hist[idx];
+            }
+            self.shaped_pos = (self.shaped_pos + 1) % len;
+            self.shaped_hist[self.shaped_pos] = (up * factor as f32 * scale).tanh();
+        }
+        let mut out = 0.0;
+        for j in 0..len {
+            out += self.kernel[j] * self.shaped_hist[(self.shaped_pos + len - j) % len];
+        }
+        out
+    }
+}
+
+impl Processor for Overdrive {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = match inputs.first() {
+            Some(s) => *s,
+            None => {
+                output.fill(0.0);
+                return;
+            }
+        };
+        let n = output.len().min(inp.len());
+        if self.drive <= 0.0 {
+            output[..n].copy_from_slice(&inp[..n]);
+        } else if self.oversample > 1 {
+            let scale = 1.0 + self.drive * 8.0;
+            for i in 0..n {
+                output[i] = self.process_oversampled(inp[i], scale);
+            }
+        } else {
+            let scale = 1.0 + self.drive * 8.0;
+            for i in 0..n {
+                output[i] = (inp[i] * scale).tanh();
+            }
+        }
+        output[n..].fill(0.0);
+    }
+
+    fn reset(&mut self) {
+        self.in_hist.fill(0.0);
+        self.shaped_hist.fill(0.0);
+    }
+}
+
+/// Biquad filter (Direct Form I). Lowpass or highpass via Audio EQ Cookbook coefficients.
+/// State is flushed of denormals each sample; if the output ever goes non-finite (e.g. a bad
+/// coefficient), the state is reset to zero so the filter recovers instead of emitting NaN forever.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiquadFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
     a2: f32,
     x1: f32,
     x2: f32,
@@ -405,6 +1202,14 @@ impl BiquadFilter {
         let a2 = 1.0 - alpha;
         (b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0)
     }
+
+    /// Clears the filter memory (input and output history).
+    fn reset_state(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
 }
 
 impl Processor for BiquadFilter {
@@ -422,6 +1227,12 @@ impl Processor for BiquadFilter {
             let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
                 - self.a1 * self.y1
                 - self.a2 * self.y2;
+            if !y.is_finite() {
+                self.reset_state();
+                output[i] = 0.0;
+                continue;
+            }
+            let y = flush_denormals(y);
             self.x2 = self.x1;
             self.x1 = x;
             self.y2 = self.y1;
@@ -430,6 +1241,529 @@ impl Processor for BiquadFilter {
         }
         output[n..].fill(0.0);
     }
+
+    fn reset(&mut self) {
+        self.reset_state();
+    }
+}
+
+/// Length of one metronome click in milliseconds.
+const CLICK_MS: f32 = 30.0;
+/// Attack of the click envelope in milliseconds; ramps from zero so the burst doesn't pop.
+const CLICK_ATTACK_MS: f32 = 1.0;
+
+/// Metronome: emits a short decaying sine burst at the start of every beat. The first beat of
+/// each bar is accented (higher pitch, full level). At a steady tempo beat `k` starts exactly at
+/// sample `round(k * 60 / bpm * sample_rate)`, counted from the first processed sample. A `bpm`
+/// change takes effect from the beat after the next one: the next beat stays where it was
+/// scheduled and later beats follow at the new spacing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metronome {
+    /// Tempo in beats per minute.
+    pub bpm: f32,
+    /// Beats per bar; beat 1 of each bar is accented. 0 or 1 accents every beat.
+    pub beats_per_bar: u32,
+    sample_rate: u32,
+    /// Samples rendered since the metronome started.
+    position: u64,
+    /// Position within the bar of the next beat to trigger; beat 0 is accented.
+    next_beat_in_bar: u32,
+    /// Sample at which the next beat starts.
+    next_beat_at: u64,
+    /// Fraction of a sample by which `next_beat_at` was rounded, carried into the next beat so
+    /// fractional beat lengths don't drift.
+    beat_frac: f64,
+    /// Samples into the current click, or `None` between clicks.
+    click_pos: Option<usize>,
+    click_accent: bool,
+}
+
+impl Metronome {
+    /// Creates a metronome at `bpm` in 4/4. The first click starts on the first sample.
+    pub fn new(bpm: f32, sample_rate: u32) -> Self {
+        Metronome {
+            bpm,
+            beats_per_bar: 4,
+            sample_rate,
+            position: 0,
+            next_beat_in_bar: 0,
+            next_beat_at: 0,
+            beat_frac: 0.0,
+            click_pos: None,
+            click_accent: false,
+        }
+    }
+
+    /// Moves `next_beat_at` on by one beat at the current tempo.
+    fn schedule_next_beat(&mut self) {
+        let beat_len = 60.0 * self.sample_rate as f64 / self.bpm.max(1.0) as f64;
+        let exact = beat_len + self.beat_frac;
+        let whole = exact.round();
+        self.beat_frac = exact - whole;
+        self.next_beat_at += whole as u64;
+    }
+
+    /// Click envelope at `pos` samples into a click of `len` samples: linear attack, exponential
+    /// decay, and a linear taper so it ends at exactly zero.
+    fn click_envelope(pos: usize, len: usize, attack: usize) -> f32 {
+        let attack_gain = (pos as f32 / attack.max(1) as f32).min(1.0);
+        let t = pos as f32 / len as f32;
+        attack_gain * (-6.0 * t).exp() * (1.0 - t)
+    }
+}
+
+impl Processor for Metronome {
+    fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+        let sr = self.sample_rate as f32;
+        let len = (CLICK_MS / 1000.0 * sr) as usize;
+        let attack = (CLICK_ATTACK_MS / 1000.0 * sr) as usize;
+        for sample in output.iter_mut() {
+            if self.position >= self.next_beat_at {
+                self.click_accent = self.next_beat_in_bar == 0;
+                self.click_pos = Some(0);
+                self.next_beat_in_bar += 1;
+                if self.next_beat_in_bar >= self.beats_per_bar {
+                    self.next_beat_in_bar = 0;
+                }
+                self.schedule_next_beat();
+            }
+            *sample = match self.click_pos {
+                Some(pos) if pos < len => {
+                    let (freq, level) = if self.click_accent {
+                        (1500.0, 1.0)
+                    } else {
+                        (1000.0, 0.6)
+                    };
+                    self.click_pos = Some(pos + 1);
+                    let phase = 2.0 * PI * freq * pos as f32 / sr;
+                    level * Self::click_envelope(pos, len, attack) * phase.sin()
+                }
+                _ => {
+                    self.click_pos = None;
+                    0.0
+                }
+            };
+            self.position += 1;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.click_pos = None;
+    }
+}
+
+/// Spectrum of a [`Noise`] source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NoiseKind {
+    /// Flat spectrum (equal energy per Hz).
+    White,
+    /// -3 dB/octave (equal energy per octave).
+    Pink,
+    /// -6 dB/octave: leaky integral of white noise.
+    Brown,
+}
+
+/// Seed used by [`Noise::new`].
+const DEFAULT_NOISE_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Noise source: white, pink, or brown. Deterministic for a given seed; no allocation in process().
+/// Output is clamped to -1.0..=1.0 and scaled by `amplitude`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    /// Linear output level (1.0 = full scale).
+    pub amplitude: f32,
+    rng: SmallRng,
+    /// Pink filter state (Paul Kellet's refined method).
+    pink: [f32; 7],
+    /// Brown (leaky integrator) state.
+    brown: f32,
+}
+
+impl Noise {
+    /// Creates a noise source of the given kind with a fixed default seed.
+    pub fn new(kind: NoiseKind) -> Self {
+        Self::with_seed(kind, DEFAULT_NOISE_SEED)
+    }
+
+    /// Creates a noise source with an explicit seed. The same seed always yields the same samples.
+    pub fn with_seed(kind: NoiseKind, seed: u64) -> Self {
+        Noise {
+            kind,
+            amplitude: 1.0,
+            rng: SmallRng::from_seed(seed),
+            pink: [0.0; 7],
+            brown: 0.0,
+        }
+    }
+
+    #[inline]
+    fn next_pink(&mut self, white: f32) -> f32 {
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+        b[2] = 0.96900 * b[2] + white * 0.153_852;
+        b[3] = 0.86650 * b[3] + white * 0.310_485_6;
+        b[4] = 0.55000 * b[4] + white * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - white * 0.016_898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115_926;
+        pink * 0.11
+    }
+
+    #[inline]
+    fn next_brown(&mut self, white: f32) -> f32 {
+        // Leak keeps the integrator from drifting off to DC.
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+        self.brown * 3.5
+    }
+}
+
+impl Processor for Noise {
+    fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+        for sample in output.iter_mut() {
+            let white = self.rng.next_f32_unit();
+            let value = match self.kind {
+                NoiseKind::White => white,
+                NoiseKind::Pink => self.next_pink(white),
+                NoiseKind::Brown => self.next_brown(white),
+            };
+            *sample = value.clamp(-1.0, 1.0) * self.amplitude;
+        }
+    }
+}
+
+/// Sample-and-hold: samples `inputs[0]` on a `rate_hz` clock and holds it until the next tick.
+/// Fed by a Noise node this gives classic random stepped modulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleHold {
+    /// Clock rate in Hz (ticks per second).
+    pub rate_hz: f32,
+    sample_rate: u32,
+    /// Samples remaining until the next tick (fractional, so non-integer periods don't drift).
+    countdown: f32,
+    held: f32,
+}
+
+impl SampleHold {
+    /// Creates a sample-and-hold that takes its first sample immediately.
+    pub fn new(rate_hz: f32, sample_rate: u32) -> Self {
+        SampleHold {
+            rate_hz,
+            sample_rate,
+            countdown: 0.0,
+            held: 0.0,
+        }
+    }
+}
+
+impl Processor for SampleHold {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let period = self.sample_rate as f32 / self.rate_hz.max(f32::MIN_POSITIVE);
+        for (i, sample) in output.iter_mut().enumerate() {
+            if self.countdown <= 0.0 {
+                self.held = inp.get(i).copied().unwrap_or(0.0);
+                self.countdown += period;
+            }
+            self.countdown -= 1.0;
+            *sample = self.held;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.held = 0.0;
+        self.countdown = 0.0;
+    }
+}
+
+/// Slew limiter: output moves toward `inputs[0]` no faster than `rise_rate` (upward) or
+/// `fall_rate` (downward), in units per second. On a frequency control signal this is portamento.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slew {
+    /// Maximum upward change per second.
+    pub rise_rate: f32,
+    /// Maximum downward change per second.
+    pub fall_rate: f32,
+    sample_rate: u32,
+    current: f32,
+}
+
+impl Slew {
+    /// Creates a slew limiter starting at 0.0.
+    pub fn new(rise_rate: f32, fall_rate: f32, sample_rate: u32) -> Self {
+        Slew {
+            rise_rate,
+            fall_rate,
+            sample_rate,
+            current: 0.0,
+        }
+    }
+}
+
+impl Processor for Slew {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let sr = self.sample_rate as f32;
+        let max_up = self.rise_rate.max(0.0) / sr;
+        let max_down = self.fall_rate.max(0.0) / sr;
+        for (i, sample) in output.iter_mut().enumerate() {
+            let target = inp.get(i).copied().unwrap_or(0.0);
+            let delta = (target - self.current).clamp(-max_down, max_up);
+            self.current += delta;
+            *sample = self.current;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.current = 0.0;
+    }
+}
+
+/// Automatic gain control: measures each input block's RMS and moves a smoothed gain toward
+/// `target_rms / rms`, never above `max_gain`. Gain falls with the `attack_ms` time constant and
+/// rises with `release_ms`; it ramps linearly across each block so changes don't click.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoGain {
+    /// Output RMS level to aim for (linear, e.g. 0.1).
+    pub target_rms: f32,
+    /// Time constant for reducing gain (loud input), in milliseconds.
+    pub attack_ms: f32,
+    /// Time constant for raising gain (quiet input), in milliseconds.
+    pub release_ms: f32,
+    /// Upper bound on the applied gain, so silence and noise aren't boosted without limit.
+    pub max_gain: f32,
+    sample_rate: u32,
+    gain: f32,
+}
+
+impl AutoGain {
+    /// Creates an AGC aiming at `target_rms`, starting at unity gain
+    /// (attack 10 ms, release 500 ms, max gain 10).
+    pub fn new(target_rms: f32, sample_rate: u32) -> Self {
+        AutoGain {
+            target_rms,
+            attack_ms: 10.0,
+            release_ms: 500.0,
+            max_gain: 10.0,
+            sample_rate,
+            gain: 1.0,
+        }
+    }
+
+    /// Gain reached at the end of the last block.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+}
+
+impl Processor for AutoGain {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let n = output.len().min(inp.len());
+        if n == 0 {
+            output.fill(0.0);
+            return;
+        }
+        let rms = (inp[..n].iter().map(|s| s * s).sum::<f32>() / n as f32).sqrt();
+        let max_gain = self.max_gain.max(0.0);
+        let desired = if rms > 1e-9 {
+            (self.target_rms / rms).min(max_gain)
+        } else {
+            max_gain
+        };
+        let time_ms = if desired < self.gain {
+            self.attack_ms
+        } else {
+            self.release_ms
+        };
+        let time_samples = (time_ms / 1000.0 * self.sample_rate as f32).max(1.0);
+        let coef = (-(n as f32) / time_samples).exp();
+        let start = self.gain;
+        let end = (desired + (start - desired) * coef).min(max_gain);
+        let step = (end - start) / n as f32;
+        for i in 0..n {
+            output[i] = inp[i] * (start + step * (i + 1) as f32);
+        }
+        output[n..].fill(0.0);
+        self.gain = end;
+    }
+
+    fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+}
+
+/// Brickwall peak limiter: no output sample exceeds `ceiling` in magnitude. Gain drops instantly
+/// to whatever a hot sample needs, then recovers toward unity with the `release_ms` time
+/// constant. No lookahead, so heavy limiting distorts; it is a safety net, not a mastering tool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limiter {
+    /// Largest allowed output magnitude (1.0 = 0 dBFS).
+    pub ceiling: f32,
+    /// Release coefficient per sample, from `release_ms`.
+    release_coef: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    /// Creates a limiter at `ceiling` that recovers with the given release time.
+    pub fn new(ceiling: f32, release_ms: f32, sample_rate: u32) -> Self {
+        let release_samples = (release_ms / 1000.0 * sample_rate as f32).max(1.0);
+        Limiter {
+            ceiling,
+            release_coef: (-1.0 / release_samples).exp(),
+            gain: 1.0,
+        }
+    }
+
+    /// Current gain reduction factor (1.0 = not limiting).
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Limits `buffer` in place.
+    pub fn process_in_place(&mut self, buffer: &mut [f32]) {
+        for s in buffer.iter_mut() {
+            self.gain = 1.0 - (1.0 - self.gain) * self.release_coef;
+            let peak = s.abs() * self.gain;
+            if peak > self.ceiling {
+                self.gain = self.ceiling / s.abs();
+            }
+            *s = (*s * self.gain).clamp(-self.ceiling, self.ceiling);
+        }
+    }
+}
+
+impl Processor for Limiter {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let inp = inputs.first().copied().unwrap_or(&[]);
+        let n = output.len().min(inp.len());
+        output[..n].copy_from_slice(&inp[..n]);
+        output[n..].fill(0.0);
+        self.process_in_place(output);
+    }
+
+    fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+}
+
+/// Plays an in-memory multi-channel file (interleaved, `channels()` wide, already at the output
+/// sample rate; see [`load_wav_interleaved_at_rate`](crate::file_feeder::load_wav_interleaved_at_rate)).
+/// Writes interleaved output `output_channels` wide: a mono output gets the average of all file
+/// channels, a mono file is duplicated to every output channel, otherwise output channel `c`
+/// plays file channel `c % channels`. The position counts frames, not samples; `playback_rate`
+/// scales speed and pitch (2.0 = double), reading between frames by linear interpolation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilePlayer {
+    samples: Arc<Vec<f32>>,
+    channels: usize,
+    /// Interleaved channel count of the output buffer (1 = mono, 2 = stereo).
+    pub output_channels: usize,
+    /// Restart from the beginning at the end of the file; otherwise output silence.
+    pub looping: bool,
+    /// Source frames advanced per output frame (1.0 = normal speed). Negative values are
+    /// treated as 0.
+    pub playback_rate: f64,
+    /// Read position in (fractional) frames.
+    position: f64,
+}
+
+impl FilePlayer {
+    /// Creates a player for `samples` (interleaved, `channels` wide) writing mono output.
+    pub fn new(samples: Arc<Vec<f32>>, channels: usize) -> Self {
+        FilePlayer {
+            samples,
+            channels: channels.max(1),
+            output_channels: 1,
+            looping: false,
+            playback_rate: 1.0,
+            position: 0.0,
+        }
+    }
+
+    /// Channel count of the loaded file.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Length of the file in frames.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// Current read position in whole frames.
+    pub fn position(&self) -> usize {
+        self.position as usize
+    }
+
+    /// Moves the read position to `frame` (clamped to the end of the file).
+    pub fn seek(&mut self, frame: usize) {
+        self.position = frame.min(self.frames()) as f64;
+    }
+
+    /// True once a non-looping player has reached the end.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.position >= self.frames() as f64
+    }
+
+    /// File channel `c` at fractional frame `pos` (< frames), interpolating toward the next frame.
+    fn sample_at(&self, pos: f64, c: usize) -> f32 {
+        let total = self.frames();
+        let i = pos as usize;
+        let frac = (pos - i as f64) as f32;
+        let a = self.samples[i * self.channels + c];
+        if frac == 0.0 {
+            return a;
+        }
+        let next = if i + 1 < total {
+            i + 1
+        } else if self.looping {
+            0
+        } else {
+            i
+        };
+        let b = self.samples[next * self.channels + c];
+        a + frac * (b - a)
+    }
+
+    /// Writes the file at the current position into one output frame.
+    fn write_frame(&self, out: &mut [f32]) {
+        if out.len() == 1 {
+            let sum: f32 = (0..self.channels)
+                .map(|c| self.sample_at(self.position, c))
+                .sum();
+            out[0] = sum / self.channels as f32;
+        } else {
+            for (c, o) in out.iter_mut().enumerate() {
+                *o = self.sample_at(self.position, c % self.channels);
+            }
+        }
+    }
+}
+
+impl Processor for FilePlayer {
+    fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+        let out_ch = self.output_channels.max(1);
+        let total = self.frames() as f64;
+        let rate = self.playback_rate.max(0.0);
+        let n = output.len() / out_ch * out_ch;
+        for out in output[..n].chunks_exact_mut(out_ch) {
+            if self.looping && total > 0.0 && self.position >= total {
+                self.position %= total;
+            }
+            if self.position < total {
+                self.write_frame(out);
+                self.position += rate;
+            } else {
+                out.fill(0.0);
+            }
+        }
+        output[n..].fill(0.0);
+    }
 }
 
 /// Source node that reads from a shared buffer (ring buffer for live input, or in-memory file for playback).
@@ -501,6 +1835,107 @@ impl Processor for RecordNode {
     }
 }
 
+/// Pass-through node that publishes the level of each block without changing the audio.
+/// Peak and RMS are stored as f32 bits in shared atomics; the control thread reads them
+/// lock-free with `f32::from_bits(handle.load(Ordering::Relaxed))`.
+#[derive(Clone)]
+pub struct MeterTap {
+    pub peak: Arc<AtomicU32>,
+    pub rms: Arc<AtomicU32>,
+}
+
+impl MeterTap {
+    pub fn new(peak: Arc<AtomicU32>, rms: Arc<AtomicU32>) -> Self {
+        Self { peak, rms }
+    }
+
+    /// Peak of the last processed block.
+    pub fn peak(&self) -> f32 {
+        f32::from_bits(self.peak.load(Ordering::Relaxed))
+    }
+
+    /// RMS of the last processed block.
+    pub fn rms(&self) -> f32 {
+        f32::from_bits(self.rms.load(Ordering::Relaxed))
+    }
+}
+
+impl std::fmt::Debug for MeterTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MeterTap(..)")
+    }
+}
+
+impl PartialEq for MeterTap {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.peak, &other.peak) && Arc::ptr_eq(&self.rms, &other.rms)
+    }
+}
+
+impl Processor for MeterTap {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let n = output.len();
+        let Some(inp) = inputs.first() else {
+            output.fill(0.0);
+            return;
+        };
+        let copy_len = n.min(inp.len());
+        output[..copy_len].copy_from_slice(&inp[..copy_len]);
+        output[copy_len..].fill(0.0);
+        let block = &output[..copy_len];
+        let peak = block.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        let rms = if block.is_empty() {
+            0.0
+        } else {
+            (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt()
+        };
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Pass-through node that copies each block into a shared [`ScopeRing`] for a waveform display;
+/// the UI thread drains it with `try_recv`. Never blocks: when the ring is full the oldest
+/// samples are overwritten, so a slow UI always finds the latest audio.
+#[derive(Clone)]
+pub struct ScopeTap {
+    pub ring: Arc<ScopeRing>,
+}
+
+impl ScopeTap {
+    pub fn new(ring: Arc<ScopeRing>) -> Self {
+        Self { ring }
+    }
+}
+
+impl std::fmt::Debug for ScopeTap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ScopeTap(..)")
+    }
+}
+
+impl PartialEq for ScopeTap {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.ring, &other.ring)
+    }
+}
+
+impl Processor for ScopeTap {
+    fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]) {
+        let n = output.len();
+        let Some(inp) = inputs.first() else {
+            output.fill(0.0);
+            return;
+        };
+        let copy_len = n.min(inp.len());
+        output[..copy_len].copy_from_slice(&inp[..copy_len]);
+        output[copy_len..].fill(0.0);
+        for &s in &output[..copy_len] {
+            self.ring.push(s);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{GainProcessor, Mixer, SineGenerator};
@@ -537,6 +1972,23 @@ mod tests {
         assert_ne!(buffer.as_slice(), buffer2.as_slice());
     }
 
+    #[test]
+    fn test_quadrature_channels_are_90_degrees_apart() {
+        use super::QuadratureOsc;
+        // 480 Hz at 48 kHz: exactly 100 frames per period.
+        let mut osc = QuadratureOsc::new(480.0, 48_000);
+        let mut out = vec![0.0f32; 200];
+        osc.process(&[], &mut out);
+        let left: Vec<f32> = out.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = out.iter().skip(1).step_by(2).copied().collect();
+        let dot: f32 = left.iter().zip(&right).map(|(l, r)| l * r).sum();
+        assert!(dot.abs() < 1e-3, "orthogonal over a period, got {dot}");
+        assert_eq!(left[0], 0.0);
+        assert_eq!(right[0], 1.0);
+        // Right leads left by a quarter period (25 frames).
+        assert!((right[0] - left[25]).abs() < 1e-5);
+    }
+
     #[test]
     fn test_gain_processor_scales_output() {
         let mut gain_processor = GainProcessor::new(0.5);
@@ -573,6 +2025,267 @@ mod tests {
         assert!(out.as_slice().iter().all(|&x| (x - 1.0).abs() < 1e-5));
     }
 
+    #[test]
+    fn test_mixer_average_mode_keeps_single_input_level() {
+        use super::MixMode;
+        let mut sine = SineGenerator::new(440.0, 48_000);
+        let mut tone = vec![0.0f32; 512];
+        sine.process(&[], &mut tone);
+        let single_peak = tone.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        let inputs = [tone.as_slice(); 4];
+        let mut out = vec![0.0f32; 512];
+
+        let mut mixer = Mixer::new(vec![1.0; 4]);
+        mixer.process(&inputs, &mut out);
+        let summed = out.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!(
+            (summed - 4.0 * single_peak).abs() < 1e-4,
+            "Sum is the default"
+        );
+
+        mixer.mode = MixMode::Average;
+        mixer.process(&inputs, &mut out);
+        let averaged = out.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!((averaged - single_peak).abs() < 1e-5);
+
+        // Zero-gain inputs don't count as active.
+        mixer.gains = vec![1.0, 1.0, 0.0, 0.0];
+        mixer.process(&inputs, &mut out);
+        let two_active = out.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!((two_active - single_peak).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_mixer_tracks_per_input_peaks() {
+        let loud = [0.9f32, -1.2, 0.3, 0.0];
+        let quiet = [0.05f32, -0.02, 0.01, 0.0];
+        // The loud input is turned way down; its peak still reads hot.
+        let mut mixer = Mixer::new(vec![0.1, 1.0]);
+        assert_eq!(mixer.input_peaks(), &[0.0, 0.0]);
+        let mut out = [0.0f32; 4];
+        mixer.process(&[&loud, &quiet], &mut out);
+        assert_eq!(mixer.input_peaks(), &[1.2, 0.05]);
+        // Metering is not a setting: a fresh mixer with the same gains is still equal.
+        assert_eq!(mixer, Mixer::new(vec![0.1, 1.0]));
+
+        // A gain added after construction gets its own peak slot.
+        mixer.gains.push(1.0);
+        mixer.process(&[&loud, &quiet, &quiet], &mut out);
+        assert_eq!(mixer.input_peaks(), &[1.2, 0.05, 0.05]);
+    }
+
+    #[test]
+    fn test_mixer_gains_and_inputs_may_differ_in_length() {
+        let a = [0.5f32; 4];
+        let b = [0.25f32; 4];
+        let mut out = [0.0f32; 4];
+        // More inputs than gains: the extra input is silent but still metered.
+        let mut mixer = Mixer::new(vec![1.0]);
+        mixer.process(&[&a, &b], &mut out);
+        assert_eq!(out, [0.5; 4]);
+        assert_eq!(mixer.input_peaks(), &[0.5]);
+        mixer.gains.push(1.0);
+        assert_eq!(mixer.input_peaks(), &[0.5, 0.25]);
+
+        // More gains than inputs: the missing inputs read as silence.
+        mixer.gains = vec![1.0, 1.0, 1.0];
+        mixer.process(&[&a], &mut out);
+        assert_eq!(out, [0.5; 4]);
+        assert_eq!(mixer.input_peaks(), &[0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_auto_panner_sweeps_smoothly_with_constant_power() {
+        use super::AutoPanner;
+        // 1 Hz at 1 kHz: one LFO cycle is 1000 frames.
+        let mut pan = AutoPanner::new(1.0, 1000);
+        let input = vec![1.0f32; 1000];
+        let mut out = vec![0.0f32; 2000];
+        pan.process(&[&input], &mut out);
+        let balance: Vec<f32> = out.chunks(2).map(|f| f[1] * f[1] - f[0] * f[0]).collect();
+        for f in out.chunks(2) {
+            assert!((f[0] * f[0] + f[1] * f[1] - 1.0).abs() < 1e-4);
+        }
+        for w in balance.windows(2) {
+            assert!((w[1] - w[0]).abs() < 0.01, "jump in pan position");
+        }
+        // Centered, hard right at a quarter cycle, centered, hard left at three quarters.
+        assert!(balance[0].abs() < 1e-4);
+        assert!(balance[250] > 0.999);
+        assert!(balance[500].abs() < 0.01);
+        assert!(balance[750] < -0.999);
+    }
+
+    #[test]
+    fn test_auto_panner_zero_depth_stays_centered() {
+        use super::AutoPanner;
+        let mut pan = AutoPanner::new(3.0, 1000);
+        pan.depth = 0.0;
+        let input = vec![0.5f32; 500];
+        let mut out = vec![0.0f32; 1000];
+        pan.process(&[&input], &mut out);
+        for f in out.chunks(2) {
+            assert_eq!(f[0], f[1]);
+            assert!((f[0] - 0.5 * std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_mono_sum_spreads_left_only_signal_at_half_level() {
+        use super::MonoSum;
+        let input = [0.8f32, 0.0, -0.4, 0.0, 0.2, 0.0];
+        let mut out = [0.0f32; 6];
+        MonoSum::new().process(&[&input], &mut out);
+        assert_eq!(out, [0.4, 0.4, -0.2, -0.2, 0.1, 0.1]);
+    }
+
+    #[test]
+    fn test_interleave_and_deinterleave_round_trip() {
+        use super::{DeInterleave, Interleave};
+        let left = [0.0f32, 0.1, 0.2, 0.3];
+        let right = [1.0f32, 0.9, 0.8, 0.7];
+        let mut stereo = [0.0f32; 8];
+        Interleave::new().process(&[&left, &right], &mut stereo);
+        assert_eq!(stereo, [0.0, 1.0, 0.1, 0.9, 0.2, 0.8, 0.3, 0.7]);
+
+        let mut l = [9.0f32; 8];
+        let mut r = [9.0f32; 8];
+        DeInterleave::new(0).process(&[&stereo], &mut l);
+        DeInterleave::new(1).process(&[&stereo], &mut r);
+        assert_eq!(l[..4], left);
+        assert_eq!(r[..4], right);
+        assert!(l[4..].iter().chain(&r[4..]).all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_gain_matches_scalar_for_all_lengths() {
+        // With the `simd` feature this compares the vector path against plain multiplies,
+        // including lengths that leave a remainder after the 8-lane chunks.
+        for len in 0..40 {
+            let input: Vec<f32> = (0..len).map(|i| (i as f32 * 0.37).sin()).collect();
+            let expected: Vec<f32> = input.iter().map(|x| x * 0.3).collect();
+            let mut out = vec![0.0f32; len];
+            GainProcessor::new(0.3).process(&[&input], &mut out);
+            assert_eq!(out, expected, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_mixer_two_input_fast_path_matches_general() {
+        use super::{mix_two, MixMode};
+        let a: Vec<f32> = (0..64).map(|i| (i as f32 * 0.1).sin()).collect();
+        let b: Vec<f32> = (0..64).map(|i| (i as f32 * 0.07).cos()).collect();
+        for mode in [MixMode::Sum, MixMode::Average] {
+            let mut mixer = Mixer::new(vec![0.7, 0.4]);
+            mixer.mode = mode;
+            let mut fast = vec![0.0f32; 64];
+            mixer.process(&[&a, &b], &mut fast);
+            let scale = if mode == MixMode::Sum { 1.0 } else { 0.5 };
+            let mut general = vec![0.0f32; 64];
+            mixer.mix_general(&[&a, &b], scale, &mut general);
+            assert_eq!(fast, general);
+            let mut direct = vec![0.0f32; 64];
+            mix_two(&a, &b, 0.7, 0.4, scale, &mut direct);
+            assert_eq!(direct, general);
+        }
+    }
+
+    #[test]
+    fn test_sine_set_frequency_updates_increment_and_pitch() {
+        let mut sine = SineGenerator::new(1000.0, 48_000);
+        assert_eq!(sine.phase_increment(), 1000.0 / 48_000.0);
+        sine.set_frequency(2000.0);
+        assert_eq!(sine.phase_increment(), 2000.0 / 48_000.0);
+        sine.set_sample_rate(96_000);
+        assert_eq!(sine.phase_increment(), 2000.0 / 96_000.0);
+        assert_eq!(sine.frequency_hz, 2000.0);
+
+        // 4 kHz at 48 kHz: one cycle per 12 samples, so 4.5 cycles (4 rising zero crossings
+        // after the start) in 54 samples.
+        sine.set_sample_rate(48_000);
+        sine.set_frequency(4000.0);
+        let mut out = vec![0.0f32; 54];
+        sine.process(&[], &mut out);
+        let rising = out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert_eq!(rising, 4);
+
+        // Writing the public field directly works too, from the next block on.
+        sine.frequency_hz = 2000.0;
+        sine.phase = 0.0;
+        sine.process(&[], &mut out);
+        let rising = out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert_eq!(rising, 2);
+    }
+
+    #[test]
+    fn test_sine_direct_frequency_write_is_picked_up_by_process() {
+        let mut sine = SineGenerator::new(1000.0, 48_000);
+        sine.frequency_hz = 4000.0;
+        // The cache is refreshed in `process`, not on the write.
+        assert_eq!(sine.phase_increment(), 1000.0 / 48_000.0);
+        let mut out = vec![0.0f32; 54];
+        sine.process(&[], &mut out);
+        assert_eq!(sine.phase_increment(), 4000.0 / 48_000.0);
+        // 4 kHz at 48 kHz: 4.5 cycles in 54 samples, so 4 rising zero crossings.
+        let rising = out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert_eq!(rising, 4);
+
+        sine.sample_rate = 96_000;
+        sine.phase = 0.0;
+        sine.process(&[], &mut out);
+        assert_eq!(sine.phase_increment(), 4000.0 / 96_000.0);
+        let rising = out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert_eq!(rising, 2);
+    }
+
+    #[test]
+    fn test_polynomial_sine_tracks_exact_within_bound() {
+        use super::SineApprox;
+        // 1 Hz at 10 kHz: one full period sampled finely.
+        let mut exact = SineGenerator::new(1.0, 10_000);
+        let mut approx = SineGenerator::new(1.0, 10_000);
+        approx.approx = SineApprox::Polynomial;
+        let mut a = vec![0.0f32; 10_000];
+        let mut b = vec![0.0f32; 10_000];
+        exact.process(&[], &mut a);
+        approx.process(&[], &mut b);
+        for (x, y) in a.iter().zip(&b) {
+            assert!((x - y).abs() < 1e-3, "{x} vs {y}");
+            assert!((-1.0..=1.0).contains(y));
+        }
+    }
+
+    #[test]
+    fn test_gain_unity_and_zero_fast_paths() {
+        let input = [0.1f32, -0.7, f32::MIN_POSITIVE, 1.5];
+        let mut out = [9.0f32; 4];
+        GainProcessor::new(1.0).process(&[&input], &mut out);
+        assert_eq!(out, input);
+
+        let mut out = [9.0f32; 4];
+        GainProcessor::new(0.0).process(&[&[f32::NAN, 1.0, -1.0, 0.5]], &mut out);
+        assert!(out.iter().all(|&s| s == 0.0), "exact zeros, even from NaN");
+
+        let mut out = [0.0f32; 4];
+        GainProcessor::new(0.5).process(&[&input], &mut out);
+        assert_eq!(out, input.map(|x| x * 0.5));
+    }
+
+    #[test]
+    fn test_balance_scales_channels_independently() {
+        use super::Balance;
+        let input = [1.0f32; 8];
+        let mut out = [0.0f32; 8];
+        Balance::new(0.25, 0.75).process(&[&input], &mut out);
+        assert_eq!(out, [0.25, 0.75, 0.25, 0.75, 0.25, 0.75, 0.25, 0.75]);
+
+        // Mono: left_gain applies to every sample.
+        let mut mono = Balance::new(0.25, 0.75);
+        mono.channels = 1;
+        mono.process(&[&input], &mut out);
+        assert!(out.iter().all(|&x| x == 0.25));
+    }
+
     #[test]
     fn test_delay_line_impulse() {
         use super::DelayLine;
@@ -630,4 +2343,488 @@ mod tests {
             "highpass should attenuate DC relative to input"
         );
     }
+
+    #[test]
+    fn test_biquad_recovers_from_nan_state() {
+        use super::BiquadFilter;
+        let mut lp = BiquadFilter::lowpass(48_000, 1000.0, 0.707);
+        lp.y1 = f32::NAN;
+        let input = vec![0.5f32; 64];
+        let mut output = vec![0.0f32; 64];
+        lp.process(&[&input[..]], &mut output[..]);
+        assert!(
+            output.iter().all(|s| s.is_finite()),
+            "NaN in state should be reset, not propagated"
+        );
+        lp.process(&[&input[..]], &mut output[..]);
+        assert!(output.iter().all(|s| s.is_finite()));
+        assert!(output[63] > 0.0, "filter keeps working after recovery");
+    }
+
+    #[test]
+    fn test_flush_denormals() {
+        use super::flush_denormals;
+        assert_eq!(flush_denormals(1e-30), 0.0);
+        assert_eq!(flush_denormals(-1e-38), 0.0);
+        assert_eq!(flush_denormals(0.25), 0.25);
+    }
+
+    #[test]
+    fn test_metronome_clicks_every_beat_at_120_bpm() {
+        use super::Metronome;
+        let mut metro = Metronome::new(120.0, 48_000);
+        let mut output = vec![0.0f32; 24_000 * 4];
+        for block in output.chunks_mut(512) {
+            metro.process(&[], block);
+        }
+        // A click onset is a non-zero sample preceded by a long silent stretch.
+        let onsets: Vec<usize> = (0..output.len())
+            .filter(|&i| {
+                output[i] != 0.0 && output[i.saturating_sub(100)..i].iter().all(|&s| s == 0.0)
+            })
+            .collect();
+        // The envelope starts at zero, so the first audible sample is one after the beat start.
+        assert_eq!(onsets, vec![1, 24_001, 48_001, 72_001]);
+        assert!(output.iter().all(|s| s.abs() <= 1.0));
+        let click_end = (0.030 * 48_000.0) as usize;
+        assert!(
+            output[click_end - 1].abs() < 1e-3,
+            "click tapers to zero, no pop"
+        );
+    }
+
+    #[test]
+    fn test_metronome_bpm_change_keeps_scheduled_beat() {
+        use super::Metronome;
+        let mut metro = Metronome::new(120.0, 48_000);
+        let mut output = vec![0.0f32; 24_000 * 4];
+        let (first, rest) = output.split_at_mut(12_000);
+        metro.process(&[], first);
+        // Halfway to beat 1 the tempo doubles: beat 1 stays at 24 000, then every 12 000.
+        metro.bpm = 240.0;
+        for block in rest.chunks_mut(512) {
+            metro.process(&[], block);
+        }
+        let onsets: Vec<usize> = (0..output.len())
+            .filter(|&i| {
+                output[i] != 0.0 && output[i.saturating_sub(100)..i].iter().all(|&s| s == 0.0)
+            })
+            .collect();
+        assert_eq!(
+            onsets,
+            vec![1, 24_001, 36_001, 48_001, 60_001, 72_001, 84_001]
+        );
+    }
+
+    #[test]
+    fn test_metronome_fractional_beat_length_does_not_drift() {
+        use super::Metronome;
+        // 130 bpm at 44.1 kHz is 20 353.85 samples per beat.
+        let mut metro = Metronome::new(130.0, 44_100);
+        let beats = 40;
+        let mut output = vec![0.0f32; 20_353 * beats];
+        for block in output.chunks_mut(441) {
+            metro.process(&[], block);
+        }
+        let onsets: Vec<usize> = (0..output.len())
+            .filter(|&i| {
+                output[i] != 0.0 && output[i.saturating_sub(100)..i].iter().all(|&s| s == 0.0)
+            })
+            .collect();
+        let expected: Vec<usize> = (0..beats)
+            .map(|k| (k as f64 * 60.0 * 44_100.0 / 130.0).round() as usize + 1)
+            .collect();
+        assert_eq!(onsets, expected);
+    }
+
+    #[test]
+    fn test_metronome_accents_first_beat_of_bar() {
+        use super::Metronome;
+        let mut metro = Metronome::new(120.0, 48_000);
+        metro.beats_per_bar = 2;
+        let mut output = vec![0.0f32; 24_000 * 2];
+        metro.process(&[], &mut output);
+        let peak = |s: &[f32]| s.iter().map(|x| x.abs()).fold(0.0f32, f32::max);
+        assert!(peak(&output[..24_000]) > peak(&output[24_000..]) * 1.2);
+    }
+
+    #[test]
+    fn test_noise_kinds_bounded_with_distinct_slopes() {
+        use super::{Noise, NoiseKind};
+        use crate::analysis::band_energy;
+        let slope_db = |kind| {
+            let mut noise = Noise::with_seed(kind, 7);
+            let mut out = vec![0.0f32; 16_384];
+            noise.process(&[], &mut out);
+            assert!(out.iter().all(|s| (-1.0..=1.0).contains(s)));
+            assert!(out.iter().any(|&s| s != 0.0));
+            // High band is four octaves above the low band.
+            // Energy per Hz, so white noise compares equal across bands of different widths.
+            let low = band_energy(&out, 48_000, 300.0, 600.0) / 300.0;
+            let high = band_energy(&out, 48_000, 4_800.0, 9_600.0) / 4_800.0;
+            10.0 * (high / low).log10()
+        };
+        let white = slope_db(NoiseKind::White);
+        let pink = slope_db(NoiseKind::Pink);
+        let brown = slope_db(NoiseKind::Brown);
+        assert!(white.abs() < 3.0, "white is flat, got {white} dB");
+        assert!(
+            (-16.0..-8.0).contains(&pink),
+            "pink ~-12 dB over 4 oct, got {pink}"
+        );
+        assert!(
+            brown < -18.0 && brown < pink - 6.0,
+            "brown ~-24 dB, got {brown}"
+        );
+    }
+
+    #[test]
+    fn test_noise_seed_is_deterministic() {
+        use super::{Noise, NoiseKind};
+        let render = |seed| {
+            let mut noise = Noise::with_seed(NoiseKind::Pink, seed);
+            let mut out = vec![0.0f32; 256];
+            noise.process(&[], &mut out);
+            out
+        };
+        assert_eq!(render(42), render(42));
+        assert_ne!(render(42), render(43));
+    }
+
+    #[test]
+    fn test_delay_note_division_to_samples() {
+        use super::{DelayLine, NoteDivision};
+        let mut delay = DelayLine::new(2000.0, 48_000);
+        delay.set_delay_note(NoteDivision::Quarter, 120.0);
+        assert_eq!(delay.delay_samples(), 24_000);
+        delay.set_delay_note(NoteDivision::Eighth, 120.0);
+        assert_eq!(delay.delay_samples(), 12_000);
+        delay.set_delay_note(NoteDivision::DottedEighth, 120.0);
+        assert_eq!(delay.delay_samples(), 18_000);
+        // A whole note (2 s) at 60 BPM is 4 s: clamped to the 2 s buffer.
+        delay.set_delay_note(NoteDivision::Whole, 60.0);
+        assert_eq!(delay.delay_samples(), 96_000);
+
+        // The line's own rate sets the length: a quarter at 120 BPM is 0.5 s at 44.1 kHz too.
+        let mut delay = DelayLine::new(2000.0, 44_100);
+        delay.set_delay_note(NoteDivision::Quarter, 120.0);
+        assert_eq!(delay.delay_samples(), 22_050);
+    }
+
+    #[test]
+    fn test_ping_pong_alternates_channels() {
+        use super::PingPongDelay;
+        // 1 kHz sample rate so 10 ms = 10 frames.
+        let mut pp = PingPongDelay::new(100.0, 1000);
+        pp.set_time_ms(10.0);
+        pp.feedback = 0.5;
+        pp.mix = 1.0;
+        let mut input = vec![0.0f32; 128];
+        input[0] = 1.0; // impulse in the left channel
+        let mut out = vec![0.0f32; 128];
+        pp.process(&[&input], &mut out);
+        // Bounce k (1, 2, ...) lands at frame 10k: odd bounces left, even right, halving each time.
+        for frame in 0..64 {
+            for ch in 0..2 {
+                let bounce = frame / 10;
+                let want = if frame % 10 == 0 && bounce > 0 && (bounce + 1) % 2 == ch {
+                    0.5f32.powi(bounce as i32 - 1)
+                } else {
+                    0.0
+                };
+                let got = out[frame * 2 + ch];
+                assert!((got - want).abs() < 1e-6, "frame {frame} ch {ch}: {got}");
+            }
+        }
+    }
+
+    /// Rising zero-crossing periods (in samples, interpolated) of the left channel.
+    fn left_periods(stereo: &[f32]) -> Vec<f32> {
+        let left: Vec<f32> = stereo.iter().step_by(2).copied().collect();
+        let crossings: Vec<f32> = (1..left.len())
+            .filter(|&i| left[i - 1] < 0.0 && left[i] >= 0.0)
+            .map(|i| (i - 1) as f32 + left[i - 1] / (left[i - 1] - left[i]))
+            .collect();
+        crossings.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    #[test]
+    fn test_chorus_dry_at_zero_mix_and_wobbles_when_modulated() {
+        use super::Chorus;
+        let sr = 48_000;
+        let tone: Vec<f32> = (0..sr as usize)
+            .flat_map(|i| {
+                let s = f32::sin(2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sr as f32);
+                [s, s]
+            })
+            .collect();
+        let mut out = vec![0.0f32; tone.len()];
+
+        let mut chorus = Chorus::new(sr);
+        chorus.mix = 0.0;
+        chorus.process(&[&tone], &mut out);
+        assert_eq!(out, tone);
+
+        // Skip the first 50 ms while the delay line fills.
+        let skip = 2 * sr as usize / 20;
+        let spread = |depth_ms| {
+            let mut chorus = Chorus::new(sr);
+            chorus.mix = 1.0;
+            chorus.rate_hz = 2.0;
+            chorus.depth_ms = depth_ms;
+            let mut out = vec![0.0f32; tone.len()];
+            chorus.process(&[&tone], &mut out);
+            let periods = left_periods(&out[skip..]);
+            let max = periods.iter().cloned().fold(f32::MIN, f32::max);
+            let min = periods.iter().cloned().fold(f32::MAX, f32::min);
+            (max - min) / 48.0
+        };
+        // Static delay: steady 48-sample period. Swept delay: pitch moves by several percent.
+        assert!(spread(0.0) < 0.005, "unmodulated spread {}", spread(0.0));
+        assert!(spread(5.0) > 0.05, "modulated spread {}", spread(5.0));
+    }
+
+    #[test]
+    fn test_sample_hold_steps_at_clock_rate() {
+        use super::SampleHold;
+        // 1 kHz clock at 48 kHz: a new sample every 48 frames.
+        let mut sh = SampleHold::new(1000.0, 48_000);
+        let ramp: Vec<f32> = (0..480).map(|i| i as f32).collect();
+        let mut out = vec![0.0f32; 480];
+        // Two blocks: the clock must carry across block boundaries.
+        sh.process(&[&ramp[..200]], &mut out[..200]);
+        let second: Vec<f32> = ramp[200..].to_vec();
+        sh.process(&[&second], &mut out[200..]);
+        for (i, &v) in out.iter().enumerate() {
+            assert_eq!(v, (i / 48 * 48) as f32, "frame {i}");
+        }
+    }
+
+    #[test]
+    fn test_slew_tracks_steps_at_configured_rates() {
+        use super::Slew;
+        // 10 kHz: rise 1000/s = 0.1 per sample, fall 500/s = 0.05 per sample.
+        let mut slew = Slew::new(1000.0, 500.0, 10_000);
+        let mut input = vec![1.0f32; 40];
+        input[20..].fill(0.0);
+        let mut out = vec![0.0f32; 40];
+        slew.process(&[&input], &mut out);
+        for (i, &v) in out[..20].iter().enumerate() {
+            let want = (0.1 * (i + 1) as f32).min(1.0);
+            assert!((v - want).abs() < 1e-5, "rise frame {i}: {v}");
+        }
+        for (i, &v) in out[20..].iter().enumerate() {
+            let want = (1.0 - 0.05 * (i + 1) as f32).max(0.0);
+            assert!((v - want).abs() < 1e-5, "fall frame {i}: {v}");
+        }
+    }
+
+    #[test]
+    fn test_auto_gain_levels_toward_target() {
+        use super::AutoGain;
+        let rms = |x: &[f32]| (x.iter().map(|s| s * s).sum::<f32>() / x.len() as f32).sqrt();
+        let run = |agc: &mut AutoGain, amplitude: f32| {
+            let mut sine = SineGenerator::new(1000.0, 48_000);
+            let mut input = vec![0.0f32; 480];
+            let mut out = vec![0.0f32; 480];
+            for _ in 0..400 {
+                sine.process(&[], &mut input);
+                input.iter_mut().for_each(|s| *s *= amplitude);
+                agc.process(&[&input], &mut out);
+            }
+            rms(&out)
+        };
+
+        // Quiet (RMS ~0.035) is boosted, loud (RMS ~0.7) is attenuated, both to ~0.2.
+        let quiet = run(&mut AutoGain::new(0.2, 48_000), 0.05);
+        assert!((quiet - 0.2).abs() < 0.01, "quiet input leveled to {quiet}");
+        let loud = run(&mut AutoGain::new(0.2, 48_000), 1.0);
+        assert!((loud - 0.2).abs() < 0.01, "loud input leveled to {loud}");
+
+        // Very quiet input would need gain ~280; it stops at max_gain.
+        let mut capped = AutoGain::new(0.2, 48_000);
+        capped.max_gain = 4.0;
+        let out = run(&mut capped, 0.001);
+        assert!(capped.gain() <= 4.0);
+        assert!(out <= 0.001 * std::f32::consts::FRAC_1_SQRT_2 * 4.0 + 1e-6);
+    }
+
+    #[test]
+    fn test_limiter_holds_ceiling_and_recovers() {
+        use super::Limiter;
+        let mut limiter = Limiter::new(1.0, 50.0, 48_000);
+        let mut sine = SineGenerator::new(440.0, 48_000);
+        let mut input = vec![0.0f32; 4800];
+        sine.process(&[], &mut input);
+        input.iter_mut().for_each(|s| *s *= 2.0);
+        let mut out = vec![0.0f32; 4800];
+        limiter.process(&[&input], &mut out);
+        assert!(out.iter().all(|s| s.abs() <= 1.0));
+        assert!(
+            out.iter().any(|s| s.abs() > 0.99),
+            "limits to, not below, the ceiling"
+        );
+
+        // Quiet material after the burst: gain climbs back toward unity.
+        let quiet = vec![0.1f32; 48_000];
+        let mut out = vec![0.0f32; 48_000];
+        limiter.process(&[&quiet], &mut out);
+        assert!(limiter.gain() > 0.99);
+    }
+
+    #[test]
+    fn test_file_player_plays_stereo_wav_by_frames() {
+        use super::FilePlayer;
+        use crate::file_feeder::load_wav_interleaved_at_rate;
+        use std::sync::Arc;
+
+        let path = std::env::temp_dir().join(format!("capstan_stereo_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        // Left counts up, right is constant, so the channels are distinguishable.
+        for i in 0..8 {
+            writer.write_sample(i as f32 * 0.1).unwrap();
+            writer.write_sample(-0.5f32).unwrap();
+        }
+        writer.finalize().unwrap();
+        let (samples, channels) = load_wav_interleaved_at_rate(&path, 48_000).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(channels, 2);
+
+        let mut player = FilePlayer::new(Arc::new(samples), channels);
+        player.output_channels = 2;
+        assert_eq!(player.frames(), 8);
+        let mut out = [0.0f32; 8];
+        player.process(&[], &mut out);
+        assert_eq!(player.position(), 4, "advances by frames");
+        for (frame, lr) in out.chunks(2).enumerate() {
+            assert!((lr[0] - frame as f32 * 0.1).abs() < 1e-6);
+            assert_eq!(lr[1], -0.5);
+        }
+
+        // Mono output: channels are averaged.
+        player.output_channels = 1;
+        let mut mono = [0.0f32; 6];
+        player.process(&[], &mut mono);
+        assert!((mono[0] - (0.4 - 0.5) / 2.0).abs() < 1e-6);
+        assert!(player.is_finished());
+        assert_eq!(&mono[4..], &[0.0, 0.0], "silence after the end");
+    }
+
+    #[test]
+    fn test_file_player_seek_and_rate() {
+        use super::FilePlayer;
+        use std::sync::Arc;
+
+        let ramp: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let mut player = FilePlayer::new(Arc::new(ramp), 1);
+        player.seek(40);
+        let mut out = [0.0f32; 4];
+        player.process(&[], &mut out);
+        assert_eq!(out, [40.0, 41.0, 42.0, 43.0]);
+
+        // Half speed: interpolated in-between frames, and twice the output before the end.
+        let count_until_end = |player: &mut FilePlayer| {
+            let mut frames = 0;
+            let mut one = [0.0f32; 1];
+            while !player.is_finished() {
+                player.process(&[], &mut one);
+                frames += 1;
+            }
+            frames
+        };
+        player.seek(0);
+        let normal = count_until_end(&mut player);
+        player.seek(0);
+        player.playback_rate = 0.5;
+        player.process(&[], &mut out);
+        assert_eq!(out, [0.0, 0.5, 1.0, 1.5]);
+        player.seek(0);
+        let slow = count_until_end(&mut player);
+        assert_eq!(normal, 100);
+        assert_eq!(slow, 2 * normal);
+    }
+
+    #[test]
+    fn test_overdrive_oversampling_reduces_aliasing() {
+        use super::Overdrive;
+        use crate::analysis::band_energy;
+        let sr = 48_000;
+        let input: Vec<f32> = (0..16_384)
+            .map(|i| 0.8 * f32::sin(2.0 * std::f32::consts::PI * 5_000.0 * i as f32 / sr as f32))
+            .collect();
+        // Energy below the 5 kHz fundamental can only be aliased harmonics.
+        let alias_ratio = |factor| {
+            let mut od = Overdrive::new(2.0);
+            od.set_oversample(factor);
+            let mut out = vec![0.0f32; input.len()];
+            od.process(&[&input], &mut out);
+            let steady = &out[256..];
+            band_energy(steady, sr, 20.0, 4_500.0) / band_energy(steady, sr, 0.0, 24_001.0)
+        };
+        let plain = alias_ratio(1);
+        let oversampled = alias_ratio(4);
+        assert!(
+            oversampled < plain / 10.0,
+            "1x alias ratio {plain}, 4x {oversampled}"
+        );
+    }
+
+    #[test]
+    fn test_meter_tap_publishes_levels_and_passes_audio() {
+        use super::MeterTap;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        let peak = Arc::new(AtomicU32::new(0));
+        let rms = Arc::new(AtomicU32::new(0));
+        let mut tap = MeterTap::new(Arc::clone(&peak), Arc::clone(&rms));
+        // 480 samples = 10 whole cycles of 1 kHz at 48 kHz, amplitude 0.5.
+        let input: Vec<f32> = (0..480)
+            .map(|i| 0.5 * f32::sin(2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48_000.0))
+            .collect();
+        let mut out = vec![0.0f32; 480];
+        tap.process(&[&input], &mut out);
+        assert_eq!(out, input, "audio passes through unmodified");
+        let read = |a: &AtomicU32| f32::from_bits(a.load(Ordering::Relaxed));
+        assert!((read(&peak) - 0.5).abs() < 1e-3, "peak {}", read(&peak));
+        let want_rms = 0.5 / 2.0f32.sqrt();
+        assert!((read(&rms) - want_rms).abs() < 1e-3, "rms {}", read(&rms));
+    }
+
+    #[test]
+    fn test_scope_tap_keeps_the_newest_samples() {
+        use super::ScopeTap;
+        use crate::ring_buffer::ScopeRing;
+        use std::sync::Arc;
+
+        let ring = Arc::new(ScopeRing::new(64));
+        let mut scope = ScopeTap::new(Arc::clone(&ring));
+        let input: Vec<f32> = (0..100).map(|i| i as f32 / 100.0).collect();
+        let mut out = vec![0.0f32; 100];
+        scope.process(&[&input], &mut out);
+        assert_eq!(out, input, "audio passes through unmodified");
+        let captured: Vec<f32> = std::iter::from_fn(|| ring.try_recv()).collect();
+        assert_eq!(
+            captured,
+            out[36..].to_vec(),
+            "a full ring holds the newest samples that passed"
+        );
+
+        // A reader that skipped a few blocks still finds the latest audio, not stale samples.
+        scope.process(&[&input[..50]], &mut out[..50]);
+        scope.process(&[&input[50..]], &mut out[..50]);
+        let captured: Vec<f32> = std::iter::from_fn(|| ring.try_recv()).collect();
+        assert_eq!(captured, input[36..].to_vec());
+
+        // Once drained, the next block is captured whole.
+        scope.process(&[&input[..10]], &mut out[..10]);
+        let captured: Vec<f32> = std::iter::from_fn(|| ring.try_recv()).collect();
+        assert_eq!(captured, input[..10].to_vec());
+    }
 }
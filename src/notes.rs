@@ -0,0 +1,82 @@
+//! Note names: scientific pitch notation (`A4`, `C#3`, `Bb5`) to frequency, for CLIs that take a
+//! pitch either in Hz or by name.
+
+/// Frequency of A4, the tuning reference (Hz).
+pub const A4_HZ: f32 = 440.0;
+
+/// Parses a note in scientific pitch notation into its equal-tempered frequency (A4 = 440 Hz):
+/// a letter `A`–`G` (either case), an optional `#` or `b`, and an octave from -1 to 9.
+/// Returns `None` for anything else.
+pub fn parse_note(s: &str) -> Option<f32> {
+    let mut chars = s.chars();
+    let semitone: i32 = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = if let Some(octave) = rest.strip_prefix('#') {
+        (1, octave)
+    } else if let Some(octave) = rest.strip_prefix('b') {
+        (-1, octave)
+    } else {
+        (0, rest)
+    };
+    if octave.starts_with('+') {
+        return None;
+    }
+    let octave: i32 = octave.parse().ok().filter(|o| (-1..=9).contains(o))?;
+    let midi = (octave + 1) * 12 + semitone + accidental;
+    Some(A4_HZ * 2f32.powf((midi - 69) as f32 / 12.0))
+}
+
+/// Parses a frequency given in Hz (`440`, `261.6`) or as a note name (see [`parse_note`]).
+pub fn parse_frequency(s: &str) -> Option<f32> {
+    match s.parse::<f32>() {
+        Ok(hz) => hz.is_finite().then_some(hz),
+        Err(_) => parse_note(s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_frequency, parse_note};
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} vs {b}");
+    }
+
+    #[test]
+    fn test_parse_note_names() {
+        assert_eq!(parse_note("A4"), Some(440.0));
+        assert_close(parse_note("C4").unwrap(), 261.63);
+        assert_close(parse_note("A#3").unwrap(), 233.08);
+        assert_close(parse_note("Bb5").unwrap(), 932.33);
+        assert_close(parse_note("a5").unwrap(), 880.0);
+        assert_close(parse_note("C-1").unwrap(), 8.18);
+        assert_eq!(parse_note("Cb4"), parse_note("B3"));
+    }
+
+    #[test]
+    fn test_parse_note_rejects_invalid() {
+        for s in [
+            "", "A", "H4", "4", "A##4", "Ab", "A10", "A-2", "A+4", "A4 ", "440",
+        ] {
+            assert_eq!(parse_note(s), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_frequency_accepts_hz_or_note() {
+        assert_eq!(parse_frequency("440"), Some(440.0));
+        assert_eq!(parse_frequency("261.5"), Some(261.5));
+        assert_eq!(parse_frequency("A4"), Some(440.0));
+        assert_eq!(parse_frequency("inf"), None);
+        assert_eq!(parse_frequency("loud"), None);
+    }
+}
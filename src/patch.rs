@@ -0,0 +1,392 @@
+//! Graph patches: a serializable description of a mono [`AudioGraph`], e.g. for loading a graph
+//! from a JSON file (the daw's `--patch`). Needs the `serde` feature.
+//!
+//! A patch is an object with a `nodes` list and an `edges` list of `[from, to]` node indices:
+//!
+//! ```json
+//! { "nodes": [{ "kind": "sine", "frequency": 220 },
+//!             { "kind": "echo", "delay_ms": 300, "feedback": 0.4 },
+//!             { "kind": "gain", "gain": 0.5 }],
+//!   "edges": [[0, 1], [1, 2]] }
+//! ```
+//!
+//! Each node has a `kind` (see [`NodeSpec`]) and optional parameters named as in its variant;
+//! an omitted parameter keeps the node's constructor default, and any other key is an error.
+//! An optional `channels` sets [`AudioGraph::set_channels`] (default 1). As for any graph, the
+//! last node in topological order is the output.
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::{AudioGraph, GraphError, GraphNode, NodeId, ParamId};
+use crate::nodes::{
+    DelayLine, Echo, GainProcessor, Mixer, Noise, NoiseKind, Overdrive, SineGenerator, Tremolo,
+};
+
+/// Longest delay a patch's `delay` or `echo` node can be set to.
+pub const PATCH_MAX_DELAY_MS: f32 = 2000.0;
+
+/// A graph as plain data. Build one with [`Patch::from_graph`] or by deserializing, and turn it
+/// into a graph with [`Patch::to_graph`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Patch {
+    pub nodes: Vec<NodeSpec>,
+    /// `[from, to]` node indices: `from` feeds `to`.
+    pub edges: Vec<[usize; 2]>,
+    #[serde(default = "mono", skip_serializing_if = "is_mono")]
+    pub channels: usize,
+}
+
+/// One patch node. Serialized with its `kind` (the variant name in snake_case) alongside the
+/// parameters; `None` parameters are left at the node's default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NodeSpec {
+    Sine {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frequency: Option<f32>,
+    },
+    Noise {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<NoiseKind>,
+        /// Amplitude.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gain: Option<f32>,
+    },
+    Gain {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gain: Option<f32>,
+    },
+    Delay {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delay_ms: Option<f32>,
+    },
+    Echo {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delay_ms: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dry: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wet: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        feedback: Option<f32>,
+    },
+    Tremolo {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rate: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        depth: Option<f32>,
+    },
+    /// Oversampling is not part of the patch; the node runs at the default (off).
+    Overdrive {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        drive: Option<f32>,
+    },
+    /// One input per gain.
+    Mixer { gains: Vec<f32> },
+}
+
+/// Why a patch could not be made from a graph, or a graph from a patch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchError {
+    /// The graph has a node a patch can't describe, e.g. an input or a tap.
+    UnsupportedNode { index: usize, kind: &'static str },
+    /// The graph has an edge trimmed with [`AudioGraph::add_edge_with_gain`].
+    TrimmedEdge { from: usize, to: usize },
+    /// An edge names a node index past the end of `nodes`.
+    BadEdge { from: usize, to: usize },
+    /// Building the graph failed, e.g. too many nodes.
+    Graph(GraphError),
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::UnsupportedNode { index, kind } => {
+                write!(f, "node {} ({}) can't be written to a patch", index, kind)
+            }
+            PatchError::TrimmedEdge { from, to } => {
+                write!(
+                    f,
+                    "edge {} -> {} has a gain; patches don't store edge gains",
+                    from, to
+                )
+            }
+            PatchError::BadEdge { from, to } => {
+                write!(f, "edge [{}, {}] is not a pair of node indices", from, to)
+            }
+            PatchError::Graph(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Graph(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+fn mono() -> usize {
+    1
+}
+
+fn is_mono(channels: &usize) -> bool {
+    *channels == 1
+}
+
+impl Patch {
+    /// Describes `graph` as a patch, with every parameter written out. Fails on nodes other
+    /// than the [`NodeSpec`] kinds and on trimmed edges.
+    pub fn from_graph(graph: &AudioGraph) -> Result<Patch, PatchError> {
+        let mut nodes = Vec::with_capacity(graph.node_count());
+        let mut edges = Vec::new();
+        for index in 0..graph.node_count() {
+            let id = NodeId::new(index);
+            let node = graph.node(id);
+            nodes.push(match node {
+                GraphNode::Sine(s) => NodeSpec::Sine {
+                    frequency: Some(s.frequency_hz),
+                },
+                GraphNode::Noise(n) => NodeSpec::Noise {
+                    color: Some(n.kind),
+                    gain: Some(n.amplitude),
+                },
+                GraphNode::Gain(g) => NodeSpec::Gain { gain: Some(g.gain) },
+                GraphNode::Delay(d) => NodeSpec::Delay {
+                    delay_ms: Some(d.delay_ms),
+                },
+                GraphNode::Echo(e) => NodeSpec::Echo {
+                    delay_ms: Some(e.delay_ms()),
+                    dry: Some(e.dry),
+                    wet: Some(e.wet),
+                    feedback: Some(e.feedback),
+                },
+                GraphNode::Tremolo(t) => NodeSpec::Tremolo {
+                    rate: Some(t.rate_hz),
+                    depth: Some(t.depth),
+                },
+                GraphNode::Overdrive(o) => NodeSpec::Overdrive {
+                    drive: Some(o.drive),
+                },
+                GraphNode::Mixer(m) => NodeSpec::Mixer {
+                    gains: m.gains.clone(),
+                },
+                other => {
+                    return Err(PatchError::UnsupportedNode {
+                        index,
+                        kind: other.kind(),
+                    })
+                }
+            });
+            for &to in graph.successors(id) {
+                if graph.edge_gain(id, to) != Some(1.0) {
+                    return Err(PatchError::TrimmedEdge {
+                        from: index,
+                        to: to.as_usize(),
+                    });
+                }
+                edges.push([index, to.as_usize()]);
+            }
+        }
+        Ok(Patch {
+            nodes,
+            edges,
+            channels: graph.channels(),
+        })
+    }
+
+    /// Builds the patch's graph at `sample_rate`. Delay and echo nodes have room for
+    /// [`PATCH_MAX_DELAY_MS`].
+    pub fn to_graph(&self, sample_rate: u32) -> Result<AudioGraph, PatchError> {
+        let mut g = AudioGraph::new();
+        g.set_channels(self.channels);
+        for spec in &self.nodes {
+            g.try_add_node(spec.to_node(sample_rate))
+                .map_err(PatchError::Graph)?;
+        }
+        for &[from, to] in &self.edges {
+            if from >= g.node_count() || to >= g.node_count() {
+                return Err(PatchError::BadEdge { from, to });
+            }
+            g.add_edge(NodeId::new(from), NodeId::new(to));
+        }
+        Ok(g)
+    }
+}
+
+impl NodeSpec {
+    /// The node this spec describes, with any given parameters applied.
+    fn to_node(&self, sample_rate: u32) -> GraphNode {
+        let (mut node, params) = match *self {
+            NodeSpec::Sine { frequency } => (
+                GraphNode::Sine(SineGenerator::new(440.0, sample_rate)),
+                vec![(ParamId::Frequency, frequency)],
+            ),
+            NodeSpec::Noise { color, gain } => (
+                GraphNode::Noise(Noise::new(color.unwrap_or(NoiseKind::White))),
+                vec![(ParamId::Gain, gain)],
+            ),
+            NodeSpec::Gain { gain } => (
+                GraphNode::Gain(GainProcessor::new(1.0)),
+                vec![(ParamId::Gain, gain)],
+            ),
+            NodeSpec::Delay { delay_ms } => (
+                GraphNode::Delay(DelayLine::new(PATCH_MAX_DELAY_MS, sample_rate)),
+                vec![(ParamId::DelayMs, delay_ms)],
+            ),
+            NodeSpec::Echo {
+                delay_ms,
+                dry,
+                wet,
+                feedback,
+            } => (
+                GraphNode::Echo(Echo::new(PATCH_MAX_DELAY_MS, sample_rate)),
+                vec![
+                    (ParamId::DelayMs, delay_ms),
+                    (ParamId::Dry, dry),
+                    (ParamId::Wet, wet),
+                    (ParamId::Feedback, feedback),
+                ],
+            ),
+            NodeSpec::Tremolo { rate, depth } => (
+                GraphNode::Tremolo(Tremolo::new(5.0, sample_rate)),
+                vec![(ParamId::Rate, rate), (ParamId::Depth, depth)],
+            ),
+            NodeSpec::Overdrive { drive } => (
+                GraphNode::Overdrive(Overdrive::new(1.0)),
+                vec![(ParamId::Drive, drive)],
+            ),
+            NodeSpec::Mixer { ref gains } => (GraphNode::Mixer(Mixer::new(gains.clone())), vec![]),
+        };
+        for (id, value) in params {
+            if let Some(value) = value {
+                node.set_param(id, value);
+            }
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NodeSpec, Patch, PatchError};
+    use crate::graph::{AudioGraph, GraphNode};
+    use crate::nodes::{
+        DelayLine, Echo, GainProcessor, Limiter, Mixer, Noise, NoiseKind, Overdrive, SineGenerator,
+        Tremolo,
+    };
+
+    #[test]
+    fn test_graph_patch_graph_round_trip() {
+        let sr = 48_000;
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(220.0, sr)));
+        let mut noise = Noise::new(NoiseKind::Pink);
+        noise.amplitude = 0.1;
+        let noise = g.add_node(GraphNode::Noise(noise));
+        let mix = g.add_node(GraphNode::Mixer(Mixer::new(vec![1.0, 0.5])));
+        let mut delay = DelayLine::new(500.0, sr);
+        delay.set_delay_ms(12.5);
+        let mut echo = Echo::new(1000.0, sr);
+        echo.set_delay_ms(300.0);
+        echo.feedback = 0.4;
+        let mut tremolo = Tremolo::new(3.0, sr);
+        tremolo.depth = 0.25;
+        let mut chain = vec![mix];
+        for node in [
+            GraphNode::Delay(delay),
+            GraphNode::Echo(echo),
+            GraphNode::Tremolo(tremolo),
+            GraphNode::Overdrive(Overdrive::new(2.0)),
+            GraphNode::Gain(GainProcessor::new(0.5)),
+        ] {
+            let id = g.add_node(node);
+            g.add_edge(*chain.last().unwrap(), id);
+            chain.push(id);
+        }
+        g.add_edge(sine, mix);
+        g.add_edge(noise, mix);
+
+        let patch = Patch::from_graph(&g).unwrap();
+        let json = serde_json::to_string(&patch).unwrap();
+        let parsed: Patch = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, patch);
+        let rebuilt = parsed.to_graph(sr).unwrap();
+        assert_eq!(rebuilt.describe(), g.describe());
+        assert_eq!(Patch::from_graph(&rebuilt).unwrap(), patch);
+        assert!(rebuilt.compile(256).is_ok());
+    }
+
+    #[test]
+    fn test_patch_json_uses_kind_tags_and_defaults() {
+        let patch: Patch = serde_json::from_str(
+            r#"{ "nodes": [{ "kind": "sine", "frequency": 220 },
+                           { "kind": "echo", "delay_ms": 300, "feedback": 0.4 },
+                           { "kind": "gain" }],
+                 "edges": [[0, 1], [1, 2]] }"#,
+        )
+        .unwrap();
+        assert_eq!(patch.channels, 1);
+        assert_eq!(patch.nodes[2], NodeSpec::Gain { gain: None });
+        let g = patch.to_graph(48_000).unwrap();
+        let text = g.describe();
+        assert!(text.contains("[2] Gain Gain=1"), "{text}");
+        assert!(
+            text.contains("[1] Echo DelayMs=300 Dry=0.6 Wet=0.4 Feedback=0.4"),
+            "{text}"
+        );
+        assert!(text.contains("[0] Sine Frequency=220"), "{text}");
+
+        let json = serde_json::to_string(&Patch::from_graph(&g).unwrap()).unwrap();
+        assert!(
+            json.starts_with(r#"{"nodes":[{"kind":"sine","frequency":220.0}"#),
+            "{json}"
+        );
+        assert!(!json.contains("channels"), "mono is the default: {json}");
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let parse = |text: &str| serde_json::from_str::<Patch>(text).map_err(|e| e.to_string());
+        assert!(parse(r#"{"nodes": [{"kind": "kazoo"}], "edges": []}"#)
+            .unwrap_err()
+            .contains("unknown variant `kazoo`"));
+        assert!(
+            parse(r#"{"nodes": [{"kind": "sine", "drive": 2}], "edges": []}"#)
+                .unwrap_err()
+                .contains("unknown field `drive`")
+        );
+        assert!(parse(r#"{"nodes": [{"kind": "mixer"}], "edges": []}"#)
+            .unwrap_err()
+            .contains("missing field `gains`"));
+
+        let patch = parse(r#"{"nodes": [{"kind": "sine"}], "edges": [[0, 1]]}"#).unwrap();
+        assert_eq!(
+            patch.to_graph(48_000),
+            Err(PatchError::BadEdge { from: 0, to: 1 })
+        );
+
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let gain = g.add_node(GraphNode::Gain(GainProcessor::new(1.0)));
+        g.add_edge_with_gain(sine, gain, 0.5);
+        assert_eq!(
+            Patch::from_graph(&g),
+            Err(PatchError::TrimmedEdge { from: 0, to: 1 })
+        );
+        let mut g = AudioGraph::new();
+        let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let limiter = g.add_node(GraphNode::Limiter(Limiter::new(1.0, 50.0, 48_000)));
+        g.add_edge(sine, limiter);
+        assert_eq!(
+            Patch::from_graph(&g),
+            Err(PatchError::UnsupportedNode {
+                index: 1,
+                kind: "Limiter"
+            })
+        );
+    }
+}
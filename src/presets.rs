@@ -0,0 +1,156 @@
+//! Built-in graph presets: small named patches that compile to a ready-to-swap [`CompiledGraph`].
+//! Built on the control thread; send the result with `Command::SwapGraph`.
+
+use crate::graph::{AudioGraph, CompiledGraph, GraphBuilder, GraphError, GraphNode};
+use crate::nodes::{BiquadFilter, Echo, GainProcessor, Overdrive, SineGenerator, Tremolo};
+
+/// Names accepted by [`preset_by_name`].
+pub const PRESET_NAMES: &[&str] = &["pad", "bass", "bell"];
+
+/// Why [`build_preset`] could not build a preset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetError {
+    /// No preset has this name; see [`PRESET_NAMES`].
+    UnknownName(String),
+    /// The preset exists but its graph failed to compile.
+    Compile(GraphError),
+}
+
+impl std::fmt::Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresetError::UnknownName(name) => write!(f, "unknown preset '{}'", name),
+            PresetError::Compile(e) => write!(f, "preset failed to compile: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PresetError::Compile(e) => Some(e),
+            PresetError::UnknownName(_) => None,
+        }
+    }
+}
+
+/// Builds the named preset at the given sample rate and block size; `None` for an unknown name
+/// or a graph that fails to compile. [`build_preset`] tells the two apart.
+pub fn preset_by_name(name: &str, sample_rate: u32, frame_count: usize) -> Option<CompiledGraph> {
+    build_preset(name, sample_rate, frame_count).ok()
+}
+
+/// Like [`preset_by_name`], but says why no graph was built.
+pub fn build_preset(
+    name: &str,
+    sample_rate: u32,
+    frame_count: usize,
+) -> Result<CompiledGraph, PresetError> {
+    graph_by_name(name, sample_rate)
+        .ok_or_else(|| PresetError::UnknownName(name.to_string()))?
+        .compile(frame_count)
+        .map_err(PresetError::Compile)
+}
+
+/// Like [`preset_by_name`], but returns the uncompiled graph; `None` for an unknown name.
+pub fn graph_by_name(name: &str, sample_rate: u32) -> Option<AudioGraph> {
+    match name {
+        "pad" => Some(pad(sample_rate)),
+        "bass" => Some(bass(sample_rate)),
+        "bell" => Some(bell(sample_rate)),
+        _ => None,
+    }
+}
+
+/// Slightly detuned A minor triad through a soft lowpass and slow tremolo.
+pub fn pad(sample_rate: u32) -> AudioGraph {
+    let mut tremolo = Tremolo::new(0.3, sample_rate);
+    tremolo.depth = 0.3;
+    sines(&[220.0, 261.63, 329.63, 221.0, 262.4], sample_rate)
+        .then(GraphNode::Biquad(BiquadFilter::lowpass(
+            sample_rate,
+            1_200.0,
+            0.707,
+        )))
+        .then(GraphNode::Tremolo(tremolo))
+        .then(GraphNode::Gain(GainProcessor::new(0.8)))
+        .into_graph()
+}
+
+/// Low A with its octave, overdriven and darkened with a 400 Hz lowpass.
+pub fn bass(sample_rate: u32) -> AudioGraph {
+    sines(&[55.0, 110.0], sample_rate)
+        .then(GraphNode::Overdrive(Overdrive::new(3.0)))
+        .then(GraphNode::Biquad(BiquadFilter::lowpass(
+            sample_rate,
+            400.0,
+            0.9,
+        )))
+        .then(GraphNode::Gain(GainProcessor::new(0.6)))
+        .into_graph()
+}
+
+/// Inharmonic partials (1, 2.76, 5.4 × 440 Hz) into an echo.
+pub fn bell(sample_rate: u32) -> AudioGraph {
+    let mut echo = Echo::new(500.0, sample_rate);
+    echo.set_delay_ms(350.0);
+    echo.feedback = 0.4;
+    sines(&[440.0, 440.0 * 2.76, 440.0 * 5.4], sample_rate)
+        .then(GraphNode::Echo(echo))
+        .then(GraphNode::Gain(GainProcessor::new(0.7)))
+        .into_graph()
+}
+
+/// One sine per frequency, all feeding an equal-gain mixer (the builder's tail).
+fn sines(freqs: &[f32], sample_rate: u32) -> GraphBuilder {
+    let voices = freqs
+        .iter()
+        .map(|&f| GraphBuilder::new().source(GraphNode::Sine(SineGenerator::new(f, sample_rate))))
+        .collect();
+    GraphBuilder::new().mix(voices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_preset, preset_by_name, PresetError, PRESET_NAMES};
+    use crate::graph::{GraphError, NodeId};
+
+    #[test]
+    fn test_presets_compile_and_sound() {
+        for name in PRESET_NAMES {
+            let mut compiled = preset_by_name(name, 48_000, 256).expect("preset compiles");
+            let mut out = vec![0.0f32; 256];
+            let mut peak = 0.0f32;
+            for _ in 0..8 {
+                compiled.process(&mut out);
+                peak = out.iter().fold(peak, |p, s| p.max(s.abs()));
+            }
+            assert!(peak > 0.01, "{name} is silent");
+            assert!(out.iter().all(|s| s.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_unknown_preset_is_none() {
+        assert!(preset_by_name("kazoo", 48_000, 256).is_none());
+    }
+
+    #[test]
+    fn test_unknown_preset_is_an_unknown_name_error() {
+        assert_eq!(
+            build_preset("kazoo", 48_000, 256).err(),
+            Some(PresetError::UnknownName("kazoo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_preset_errors_tell_unknown_names_from_compile_failures() {
+        let unknown = PresetError::UnknownName("kazoo".to_string());
+        let broken = PresetError::Compile(GraphError::MissingInputs(NodeId::new(2)));
+        assert_eq!(unknown.to_string(), "unknown preset 'kazoo'");
+        assert_eq!(
+            broken.to_string(),
+            "preset failed to compile: node 2 has no inputs"
+        );
+    }
+}
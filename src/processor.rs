@@ -1,11 +1,69 @@
 //! Audio node interface. Every source, filter, and processor in the graph implements this trait.
 
 /// Interface for all audio nodes. Implementations must be real-time safe: no allocation, no locks.
-/// `inputs` are the output buffers of predecessor nodes (empty for sources); write to `output`.
+/// `inputs` are the output buffers of predecessor nodes (empty for sources). `process` reads its
+/// signal only from `inputs` and overwrites every sample of `output`; it never reads `output`,
+/// whose previous contents are stale. Sources ignore `inputs`; other nodes treat a missing input
+/// as silence.
 pub trait Processor {
     fn process(&mut self, inputs: &[&[f32]], output: &mut [f32]);
+
+    /// Inputs the node reads when boxed into `GraphNode::Dynamic`; return 0 for a source, so the
+    /// graph accepts it with no incoming edges. Defaults to 1.
+    fn num_inputs(&self) -> usize {
+        1
+    }
+
+    /// Samples of output this node can still produce after its input goes silent (delay time,
+    /// echo decay, reverb length), so an offline render knows how long to keep going.
+    /// 0 for nodes without memory.
+    fn tail_samples(&self) -> usize {
+        0
+    }
+
+    /// Clears the node's signal memory (delay lines, filter history, envelope and follower
+    /// levels, held samples) so it carries nothing over from before; parameters are kept.
+    /// Runs on the audio thread (`Command::Panic`), so it must not allocate. The default does
+    /// nothing, for nodes without memory.
+    fn reset(&mut self) {}
+}
+
+/// A [`Processor`] that can be boxed into `GraphNode::Dynamic`: it can cross to the audio
+/// thread and be cloned when the graph is compiled. Implemented automatically for every
+/// `Processor + Clone + Send + Sync + 'static` type; do not implement by hand.
+pub trait DynProcessor: Processor + Send + Sync {
+    fn clone_box(&self) -> Box<dyn DynProcessor>;
+}
+
+impl<T: Processor + Clone + Send + Sync + 'static> DynProcessor for T {
+    fn clone_box(&self) -> Box<dyn DynProcessor> {
+        Box::new(self.clone())
+    }
 }
 
+impl Clone for Box<dyn DynProcessor> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl std::fmt::Debug for dyn DynProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("dyn Processor")
+    }
+}
+
+/// Boxed processors are opaque: two are equal only if they are the same instance, so a boxed
+/// processor never equals its own clone. Zero-sized processors have no address of their own
+/// (every box of one points at the same dangling address), so they never compare equal, not
+/// even to themselves.
+impl PartialEq for dyn DynProcessor {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::size_of_val(self) != 0 && std::ptr::addr_eq(self, other)
+    }
+}
+
+#[derive(Clone)]
 pub struct Silence;
 
 impl Processor for Silence {
@@ -46,4 +104,22 @@ mod tests {
         silence_processor.process(&[], buffer.as_mut_slice());
         assert_eq!(buffer.len(), buffer_len);
     }
+
+    #[test]
+    /// Test that a processor needs no Clone unless it is boxed into `GraphNode::Dynamic`.
+    fn test_processor_need_not_be_clone() {
+        struct Ramp(f32);
+        impl Processor for Ramp {
+            fn process(&mut self, _inputs: &[&[f32]], output: &mut [f32]) {
+                for sample in output.iter_mut() {
+                    self.0 += 1.0;
+                    *sample = self.0;
+                }
+            }
+        }
+        let mut ramp = Ramp(0.0);
+        let mut out = [0.0f32; 3];
+        ramp.process(&[], &mut out);
+        assert_eq!(out, [1.0, 2.0, 3.0]);
+    }
 }
@@ -0,0 +1,302 @@
+//! Offline rendering ("bounce"): run a compiled graph faster than real time and write the result
+//! to a WAV file. Runs on the control thread; nothing here is real-time safe.
+
+use std::path::Path;
+
+use crate::dither::quantize_tpdf;
+use crate::graph::CompiledGraph;
+use crate::record::{write_wav, RecordError};
+use crate::rng::SmallRng;
+
+/// Sample format of the written WAV file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    /// 32-bit float: lossless, but not every tool reads it.
+    #[default]
+    F32,
+    /// 24-bit integer, TPDF dithered.
+    I24,
+    /// 16-bit integer, TPDF dithered.
+    I16,
+}
+
+impl BitDepth {
+    fn bits(self) -> u16 {
+        match self {
+            BitDepth::F32 => 32,
+            BitDepth::I24 => 24,
+            BitDepth::I16 => 16,
+        }
+    }
+}
+
+/// Post-processing applied by [`render_to_wav`] before the file is written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderOptions {
+    /// Scale the whole render so its peak equals this level (see [`normalize`]).
+    pub normalize: Option<f32>,
+    /// Linear fade-in at the start, in milliseconds (0 = none). See [`apply_fades`].
+    pub fade_in_ms: f32,
+    /// Linear fade-out at the end, in milliseconds (0 = none).
+    pub fade_out_ms: f32,
+    /// Sample format of the output file.
+    pub bit_depth: BitDepth,
+    /// Keep rendering for the graph's [`tail_samples`](CompiledGraph::tail_samples) after the
+    /// requested length, so delay and reverb tails are not cut off.
+    pub tail: bool,
+}
+
+/// Renders `frames` mono samples from `graph`, one block (the graph's frame count) at a time.
+pub fn render(graph: &mut CompiledGraph, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0f32; frames];
+    let block = graph.frame_count().max(1);
+    for chunk in out.chunks_mut(block) {
+        graph.process(chunk);
+    }
+    out
+}
+
+/// Scales `samples` so the absolute peak equals `target_peak`. A silent buffer is left unchanged.
+pub fn normalize(samples: &mut [f32], target_peak: f32) {
+    let peak = samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+    if peak == 0.0 {
+        return;
+    }
+    let scale = target_peak / peak;
+    for s in samples.iter_mut() {
+        *s *= scale;
+    }
+}
+
+/// Applies linear fades so the buffer starts and ends at zero: the first sample is fully
+/// attenuated and gain rises to 1.0 over `fade_in_samples`; mirrored at the end. Each fade is
+/// clamped to the buffer length.
+pub fn apply_fades(samples: &mut [f32], fade_in_samples: usize, fade_out_samples: usize) {
+    let len = samples.len();
+    let fade_in = fade_in_samples.min(len);
+    for (i, s) in samples[..fade_in].iter_mut().enumerate() {
+        *s *= i as f32 / fade_in as f32;
+    }
+    let fade_out = fade_out_samples.min(len);
+    for (i, s) in samples[len - fade_out..].iter_mut().rev().enumerate() {
+        *s *= i as f32 / fade_out as f32;
+    }
+}
+
+/// Writes mono samples to a WAV file at `depth`. Integer depths add triangular (TPDF) dither of
+/// +/- 1 LSB before rounding, and clamp out-of-range samples to full scale.
+pub fn write_wav_with_depth(
+    path: impl AsRef<Path>,
+    samples: &[f32],
+    sample_rate: u32,
+    depth: BitDepth,
+) -> Result<(), RecordError> {
+    if depth == BitDepth::F32 {
+        return write_wav(path, samples, sample_rate);
+    }
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: depth.bits(),
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut rng = SmallRng::default();
+    let mut writer = hound::WavWriter::create(path.as_ref(), spec)?;
+    for &s in samples {
+        writer.write_sample(quantize_tpdf(s, depth.bits(), &mut rng))?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Renders `frames` samples (plus the graph's tail if `options.tail`) from `graph`, applies
+/// `options`, and writes a mono WAV to `path`.
+pub fn render_to_wav(
+    graph: &mut CompiledGraph,
+    sample_rate: u32,
+    frames: usize,
+    path: impl AsRef<Path>,
+    options: &RenderOptions,
+) -> Result<(), RecordError> {
+    let tail = if options.tail {
+        graph.tail_samples()
+    } else {
+        0
+    };
+    let mut samples = render(graph, frames + tail);
+    if let Some(peak) = options.normalize {
+        normalize(&mut samples, peak);
+    }
+    let ms_to_samples = |ms: f32| (ms.max(0.0) / 1000.0 * sample_rate as f32).round() as usize;
+    apply_fades(
+        &mut samples,
+        ms_to_samples(options.fade_in_ms),
+        ms_to_samples(options.fade_out_ms),
+    );
+    write_wav_with_depth(path, &samples, sample_rate, options.bit_depth)
+}
+
+/// Renders exactly `loop_frames` samples for a seamless loop, writes them as a float WAV, and
+/// returns the seam discontinuity: how far the loop's first sample is from where the signal
+/// actually continues (one extra frame is rendered to find out). For a source whose period
+/// divides `loop_frames` this is ~0; a large value means the loop will click.
+pub fn render_loop(
+    graph: &mut CompiledGraph,
+    sample_rate: u32,
+    loop_frames: usize,
+    path: impl AsRef<Path>,
+) -> Result<f32, RecordError> {
+    let mut samples = render(graph, loop_frames + 1);
+    let continuation = samples.pop().unwrap_or(0.0);
+    let discontinuity = samples
+        .first()
+        .map_or(0.0, |&first| (continuation - first).abs());
+    write_wav(path, &samples, sample_rate)?;
+    Ok(discontinuity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_fades, normalize, render, render_loop, render_to_wav, write_wav_with_depth, BitDepth,
+        RenderOptions,
+    };
+    use crate::graph::{AudioGraph, GraphNode};
+    use crate::nodes::SineGenerator;
+
+    #[test]
+    fn test_normalize_scales_to_target_peak() {
+        let mut samples = vec![0.1, -0.25, 0.2, 0.0];
+        normalize(&mut samples, 1.0);
+        assert_eq!(samples, vec![0.4, -1.0, 0.8, 0.0]);
+    }
+
+    #[test]
+    fn test_normalize_leaves_silence_unchanged() {
+        let mut samples = vec![0.0f32; 16];
+        normalize(&mut samples, 1.0);
+        assert!(samples.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_fades_zero_the_edges_and_leave_the_middle() {
+        let mut samples = vec![1.0f32; 100];
+        apply_fades(&mut samples, 10, 20);
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[99], 0.0);
+        assert!((samples[5] - 0.5).abs() < 1e-6);
+        assert!((samples[89] - 0.5).abs() < 1e-6);
+        assert!(samples[10..80].iter().all(|&s| s == 1.0));
+    }
+
+    #[test]
+    fn test_fades_clamp_to_buffer_length() {
+        let mut samples = vec![1.0f32; 8];
+        apply_fades(&mut samples, 100, 100);
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[7], 0.0);
+        assert!(samples
+            .iter()
+            .all(|s| s.is_finite() && (0.0..=1.0).contains(s)));
+    }
+
+    #[test]
+    fn test_i16_export_within_one_lsb() {
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| f32::sin(2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48_000.0))
+            .collect();
+        let path = std::env::temp_dir().join(format!("capstan_i16_{}.wav", std::process::id()));
+        write_wav_with_depth(&path, &samples, 48_000, BitDepth::I16).unwrap();
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Int);
+        let read: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read.len(), samples.len());
+        for (&got, &s) in read.iter().zip(&samples) {
+            let want = (s * 32767.0).round() as i32;
+            assert!((got as i32 - want).abs() <= 1, "got {got}, want {want}");
+        }
+        // Full scale is reached but never wraps around.
+        assert!(read.iter().any(|&s| s >= 32766));
+        assert!(read.iter().any(|&s| s <= -32766));
+    }
+
+    #[test]
+    fn test_int_export_clamps_out_of_range() {
+        let samples = [2.0f32, -2.0, 0.0];
+        let path = std::env::temp_dir().join(format!("capstan_i24_{}.wav", std::process::id()));
+        write_wav_with_depth(&path, &samples, 48_000, BitDepth::I24).unwrap();
+        let read: Vec<i32> = hound::WavReader::open(&path)
+            .unwrap()
+            .samples::<i32>()
+            .map(|s| s.unwrap())
+            .collect();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read[0], (1 << 23) - 1);
+        assert_eq!(read[1], -(1 << 23));
+        assert!(read[2].abs() <= 1);
+    }
+
+    #[test]
+    fn test_render_loop_seam_is_continuous_for_whole_cycles() {
+        let seam = |freq_hz| {
+            let mut g = AudioGraph::new();
+            g.add_node(GraphNode::Sine(SineGenerator::new(freq_hz, 48_000)));
+            let mut compiled = g.compile(256).unwrap();
+            let path = std::env::temp_dir().join(format!(
+                "capstan_loop_{}_{}.wav",
+                freq_hz,
+                std::process::id()
+            ));
+            // 12800 frames = exactly 100 cycles of 375 Hz (period 128 samples).
+            let d = render_loop(&mut compiled, 48_000, 12_800, &path).unwrap();
+            let written = hound::WavReader::open(&path).unwrap().len();
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(written, 12_800);
+            d
+        };
+        assert!(seam(375.0) < 1e-4, "whole cycles: {}", seam(375.0));
+        assert!(seam(440.0) > 0.1, "partial cycle: {}", seam(440.0));
+    }
+
+    #[test]
+    fn test_render_spans_multiple_blocks() {
+        let mut g = AudioGraph::new();
+        g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+        let mut compiled = g.compile(64).unwrap();
+        let out = render(&mut compiled, 200);
+        assert_eq!(out.len(), 200);
+        // The tail past the first block must be rendered too, not zero-filled.
+        assert!(out[128..].iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_render_to_wav_adds_tail_when_asked() {
+        use crate::nodes::DelayLine;
+        let written = |tail| {
+            let mut g = AudioGraph::new();
+            let sine = g.add_node(GraphNode::Sine(SineGenerator::new(440.0, 48_000)));
+            let mut delay = DelayLine::new(10.0, 48_000);
+            delay.set_delay_ms(10.0);
+            let delay = g.add_node(GraphNode::Delay(delay));
+            g.add_edge(sine, delay);
+            let mut compiled = g.compile(64).unwrap();
+            let path = std::env::temp_dir().join(format!(
+                "capstan_tail_{}_{}.wav",
+                tail,
+                std::process::id()
+            ));
+            let options = RenderOptions {
+                tail,
+                ..RenderOptions::default()
+            };
+            render_to_wav(&mut compiled, 48_000, 1000, &path, &options).unwrap();
+            let len = hound::WavReader::open(&path).unwrap().len();
+            let _ = std::fs::remove_file(&path);
+            len
+        };
+        assert_eq!(written(false), 1000);
+        assert_eq!(written(true), 1480);
+    }
+}
@@ -1,5 +1,6 @@
 //! SPSC (single producer, single consumer) ring buffer for lock-free messaging
-//! between the control thread and the audio thread.
+//! between the control thread and the audio thread, and [`ScopeRing`], an overwriting ring of
+//! samples for displays.
 //!
 //! # Safety
 //!
@@ -16,34 +17,75 @@
 //! - **Memory ordering:** `Acquire`/`Release` on the indices synchronize the producer and consumer
 //!   so that the consumer sees the written value after advancing `write_index`, and the producer
 //!   sees the consumer’s read after advancing `read_index`.
+//! - **Interior mutability:** slots are `UnsafeCell`s, so writing through `&self` is allowed;
+//!   the two rules above make sure no slot is accessed by both threads at once.
 
+use std::cell::UnsafeCell;
 use std::mem::MaybeUninit;
 use std::ptr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{fence, AtomicU32, AtomicUsize, Ordering};
+
+/// Assumed cache line size. 64 bytes on x86_64 and most ARM cores; on CPUs with 128-byte lines
+/// the two indices still land on different 64-byte halves, which is where prefetch pairs split.
+const CACHE_LINE: usize = 64;
+
+/// Aligns (and so pads) its contents to a cache line, so the producer's and consumer's indices
+/// never share one and the two threads don't invalidate each other's cache on every operation.
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+// `repr(align)` needs a literal; keep it in step with CACHE_LINE.
+const _: () = assert!(std::mem::align_of::<CachePadded<AtomicUsize>>() == CACHE_LINE);
+
+impl<T> std::ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
 
 /// Lock-free SPSC ring buffer. One thread may call `try_send`; another may call `try_recv`.
 /// No allocation in send/recv; capacity fixed at creation.
 pub struct RingBuffer<T> {
     /// Slots (uninitialized until sent, then read by consumer)
-    storage: Box<[MaybeUninit<T>]>,
+    storage: Box<[UnsafeCell<MaybeUninit<T>>]>,
     /// Capacity (storage.len()). Must be > 0.
     cap: usize,
+    /// `cap - 1` when `cap` is a power of two: slot lookup is then a mask instead of a division.
+    mask: Option<usize>,
     /// Producer index: next slot to write. Consumer never writes this.
-    write_index: AtomicUsize,
+    write_index: CachePadded<AtomicUsize>,
     /// Consumer index: next slot to read. Producer never writes this.
-    read_index: AtomicUsize,
+    read_index: CachePadded<AtomicUsize>,
 }
 
+// SAFETY: values move from the producer thread to the consumer thread (hence `T: Send`), and the
+// SPSC rules in the module docs keep the two threads off each other's slots. (`Send` is automatic.)
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
 impl<T> RingBuffer<T> {
     /// Creates a ring buffer with the given capacity. No allocation after this.
     pub fn new(capacity: usize) -> Self {
         assert!(capacity > 0, "capacity must be > 0");
-        let storage: Box<[MaybeUninit<T>]> = Box::new_uninit_slice(capacity);
+        let storage: Box<[UnsafeCell<MaybeUninit<T>>]> = (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect();
         RingBuffer {
             storage,
             cap: capacity,
-            write_index: AtomicUsize::new(0),
-            read_index: AtomicUsize::new(0),
+            mask: capacity.is_power_of_two().then(|| capacity - 1),
+            write_index: CachePadded(AtomicUsize::new(0)),
+            read_index: CachePadded(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Slot in the ring for a free-running index.
+    #[inline]
+    fn slot(&self, index: usize) -> usize {
+        match self.mask {
+            Some(mask) => index & mask,
+            None => index % self.cap,
         }
     }
 
@@ -58,14 +100,12 @@ impl<T> RingBuffer<T> {
             return Err(value);
         }
 
-        // Compute slot index in the ring.
-        let index = write % self.cap;
+        let index = self.slot(write);
 
         // SAFETY: only the producer thread writes this slot, and we only write
         // when the ring is not full, so we don't overwrite an unread value.
         unsafe {
-            let ptr = self.storage.as_ptr().add(index) as *mut MaybeUninit<T>;
-            ptr::write(ptr, MaybeUninit::new(value));
+            ptr::write(self.storage[index].get(), MaybeUninit::new(value));
         }
 
         self.write_index
@@ -84,14 +124,12 @@ impl<T> RingBuffer<T> {
             return None;
         }
 
-        // Compute slot index in the ring.
-        let index = read % self.cap;
+        let index = self.slot(read);
 
         // SAFETY: only the consumer thread reads this slot, and we only read
         // when the ring is not empty, so we don't read an unwritten value.
         unsafe {
-            let ptr = self.storage.as_ptr().add(index) as *mut MaybeUninit<T>;
-            let value = ptr::read(ptr);
+            let value = ptr::read(self.storage[index].get());
 
             self.read_index
                 .store(read.wrapping_add(1), Ordering::Release);
@@ -99,24 +137,109 @@ impl<T> RingBuffer<T> {
         }
     }
 
-    /// Returns whether the buffer is empty (nothing to recv).
+    /// Returns whether the buffer is empty (nothing to recv). Acquire on the other side's
+    /// index, as in `try_recv`, so a `false` seen by the consumer means the value is visible.
     pub fn is_empty(&self) -> bool {
-        let read = self.read_index.load(Ordering::Relaxed);
-        let write = self.write_index.load(Ordering::Relaxed);
+        let read = self.read_index.load(Ordering::Acquire);
+        let write = self.write_index.load(Ordering::Acquire);
         read == write
     }
 
     /// Returns whether the buffer is full (try_send would fail).
     pub fn is_full(&self) -> bool {
-        let write = self.write_index.load(Ordering::Relaxed);
-        let read = self.read_index.load(Ordering::Relaxed);
+        let write = self.write_index.load(Ordering::Acquire);
+        let read = self.read_index.load(Ordering::Acquire);
         write.wrapping_sub(read) == self.cap
     }
 }
 
+impl<T> Drop for RingBuffer<T> {
+    /// Drops values that were sent but never received (e.g. a graph still in flight at shutdown).
+    fn drop(&mut self) {
+        while self.try_recv().is_some() {}
+    }
+}
+
+/// Single-producer ring of `f32` samples that overwrites the oldest samples when full, for
+/// displays that want the latest audio rather than every sample. The producer never waits and
+/// never fails; a consumer that falls behind skips to the oldest sample still held.
+///
+/// Slots are atomics, so the producer can overwrite a slot while the consumer reads it without a
+/// data race. The consumer notices such a lap (the producer claims an index before writing its
+/// slot) and retries from the oldest sample still held, so it never returns a mixed-up order.
+pub struct ScopeRing {
+    /// Sample bits, `f32::to_bits`.
+    slots: Box<[AtomicU32]>,
+    /// Producer: one past the newest index whose slot write has started.
+    claimed: CachePadded<AtomicUsize>,
+    /// Producer: one past the newest index whose slot is written.
+    published: CachePadded<AtomicUsize>,
+    /// Consumer: next index to read.
+    read_index: CachePadded<AtomicUsize>,
+}
+
+impl ScopeRing {
+    /// Creates a ring holding the latest `capacity` samples. No allocation after this.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be > 0");
+        ScopeRing {
+            slots: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            claimed: CachePadded(AtomicUsize::new(0)),
+            published: CachePadded(AtomicUsize::new(0)),
+            read_index: CachePadded(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of samples the ring holds.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Appends a sample, overwriting the oldest one if the ring is full. Only the producer
+    /// thread may call this.
+    pub fn push(&self, sample: f32) {
+        let write = self.published.load(Ordering::Relaxed);
+        self.claimed.store(write.wrapping_add(1), Ordering::Relaxed);
+        // Orders the claim before the slot write, for the consumer's lap check.
+        fence(Ordering::Release);
+        self.slots[write % self.slots.len()].store(sample.to_bits(), Ordering::Relaxed);
+        self.published
+            .store(write.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Takes the oldest sample still held, or `None` if the consumer has caught up. Only the
+    /// consumer thread may call this.
+    pub fn try_recv(&self) -> Option<f32> {
+        let cap = self.slots.len();
+        let mut read = self.read_index.load(Ordering::Relaxed);
+        loop {
+            let write = self.published.load(Ordering::Acquire);
+            if write.wrapping_sub(read) > cap {
+                // Overwritten while we were away: skip to the oldest sample still held.
+                read = write.wrapping_sub(cap);
+            }
+            if read == write {
+                self.read_index.store(read, Ordering::Relaxed);
+                return None;
+            }
+            let bits = self.slots[read % cap].load(Ordering::Relaxed);
+            // If that load saw a newer sample, the claim for it is visible after this fence.
+            fence(Ordering::Acquire);
+            let claimed = self.claimed.load(Ordering::Relaxed);
+            if claimed.wrapping_sub(read) > cap {
+                read = claimed.wrapping_sub(cap);
+                continue;
+            }
+            self.read_index
+                .store(read.wrapping_add(1), Ordering::Relaxed);
+            return Some(f32::from_bits(bits));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::RingBuffer;
+    use super::{RingBuffer, ScopeRing, CACHE_LINE};
 
     #[test]
     /// Test that sending a value and then receiving it returns the value.
@@ -133,6 +256,20 @@ mod tests {
         assert_eq!(ring_buffer.try_recv(), None);
     }
 
+    #[test]
+    /// Test that a full scope ring keeps the newest samples, in order.
+    fn test_scope_ring_overwrites_oldest() {
+        let ring = ScopeRing::new(4);
+        for i in 0..10 {
+            ring.push(i as f32);
+        }
+        let held: Vec<f32> = std::iter::from_fn(|| ring.try_recv()).collect();
+        assert_eq!(held, [6.0, 7.0, 8.0, 9.0]);
+        ring.push(10.0);
+        assert_eq!(ring.try_recv(), Some(10.0));
+        assert_eq!(ring.try_recv(), None);
+    }
+
     #[test]
     /// Test that sending a value to a full buffer returns Err.
     fn test_full_send_returns_err() {
@@ -152,4 +289,152 @@ mod tests {
         assert_eq!(ring_buffer.try_recv(), Some(2));
         assert_eq!(ring_buffer.try_recv(), Some(3));
     }
+
+    #[test]
+    /// Test that power-of-two (masked) and other (modulo) capacities behave the same, across
+    /// many wrap-arounds and at every fill level.
+    fn test_masked_and_modulo_capacities_wrap_alike() {
+        for cap in [8, 6] {
+            let ring = RingBuffer::new(cap);
+            assert_eq!(ring.mask.is_some(), cap == 8);
+            let mut next_in = 0;
+            let mut next_out = 0;
+            for round in 0..50 {
+                let fill = round % cap + 1;
+                for _ in 0..fill {
+                    ring.try_send(next_in).unwrap();
+                    next_in += 1;
+                }
+                if fill == cap {
+                    assert!(ring.is_full());
+                    assert_eq!(ring.try_send(-1), Err(-1));
+                }
+                for _ in 0..fill {
+                    assert_eq!(ring.try_recv(), Some(next_out));
+                    next_out += 1;
+                }
+                assert!(ring.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    /// Test that a producer and a consumer thread exchange a long sequence of heap-allocated
+    /// values with nothing lost, duplicated or reordered, through a small (often full) ring.
+    fn test_threaded_fifo_integrity() {
+        use std::sync::Arc;
+        use std::thread;
+
+        const COUNT: usize = 200_000;
+        let ring = Arc::new(RingBuffer::<String>::new(16));
+        let producer = {
+            let ring = Arc::clone(&ring);
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    let mut value = i.to_string();
+                    while let Err(back) = ring.try_send(value) {
+                        value = back;
+                        thread::yield_now();
+                    }
+                }
+            })
+        };
+        let mut expected = 0;
+        while expected < COUNT {
+            match ring.try_recv() {
+                Some(value) => {
+                    assert_eq!(value, expected.to_string());
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    /// Test that a consumer racing an overwriting producer only ever sees samples in the order
+    /// they were pushed, skipping the ones it was too slow for, and ends on the newest.
+    fn test_threaded_scope_ring_stays_in_order() {
+        use std::sync::Arc;
+        use std::thread;
+
+        const COUNT: u32 = 200_000;
+        let ring = Arc::new(ScopeRing::new(16));
+        let producer = {
+            let ring = Arc::clone(&ring);
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    ring.push(i as f32);
+                }
+            })
+        };
+        let mut last = -1.0f32;
+        while last < (COUNT - 1) as f32 {
+            match ring.try_recv() {
+                Some(value) => {
+                    assert!(value > last, "{value} after {last}");
+                    last = value;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(ring.try_recv(), None);
+    }
+
+    #[test]
+    /// Test that values still in the ring are dropped with it.
+    fn test_drop_releases_unreceived_values() {
+        use std::sync::Arc;
+
+        let tracker = Arc::new(());
+        let ring = RingBuffer::new(4);
+        ring.try_send(Arc::clone(&tracker)).unwrap();
+        ring.try_send(Arc::clone(&tracker)).unwrap();
+        drop(ring.try_recv());
+        assert_eq!(Arc::strong_count(&tracker), 2);
+        drop(ring);
+        assert_eq!(Arc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    /// Test that the producer and consumer indices sit on different cache lines, and that a
+    /// threaded send/recv loop still completes (correctness is covered by the FIFO tests).
+    fn test_indices_do_not_share_a_cache_line() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let ring = Arc::new(RingBuffer::<u64>::new(1024));
+        let write = &*ring.write_index as *const _ as usize;
+        let read = &*ring.read_index as *const _ as usize;
+        assert_eq!(write % CACHE_LINE, 0);
+        assert_eq!(read % CACHE_LINE, 0);
+        assert!(write.abs_diff(read) >= CACHE_LINE);
+
+        const COUNT: u64 = 200_000;
+        let producer = {
+            let ring = Arc::clone(&ring);
+            thread::spawn(move || {
+                for i in 0..COUNT {
+                    while ring.try_send(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            })
+        };
+        let mut sum = 0u64;
+        let mut received = 0;
+        while received < COUNT {
+            if let Some(v) = ring.try_recv() {
+                sum += v;
+                received += 1;
+            } else {
+                thread::yield_now();
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(sum, COUNT * (COUNT - 1) / 2);
+    }
 }
@@ -0,0 +1,118 @@
+//! Checks that swapping and rendering graphs on the audio thread neither allocates nor frees.
+//! Lives in its own test binary because it installs a counting `#[global_allocator]`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Arc;
+
+use capstan::command::Command;
+use capstan::engine::Engine;
+use capstan::event::{event_channel, Event};
+use capstan::graph::{AudioGraph, CompiledGraph, GraphNode};
+use capstan::input_buffer::FilePlaybackBuffer;
+use capstan::nodes::{InputNode, Mixer};
+
+/// Counts allocations per thread, so the check is not disturbed by other tests running in
+/// parallel.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Allocations and deallocations made by this thread so far.
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// A graph that outputs a constant `level`.
+fn dc_graph(level: f32) -> CompiledGraph {
+    let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![level; 1024])));
+    let mut g = AudioGraph::new();
+    g.add_node(GraphNode::Input(InputNode::new(source)));
+    g.compile(512).unwrap()
+}
+
+/// Two constant sources into a mixer, over trimmed edges: 1.0 * 0.5 * 0.5 + 0.25 * 2.0 * 1.0.
+fn trimmed_mix_graph() -> CompiledGraph {
+    let dc = |level: f32| {
+        let source = Arc::new(FilePlaybackBuffer::new(Arc::new(vec![level; 4096])));
+        GraphNode::Input(InputNode::new(source))
+    };
+    let mut g = AudioGraph::new();
+    let a = g.add_node(dc(1.0));
+    let b = g.add_node(dc(0.25));
+    let mix = g.add_node(GraphNode::Mixer(Mixer::new(vec![0.5, 1.0])));
+    g.add_edge_with_gain(a, mix, 0.5);
+    g.add_edge_with_gain(b, mix, 2.0);
+    g.compile(512).unwrap()
+}
+
+#[test]
+fn test_swap_graph_allocates_nothing() {
+    let (evt_tx, evt_rx) = event_channel(8);
+    let mut engine = Engine::new(48_000, 440.0, 1.0);
+    engine.apply_command(Command::SwapGraph(dc_graph(1.0)), &evt_tx);
+
+    let swap = Command::SwapGraph(dc_graph(0.5));
+    let before = allocations();
+    engine.apply_command(swap, &evt_tx);
+    assert_eq!(allocations(), before, "swap allocates or frees nothing");
+
+    let mut out = vec![0.0f32; 512];
+    engine.render_block(&mut out);
+    assert!(out.iter().all(|&s| s == 0.5), "the new graph plays");
+    assert!(matches!(evt_rx.try_recv(), Some(Event::GraphSwapped(_))));
+}
+
+#[test]
+fn test_rendering_a_trimmed_mix_allocates_nothing() {
+    let (evt_tx, _evt_rx) = event_channel(8);
+    let mut engine = Engine::new(48_000, 440.0, 1.0);
+    engine.apply_command(Command::SwapGraph(trimmed_mix_graph()), &evt_tx);
+    let mut out = vec![0.0f32; 512];
+
+    let before = allocations();
+    for _ in 0..4 {
+        engine.render_block(&mut out);
+    }
+    assert_eq!(
+        allocations(),
+        before,
+        "rendering allocates or frees nothing"
+    );
+    assert!(
+        out.iter().all(|&s| (s - 0.75).abs() < 1e-6),
+        "trims and gains apply"
+    );
+}
+
+#[test]
+fn test_swaps_with_a_full_event_queue_free_nothing() {
+    let (evt_tx, evt_rx) = event_channel(2);
+    let mut engine = Engine::new(48_000, 440.0, 1.0);
+    while evt_tx.try_send(Event::NoOp).is_ok() {}
+    let swaps = [dc_graph(1.0), dc_graph(0.5), dc_graph(0.25)].map(Command::SwapGraph);
+
+    let before = allocations();
+    for swap in swaps {
+        engine.apply_command(swap, &evt_tx);
+    }
+    assert_eq!(allocations(), before, "replaced graphs are kept, not freed");
+    while evt_rx.try_recv().is_some() {}
+}